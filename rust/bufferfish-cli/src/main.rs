@@ -0,0 +1,163 @@
+//! The `bufferfish` command line tool, wrapping the TypeScript compiler and
+//! schema tooling from `bufferfish::compiler`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bufferfish::{
+    compat,
+    compiler::{self, schema::Schema},
+};
+use clap::{Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(name = "bufferfish", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate TypeScript definitions, encoders and decoders.
+    Gen {
+        /// Directory of Rust source files to read.
+        #[arg(long, default_value = "src/")]
+        src: String,
+        /// TypeScript file to write.
+        #[arg(long)]
+        out: PathBuf,
+        /// Fail if `out` is not up to date, rather than writing it.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Export the protocol schema as JSON.
+    Schema {
+        /// Directory of Rust source files to read.
+        #[arg(long, default_value = "src/")]
+        src: String,
+        /// JSON file to write. Prints to stdout if not given.
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// Fail if `out` is not up to date, rather than writing it.
+        #[arg(long, requires = "out")]
+        check: bool,
+    },
+    /// Check the protocol for wire-breaking changes against a baseline schema.
+    Check {
+        /// Directory of Rust source files to read.
+        #[arg(long, default_value = "src/")]
+        src: String,
+        /// JSON schema previously written by `bufferfish schema`.
+        #[arg(long)]
+        baseline: PathBuf,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    match command {
+        Command::Gen { src, out, check } => {
+            if !report_diagnostics(&src)? {
+                return Ok(ExitCode::FAILURE);
+            }
+
+            let output = compiler::generate_to_string(&src)?;
+            write_output(&out, &output, check)
+        }
+        Command::Schema { src, out, check } => {
+            if !report_diagnostics(&src)? {
+                return Ok(ExitCode::FAILURE);
+            }
+
+            let json = compiler::schema(&src)?.to_json()?;
+            match out {
+                Some(out) => write_output(&out, &json, check),
+                None => {
+                    println!("{json}");
+                    Ok(ExitCode::SUCCESS)
+                }
+            }
+        }
+        Command::Check { src, baseline } => {
+            if !report_diagnostics(&src)? {
+                return Ok(ExitCode::FAILURE);
+            }
+
+            let baseline = fs::read_to_string(&baseline)
+                .map_err(|e| format!("failed to read {}: {e}", baseline.display()))?;
+            let old = Schema::from_json(&baseline)?;
+            let new = compiler::schema(&src)?;
+
+            let incompatibilities = compat::check(&old, &new);
+            for incompatibility in &incompatibilities {
+                eprintln!("error: {incompatibility}");
+            }
+
+            if incompatibilities.is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                eprintln!(
+                    "found {} wire-breaking change(s) against the baseline",
+                    incompatibilities.len()
+                );
+                Ok(ExitCode::FAILURE)
+            }
+        }
+    }
+}
+
+/// Prints any diagnostics for the source files at `src`, returning false if
+/// there were any.
+fn report_diagnostics(src: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let diagnostics = compiler::diagnose(src)?;
+    for diagnostic in &diagnostics {
+        eprintln!("error: {diagnostic}");
+    }
+
+    Ok(diagnostics.is_empty())
+}
+
+/// Writes `content` to `out`, or with `check` set, fails if `out` does not
+/// already contain exactly `content`.
+fn write_output(
+    out: &Path,
+    content: &str,
+    check: bool,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    if check {
+        if fs::read_to_string(out).ok().as_deref() == Some(content) {
+            return Ok(ExitCode::SUCCESS);
+        }
+
+        eprintln!(
+            "error: {} is out of date; run without `--check` to regenerate it",
+            out.display()
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+
+    if let Some(parent) = out.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(out, content)?;
+
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,166 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const PROTOCOL: &str = r#"
+use bufferfish::Encode;
+
+#[derive(Encode)]
+#[repr(u16)]
+pub enum MessageId {
+    Ping = 0,
+}
+
+#[derive(Encode)]
+#[bufferfish(MessageId::Ping)]
+pub struct Ping {
+    pub seq: u32,
+}
+"#;
+
+/// Creates an empty scratch directory for a single test.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bufferfish-cli-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("src")).unwrap();
+
+    dir
+}
+
+fn write_source(dir: &Path, source: &str) {
+    fs::write(dir.join("src/lib.rs"), source).unwrap();
+}
+
+fn bufferfish(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bufferfish"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn gen_writes_output_and_check_passes() {
+    let dir = scratch_dir("gen");
+    write_source(&dir, PROTOCOL);
+
+    let output = bufferfish(&dir, &["gen", "--out", "client/protocol.ts"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let generated = fs::read_to_string(dir.join("client/protocol.ts")).unwrap();
+    assert!(generated.starts_with(
+        "/* AUTOGENERATED BUFFERFISH FILE, DO NOT EDIT */\nimport { Bufferfish } from 'bufferfish'\n"
+    ));
+    assert!(generated.contains("export interface Ping"));
+
+    let output = bufferfish(&dir, &["gen", "--out", "client/protocol.ts", "--check"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn gen_check_fails_when_stale() {
+    let dir = scratch_dir("stale");
+    write_source(&dir, PROTOCOL);
+    fs::write(dir.join("protocol.ts"), "// old\n").unwrap();
+
+    let output = bufferfish(&dir, &["gen", "--out", "protocol.ts", "--check"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("protocol.ts is out of date"));
+    assert_eq!(
+        fs::read_to_string(dir.join("protocol.ts")).unwrap(),
+        "// old\n"
+    );
+}
+
+#[test]
+fn gen_reports_unsupported_types() {
+    let dir = scratch_dir("unsupported");
+    write_source(
+        &dir,
+        r#"
+use bufferfish::Encode;
+
+#[derive(Encode)]
+pub struct Player {
+    pub id: u32,
+    pub callback: Box<dyn Fn(u32)>,
+}
+"#,
+    );
+
+    let output = bufferfish(&dir, &["gen", "--out", "protocol.ts"]);
+    assert!(!output.status.success());
+
+    let stderr = stderr(&output);
+    assert!(stderr.contains("lib.rs:7:"), "{stderr}");
+    assert!(stderr.contains("`Player`"), "{stderr}");
+    assert!(!dir.join("protocol.ts").exists());
+}
+
+#[test]
+fn gen_reports_types_it_cannot_generate() {
+    let dir = scratch_dir("type_paths");
+    write_source(
+        &dir,
+        r#"
+use bufferfish::Encode;
+
+#[derive(Encode)]
+pub struct Point {
+    pub x: u16,
+}
+
+#[derive(Encode)]
+pub struct Route {
+    pub stops: Vec<crate::Point>,
+}
+"#,
+    );
+
+    let output = bufferfish(&dir, &["gen", "--out", "protocol.ts"]);
+    assert!(!output.status.success());
+
+    let stderr = stderr(&output);
+    assert!(stderr.contains("lib.rs:11:"), "{stderr}");
+    assert!(
+        stderr.contains("unsupported type `crate::Point`"),
+        "{stderr}"
+    );
+    assert!(!dir.join("protocol.ts").exists());
+}
+
+#[test]
+fn schema_prints_json() {
+    let dir = scratch_dir("schema");
+    write_source(&dir, PROTOCOL);
+
+    let output = bufferfish(&dir, &["schema"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"name\": \"Ping\""));
+}
+
+#[test]
+fn check_detects_breaking_changes() {
+    let dir = scratch_dir("check");
+    write_source(&dir, PROTOCOL);
+
+    let output = bufferfish(&dir, &["schema", "--out", "schema.json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = bufferfish(&dir, &["check", "--baseline", "schema.json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    write_source(&dir, &PROTOCOL.replace("seq: u32", "seq: u16"));
+
+    let output = bufferfish(&dir, &["check", "--baseline", "schema.json"]);
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("1 wire-breaking change"));
+}
@@ -0,0 +1,231 @@
+//! Annotated dumps of a `Bufferfish` decoded against a type, mapping each
+//! byte range to the field it was read into. See `Bufferfish::annotate`.
+
+use std::fmt::Display;
+
+use crate::{Bufferfish, BufferfishError, Decodable};
+
+/// A single field (or header) read while annotating a buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationEntry {
+    /// The offset of the first byte read for this entry.
+    pub start: usize,
+    /// The offset after the last byte read for this entry, or `None` if
+    /// decoding failed within it.
+    pub end: Option<usize>,
+    /// How deeply this entry is nested within other entries.
+    pub depth: usize,
+    /// The field name, eg. `id`, `0` or `[2]`.
+    pub name: String,
+    /// The type the entry was decoded as, if known.
+    pub type_name: Option<String>,
+    /// The decoded value, for primitive types.
+    pub value: Option<String>,
+}
+
+/// Where and why decoding stopped while annotating a buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationError {
+    /// The offset of the start of the innermost field that failed to decode.
+    pub offset: usize,
+    /// The path to the field that failed to decode, eg. `players[2].name`.
+    pub path: String,
+    /// The error message.
+    pub message: String,
+}
+
+/// An annotated dump of a buffer, produced by `Bufferfish::annotate`.
+///
+/// The `Display` implementation prints one line per entry, eg.
+/// `[2..6] id: u32 = 1`, followed by where decoding stopped on error.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Annotation {
+    entries: Vec<AnnotationEntry>,
+    open: Vec<usize>,
+    error: Option<AnnotationError>,
+    len: usize,
+    end: usize,
+}
+
+impl Annotation {
+    /// Returns every entry read, in the order they appear in the buffer.
+    pub fn entries(&self) -> &[AnnotationEntry] {
+        &self.entries
+    }
+
+    /// Returns where and why decoding stopped, if it failed.
+    pub fn error(&self) -> Option<&AnnotationError> {
+        self.error.as_ref()
+    }
+
+    /// Returns the number of bytes left unread after a successful decode.
+    pub fn trailing_bytes(&self) -> usize {
+        if self.error.is_some() {
+            return 0;
+        }
+
+        self.len.saturating_sub(self.end)
+    }
+
+    /// Records a field read by `decode`, covering every byte it reads.
+    /// Entries recorded within `decode` are nested under this one.
+    ///
+    /// This is called by the `Decode` derive, and is rarely needed directly.
+    pub fn field<T>(
+        &mut self,
+        bf: &mut Bufferfish,
+        name: &str,
+        type_name: Option<&str>,
+        decode: impl FnOnce(&mut Bufferfish, &mut Annotation) -> Result<T, BufferfishError>,
+    ) -> Result<T, BufferfishError> {
+        let start = bf.position();
+        let index = self.entries.len();
+
+        self.entries.push(AnnotationEntry {
+            start,
+            end: None,
+            depth: self.open.len(),
+            name: name.to_string(),
+            type_name: type_name.map(str::to_string),
+            value: None,
+        });
+        self.open.push(index);
+
+        let result = decode(bf, self);
+
+        match &result {
+            Ok(_) => self.entries[index].end = Some(bf.position()),
+            Err(e) if self.error.is_none() => {
+                self.error = Some(AnnotationError {
+                    offset: start,
+                    path: self.path(),
+                    message: e.to_string(),
+                });
+            }
+            Err(_) => {}
+        }
+
+        self.open.pop();
+
+        result
+    }
+
+    /// Sets the displayed value of the innermost field being read.
+    pub fn value(&mut self, value: impl Display) {
+        if let Some(&index) = self.open.last() {
+            self.entries[index].value = Some(value.to_string());
+        }
+    }
+
+    /// Returns the path to the innermost field being read.
+    fn path(&self) -> String {
+        let mut path = String::new();
+
+        for &index in &self.open {
+            let name = &self.entries[index].name;
+            if !path.is_empty() && !name.starts_with('[') {
+                path.push('.');
+            }
+
+            path.push_str(name);
+        }
+
+        path
+    }
+}
+
+impl Display for Annotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for entry in &self.entries {
+            write!(
+                f,
+                "{:indent$}[{}..",
+                "",
+                entry.start,
+                indent = entry.depth * 2
+            )?;
+            if let Some(end) = entry.end {
+                write!(f, "{end}")?;
+            }
+
+            write!(f, "] {}", entry.name)?;
+            if let Some(type_name) = &entry.type_name {
+                write!(f, ": {type_name}")?;
+            }
+
+            if let Some(value) = &entry.value {
+                write!(f, " = {value}")?;
+            }
+
+            writeln!(f)?;
+        }
+
+        if let Some(error) = &self.error {
+            write!(f, "decoding stopped at byte {}", error.offset)?;
+            if !error.path.is_empty() {
+                write!(f, " in `{}`", error.path)?;
+            }
+
+            writeln!(f, ": {}", error.message)?;
+        } else if self.trailing_bytes() > 0 {
+            writeln!(
+                f,
+                "[{}..{}] {} trailing byte(s) not read",
+                self.end,
+                self.len,
+                self.trailing_bytes()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns the name of `T` with module paths removed, eg. `Vec<Player>`
+/// rather than `alloc::vec::Vec<my_game::Player>`.
+pub fn type_name<T: ?Sized>() -> String {
+    let full = std::any::type_name::<T>();
+    let mut name = String::with_capacity(full.len());
+    let mut segment = String::new();
+
+    let mut chars = full.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            name.push_str(&segment);
+            segment.clear();
+            name.push(c);
+        }
+    }
+
+    name.push_str(&segment);
+
+    name
+}
+
+/// Decodes a `T` from `bf`, which must be at the start of its buffer,
+/// annotating each field read.
+pub(crate) fn annotate<T: Decodable>(bf: &mut Bufferfish) -> Annotation {
+    let mut annotation = Annotation {
+        len: bf.len(),
+        ..Default::default()
+    };
+
+    match T::annotate(bf, &mut annotation) {
+        Ok(_) => annotation.end = bf.position(),
+        Err(e) if annotation.error.is_none() => {
+            annotation.error = Some(AnnotationError {
+                offset: 0,
+                path: String::new(),
+                message: e.to_string(),
+            });
+        }
+        Err(_) => {}
+    }
+
+    annotation
+}
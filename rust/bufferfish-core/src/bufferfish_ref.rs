@@ -0,0 +1,365 @@
+//! A borrowed, read-only view over an encoded byte slice. Values decoded through
+//! a `BufferfishRef` may borrow directly from the underlying bytes.
+
+use crate::{
+    Bufferfish, BufferfishError, DecodableRef, DecodeLimits, LengthPrefix, limits::DecodeState,
+    varint,
+};
+
+/// A read-only cursor over a borrowed `&[u8]`.
+///
+/// Unlike `Bufferfish`, this never copies the input. Strings and byte slices
+/// read from it point directly into the original slice, which makes it
+/// suitable for decoding inbound frames without allocating.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferfishRef<'a> {
+    inner: &'a [u8],
+    position: usize,
+    length_prefix: LengthPrefix,
+    pub(crate) decode_state: DecodeState,
+}
+
+impl<'a> BufferfishRef<'a> {
+    /// Creates a new `BufferfishRef` reading from the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            inner: bytes,
+            position: 0,
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
+        }
+    }
+
+    /// Returns the total length (bytes) of the underlying slice.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns true if the underlying slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Returns the current cursor position.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of bytes left to read.
+    pub fn remaining(&self) -> usize {
+        self.inner.len() - self.position
+    }
+
+    /// Returns the underlying slice.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.inner
+    }
+
+    /// Set how string and collection lengths are read from this buffer. See
+    /// `Bufferfish::set_length_prefix`.
+    pub fn set_length_prefix(&mut self, prefix: LengthPrefix) {
+        self.length_prefix = prefix;
+    }
+
+    /// Returns how string and collection lengths are read from this buffer.
+    pub fn length_prefix(&self) -> LengthPrefix {
+        self.length_prefix
+    }
+
+    /// Decodes with `f` using `prefix` for string and collection lengths,
+    /// restoring the previous prefix afterwards.
+    ///
+    /// This is called by the `DecodeRef` derive for fields marked
+    /// `#[bufferfish(length_prefix = "..")]`, and is rarely needed directly.
+    pub fn with_length_prefix<T>(
+        &mut self,
+        prefix: LengthPrefix,
+        f: impl FnOnce(&mut Self) -> Result<T, BufferfishError>,
+    ) -> Result<T, BufferfishError> {
+        let previous = std::mem::replace(&mut self.length_prefix, prefix);
+        let result = f(self);
+        self.length_prefix = previous;
+
+        result
+    }
+
+    /// Set the limits enforced when decoding from this buffer. See
+    /// `DecodeLimits`.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_state.limits = limits;
+    }
+
+    /// Returns the limits enforced when decoding from this buffer.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        self.decode_state.limits
+    }
+
+    /// Decodes a nested value with `decode`, enforcing the max nesting depth
+    /// of the buffer's `DecodeLimits`.
+    ///
+    /// This is called by the `DecodeRef` derive and collection impls, and is
+    /// rarely needed directly.
+    pub fn decode_nested<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T, BufferfishError>,
+    ) -> Result<T, BufferfishError> {
+        self.decode_state.enter()?;
+        let result = decode(self);
+        self.decode_state.exit();
+
+        result
+    }
+
+    /// Returns the next byte in the buffer without advancing the cursor.
+    /// Returns a `Result` if the cursor is at the end of the buffer.
+    pub fn peek(&self) -> Result<u8, BufferfishError> {
+        let Some(byte) = self.inner.get(self.position) else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: 1,
+            });
+        };
+
+        Ok(*byte)
+    }
+
+    /// Returns the next n-bytes in the buffer without advancing the cursor.
+    /// Returns a Result if the cursor is at the end of the buffer.
+    pub fn peek_n(&self, n: usize) -> Result<&'a [u8], BufferfishError> {
+        let Some(bytes) = self.inner[self.position..].get(..n) else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: n,
+            });
+        };
+
+        Ok(bytes)
+    }
+
+    /// Advances the cursor by `n` bytes and returns the slice that was
+    /// skipped over.
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BufferfishError> {
+        let Some(bytes) = self.inner[self.position..].get(..n) else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: n,
+            });
+        };
+
+        self.position += n;
+
+        Ok(bytes)
+    }
+
+    /// Reads exactly `N` bytes into a fixed-size array.
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], BufferfishError> {
+        let mut bf = [0u8; N];
+        bf.copy_from_slice(self.take(N)?);
+
+        Ok(bf)
+    }
+
+    /// Reads a varint for an integer of `bits`.
+    fn take_varint(&mut self, bits: u32) -> Result<u64, BufferfishError> {
+        let Some((len, value)) = varint::decode(&self.inner[self.position..], bits)? else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: self.remaining() + 1,
+            });
+        };
+
+        self.position += len;
+
+        Ok(value)
+    }
+
+    /// Reads a u8 from the buffer.
+    pub fn read_u8(&mut self) -> Result<u8, BufferfishError> {
+        Ok(self.take_array::<1>()?[0])
+    }
+
+    /// Reads a u16 from the buffer.
+    pub fn read_u16(&mut self) -> Result<u16, BufferfishError> {
+        Ok(u16::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads a u32 from the buffer.
+    pub fn read_u32(&mut self) -> Result<u32, BufferfishError> {
+        Ok(u32::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads a u64 from the buffer.
+    pub fn read_u64(&mut self) -> Result<u64, BufferfishError> {
+        Ok(u64::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads a u128 from the buffer.
+    pub fn read_u128(&mut self) -> Result<u128, BufferfishError> {
+        Ok(u128::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an i8 from the buffer.
+    pub fn read_i8(&mut self) -> Result<i8, BufferfishError> {
+        Ok(i8::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an i16 from the buffer.
+    pub fn read_i16(&mut self) -> Result<i16, BufferfishError> {
+        Ok(i16::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an i32 from the buffer.
+    pub fn read_i32(&mut self) -> Result<i32, BufferfishError> {
+        Ok(i32::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an i64 from the buffer.
+    pub fn read_i64(&mut self) -> Result<i64, BufferfishError> {
+        Ok(i64::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an i128 from the buffer.
+    pub fn read_i128(&mut self) -> Result<i128, BufferfishError> {
+        Ok(i128::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an f32 from the buffer.
+    pub fn read_f32(&mut self) -> Result<f32, BufferfishError> {
+        Ok(f32::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads an f64 from the buffer.
+    pub fn read_f64(&mut self) -> Result<f64, BufferfishError> {
+        Ok(f64::from_be_bytes(self.take_array()?))
+    }
+
+    /// Reads a bool from the buffer.
+    pub fn read_bool(&mut self) -> Result<bool, BufferfishError> {
+        let value = self.read_u8()?;
+
+        Ok(value != 0)
+    }
+
+    /// Reads a string or collection length using the buffer's length prefix.
+    pub fn read_len(&mut self) -> Result<usize, BufferfishError> {
+        match self.length_prefix {
+            LengthPrefix::U16 => Ok(self.read_u16()? as usize),
+            LengthPrefix::U32 => Ok(self.read_u32()? as usize),
+            LengthPrefix::Varint => Ok(self.read_varint_u32()? as usize),
+        }
+    }
+
+    /// Reads a varint u16 from the buffer.
+    pub fn read_varint_u16(&mut self) -> Result<u16, BufferfishError> {
+        Ok(self.take_varint(u16::BITS)? as u16)
+    }
+
+    /// Reads a varint u32 from the buffer.
+    pub fn read_varint_u32(&mut self) -> Result<u32, BufferfishError> {
+        Ok(self.take_varint(u32::BITS)? as u32)
+    }
+
+    /// Reads a varint u64 from the buffer.
+    pub fn read_varint_u64(&mut self) -> Result<u64, BufferfishError> {
+        self.take_varint(u64::BITS)
+    }
+
+    /// Reads a zigzag encoded varint i16 from the buffer.
+    pub fn read_varint_i16(&mut self) -> Result<i16, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i16::BITS)?) as i16)
+    }
+
+    /// Reads a zigzag encoded varint i32 from the buffer.
+    pub fn read_varint_i32(&mut self) -> Result<i32, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i32::BITS)?) as i32)
+    }
+
+    /// Reads a zigzag encoded varint i64 from the buffer.
+    pub fn read_varint_i64(&mut self) -> Result<i64, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i64::BITS)?))
+    }
+
+    /// Attempts to read a packed array of booleans from the buffer.
+    /// You must specify the number of booleans to read.
+    pub fn read_packed_bools(&mut self, count: u8) -> Result<Vec<bool>, BufferfishError> {
+        // Only 8 booleans fit in the single packed byte.
+        if count > 8 {
+            return Err(BufferfishError::InsufficientBytes {
+                available: 1,
+                required: (count as usize).div_ceil(8),
+            });
+        }
+
+        let packed = self.read_u8()?;
+        let mut bools = Vec::with_capacity(count as usize);
+
+        for i in 0..count {
+            bools.push(packed & (1 << (7 - i)) != 0);
+        }
+
+        Ok(bools)
+    }
+
+    /// Reads a variable length string from the buffer, borrowing it from the
+    /// underlying slice.
+    pub fn read_str(&mut self) -> Result<&'a str, BufferfishError> {
+        let len = self.read_len()?;
+        self.decode_state.string(len)?;
+        let slice = self.take(len)?;
+
+        std::str::from_utf8(slice).map_err(BufferfishError::InvalidUtf8)
+    }
+
+    /// Reads a variable length string from the buffer into an owned `String`.
+    pub fn read_string(&mut self) -> Result<String, BufferfishError> {
+        let s = self.read_str()?;
+        self.decode_state.allocate(s.len())?;
+
+        Ok(s.to_owned())
+    }
+
+    /// Reads a length-prefixed run of bytes from the buffer, borrowing it
+    /// from the underlying slice. This matches the encoding of a `Vec<u8>`.
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], BufferfishError> {
+        let len = self.read_len()?;
+        self.decode_state.collection(len, 0)?;
+
+        self.take(len)
+    }
+
+    /// Reads `n` raw bytes from the buffer, borrowing them from the underlying
+    /// slice.
+    pub fn read_raw_bytes(&mut self, n: usize) -> Result<&'a [u8], BufferfishError> {
+        self.take(n)
+    }
+
+    /// Reads an array from the buffer, where the items implement the
+    /// DecodableRef trait.
+    pub fn read_array<T: DecodableRef<'a>>(&mut self) -> Result<Vec<T>, BufferfishError> {
+        Vec::<T>::decode_value_ref(self)
+    }
+}
+
+impl<'a> From<&'a [u8]> for BufferfishRef<'a> {
+    fn from(slice: &'a [u8]) -> Self {
+        Self::new(slice)
+    }
+}
+
+impl<'a> From<&'a Vec<u8>> for BufferfishRef<'a> {
+    fn from(vec: &'a Vec<u8>) -> Self {
+        Self::new(vec.as_slice())
+    }
+}
+
+impl<'a> From<&'a Bufferfish> for BufferfishRef<'a> {
+    fn from(bf: &'a Bufferfish) -> Self {
+        Self::new(bf.as_bytes())
+    }
+}
+
+impl AsRef<[u8]> for BufferfishRef<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.inner
+    }
+}
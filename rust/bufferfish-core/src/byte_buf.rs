@@ -0,0 +1,141 @@
+//! A byte blob type, written with a single copy rather than byte by byte.
+
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    Annotation, Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+};
+
+/// An owned run of bytes, encoded the same way as a `Vec<u8>`, but copied to
+/// and from the buffer in one go instead of through `encode_value` and
+/// `decode_value` for every byte.
+///
+/// Use this over `Vec<u8>` for binary payloads such as images or compressed
+/// data. The generated TypeScript reads it as a `Uint8Array`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ByteBuf(Vec<u8>);
+
+impl ByteBuf {
+    /// Creates an empty `ByteBuf`.
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Returns the inner `Vec<u8>`.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl Deref for ByteBuf {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.0
+    }
+}
+
+impl DerefMut for ByteBuf {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.0
+    }
+}
+
+impl AsRef<[u8]> for ByteBuf {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ByteBuf {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for ByteBuf {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl From<ByteBuf> for Vec<u8> {
+    fn from(bytes: ByteBuf) -> Self {
+        bytes.0
+    }
+}
+
+impl Encodable for ByteBuf {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_bytes(self)
+    }
+}
+
+impl Decodable for ByteBuf {
+    fn decode_value(bf: &mut Bufferfish) -> Result<ByteBuf, BufferfishError> {
+        bf.read_bytes().map(ByteBuf)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<ByteBuf, BufferfishError> {
+        let value = bf.read_bytes()?;
+        annotation.value(format_args!("{value:?}"));
+
+        Ok(ByteBuf(value))
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        Some(u16::MAX as usize + 2)
+    }
+}
+
+impl<'de> DecodableRef<'de> for ByteBuf {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<ByteBuf, BufferfishError> {
+        let bytes = bf.read_bytes()?;
+        bf.decode_state.allocate(bytes.len())?;
+
+        Ok(ByteBuf(bytes.to_vec()))
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Encodable for bytes::Bytes {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_bytes(self)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Decodable for bytes::Bytes {
+    fn decode_value(bf: &mut Bufferfish) -> Result<bytes::Bytes, BufferfishError> {
+        bf.read_bytes().map(bytes::Bytes::from)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<bytes::Bytes, BufferfishError> {
+        ByteBuf::annotate_value(bf, annotation).map(|bytes| bytes::Bytes::from(bytes.0))
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        ByteBuf::min_bytes_required()
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        ByteBuf::max_bytes_allowed()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'de> DecodableRef<'de> for bytes::Bytes {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<bytes::Bytes, BufferfishError> {
+        ByteBuf::decode_value_ref(bf).map(|bytes| bytes::Bytes::from(bytes.0))
+    }
+}
@@ -0,0 +1,133 @@
+//! A `tokio_util::codec` implementation for reading and writing length-prefixed
+//! messages over async streams.
+
+use std::marker::PhantomData;
+
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    Bufferfish, BufferfishError, Decodable, DecodeLimits, Encodable,
+    frame::{DEFAULT_MAX_FRAME_SIZE, LengthPrefix},
+};
+
+/// Encodes and decodes messages of type `T` as length-prefixed frames, using
+/// the same wire format as `FrameEncoder` and `FrameDecoder`.
+///
+/// Wrap any `AsyncRead + AsyncWrite` (eg. a `TcpStream`) in a
+/// `tokio_util::codec::Framed` to get a `Stream` and `Sink` of `T`.
+pub struct BufferfishCodec<T> {
+    prefix: LengthPrefix,
+    inner_prefix: LengthPrefix,
+    max_frame_size: usize,
+    decode_limits: DecodeLimits,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> BufferfishCodec<T> {
+    /// Creates a new `BufferfishCodec` with a default max frame size (1024
+    /// bytes).
+    pub fn new(prefix: LengthPrefix) -> Self {
+        Self {
+            prefix,
+            inner_prefix: LengthPrefix::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            decode_limits: DecodeLimits::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the max frame size (in bytes).
+    /// A value of 0 will allow frames up to the limit of the length prefix.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// Set the limits enforced when decoding each message. See
+    /// `DecodeLimits`.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_limits = limits;
+    }
+
+    /// Set the length prefix of strings and collections within each message,
+    /// as with `Bufferfish::set_length_prefix`. Defaults to
+    /// `LengthPrefix::U16`. Both sides must use the same prefix.
+    pub fn set_inner_length_prefix(&mut self, prefix: LengthPrefix) {
+        self.inner_prefix = prefix;
+    }
+}
+
+impl<T> Default for BufferfishCodec<T> {
+    fn default() -> Self {
+        Self::new(LengthPrefix::default())
+    }
+}
+
+impl<T> Clone for BufferfishCodec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            prefix: self.prefix,
+            inner_prefix: self.inner_prefix,
+            max_frame_size: self.max_frame_size,
+            decode_limits: self.decode_limits,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for BufferfishCodec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferfishCodec")
+            .field("prefix", &self.prefix)
+            .field("inner_prefix", &self.inner_prefix)
+            .field("max_frame_size", &self.max_frame_size)
+            .field("decode_limits", &self.decode_limits)
+            .finish()
+    }
+}
+
+impl<T: Decodable> Decoder for BufferfishCodec<T> {
+    type Item = T;
+    type Error = BufferfishError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<T>, BufferfishError> {
+        let Some((header_len, frame_len)) = self.prefix.read(src)? else {
+            return Ok(None);
+        };
+
+        self.prefix.check_len(frame_len, self.max_frame_size)?;
+
+        if src.len() < header_len + frame_len {
+            src.reserve(header_len + frame_len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(header_len);
+        let frame = src.split_to(frame_len);
+
+        let mut bf = Bufferfish::from(&frame[..]);
+        bf.set_length_prefix(self.inner_prefix);
+        bf.set_decode_limits(self.decode_limits);
+
+        T::decode(&mut bf).map(Some)
+    }
+}
+
+impl<T: Encodable> Encoder<T> for BufferfishCodec<T> {
+    type Error = BufferfishError;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), BufferfishError> {
+        let mut bf = Bufferfish::with_capacity(0);
+        bf.set_length_prefix(self.inner_prefix);
+        item.encode(&mut bf)?;
+
+        let frame = bf.as_bytes();
+        self.prefix.check_len(frame.len(), self.max_frame_size)?;
+
+        dst.reserve(frame.len() + 5);
+        self.prefix.write(frame.len(), dst);
+        dst.extend_from_slice(frame);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,14 @@
 //! Base trait for types that can be decoded from a `Bufferfish`. Implements decoding for primitive types.
 
-use crate::{Bufferfish, BufferfishError};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{Annotation, Bufferfish, BufferfishError, LengthPrefix, annotate};
 
 /// Types implementing this trait are able to be decoded from a `Bufferfish`.
 pub trait Decodable: Sized {
@@ -16,11 +24,36 @@ pub trait Decodable: Sized {
     /// Instead, use `decode` to decode an entire type.
     fn decode_value(bf: &mut Bufferfish) -> Result<Self, BufferfishError>;
 
+    /// Decodes the type like `decode`, recording the byte range and value
+    /// of each field read in `annotation`. See `Bufferfish::annotate`.
+    ///
+    /// The default implementation decodes the type without annotating it.
+    fn annotate(
+        bf: &mut Bufferfish,
+        _annotation: &mut Annotation,
+    ) -> Result<Self, BufferfishError> {
+        Self::decode(bf)
+    }
+
+    /// Decodes a raw value like `decode_value`, recording the byte range and
+    /// value of each field read in `annotation`.
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        _annotation: &mut Annotation,
+    ) -> Result<Self, BufferfishError> {
+        Self::decode_value(bf)
+    }
+
     /// Creates a checked, generic type from a `Bufferfish`.
     ///
     /// If the `Bufferfish` does not contain enough bytes to properly
-    /// decode the type, an error is returned.
+    /// decode the type, an error is returned. The size is only checked when
+    /// the buffer uses the default length prefix, as the bounds assume it.
     fn from_bufferfish(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+        if bf.length_prefix() != LengthPrefix::U16 {
+            return Self::decode(bf);
+        }
+
         if let Some(min) = Self::min_bytes_required()
             && bf.len() < min
         {
@@ -42,17 +75,26 @@ pub trait Decodable: Sized {
         Self::decode(bf)
     }
 
-    /// Get the minimum number of bytes required to decode this type.
-    /// Returns None if the size can't be determined statically.
+    /// Get the minimum number of bytes required to decode this type, with the
+    /// default `u16` length prefix. Returns None if the size can't be
+    /// determined statically.
     fn min_bytes_required() -> Option<usize> {
         None
     }
 
-    /// Get the maximum number of bytes this type can occupy.
-    /// Returns None if the size can't be determined statically.
+    /// Get the maximum number of bytes this type can occupy, with the default
+    /// `u16` length prefix. Returns None if the size can't be determined
+    /// statically.
     fn max_bytes_allowed() -> Option<usize> {
         None
     }
+
+    /// Get the number of header bytes (eg. a message ID) read by `decode`
+    /// before the value itself. These are included in `min_bytes_required`
+    /// and `max_bytes_allowed`, but not read when decoding a nested value.
+    fn header_size() -> usize {
+        0
+    }
 }
 
 impl Decodable for u8 {
@@ -60,6 +102,16 @@ impl Decodable for u8 {
         bf.read_u8()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<u8, BufferfishError> {
+        let value = bf.read_u8()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(1)
     }
@@ -74,6 +126,16 @@ impl Decodable for u16 {
         bf.read_u16()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<u16, BufferfishError> {
+        let value = bf.read_u16()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(2)
     }
@@ -88,6 +150,16 @@ impl Decodable for u32 {
         bf.read_u32()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<u32, BufferfishError> {
+        let value = bf.read_u32()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(4)
     }
@@ -102,6 +174,16 @@ impl Decodable for u64 {
         bf.read_u64()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<u64, BufferfishError> {
+        let value = bf.read_u64()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(8)
     }
@@ -116,6 +198,16 @@ impl Decodable for u128 {
         bf.read_u128()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<u128, BufferfishError> {
+        let value = bf.read_u128()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(16)
     }
@@ -130,6 +222,16 @@ impl Decodable for i8 {
         bf.read_i8()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<i8, BufferfishError> {
+        let value = bf.read_i8()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(1)
     }
@@ -144,6 +246,16 @@ impl Decodable for i16 {
         bf.read_i16()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<i16, BufferfishError> {
+        let value = bf.read_i16()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(2)
     }
@@ -158,6 +270,16 @@ impl Decodable for i32 {
         bf.read_i32()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<i32, BufferfishError> {
+        let value = bf.read_i32()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(4)
     }
@@ -172,6 +294,16 @@ impl Decodable for i64 {
         bf.read_i64()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<i64, BufferfishError> {
+        let value = bf.read_i64()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(8)
     }
@@ -186,6 +318,16 @@ impl Decodable for i128 {
         bf.read_i128()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<i128, BufferfishError> {
+        let value = bf.read_i128()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(16)
     }
@@ -195,11 +337,69 @@ impl Decodable for i128 {
     }
 }
 
+impl Decodable for f32 {
+    fn decode_value(bf: &mut Bufferfish) -> Result<f32, BufferfishError> {
+        bf.read_f32()
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<f32, BufferfishError> {
+        let value = bf.read_f32()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(4)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        Some(4)
+    }
+}
+
+impl Decodable for f64 {
+    fn decode_value(bf: &mut Bufferfish) -> Result<f64, BufferfishError> {
+        bf.read_f64()
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<f64, BufferfishError> {
+        let value = bf.read_f64()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(8)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        Some(8)
+    }
+}
+
 impl Decodable for bool {
     fn decode_value(bf: &mut Bufferfish) -> Result<bool, BufferfishError> {
         bf.read_bool()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<bool, BufferfishError> {
+        let value = bf.read_bool()?;
+        annotation.value(value);
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(1)
     }
@@ -214,6 +414,16 @@ impl Decodable for String {
         bf.read_string()
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<String, BufferfishError> {
+        let value = bf.read_string()?;
+        annotation.value(format_args!("{value:?}"));
+
+        Ok(value)
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(2)
     }
@@ -223,27 +433,349 @@ impl Decodable for String {
     }
 }
 
+/// Always decoded as `Cow::Owned`. Use `DecodableRef` to borrow the string.
+impl Decodable for Cow<'_, str> {
+    fn decode_value(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+        String::decode_value(bf).map(Cow::Owned)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<Self, BufferfishError> {
+        String::annotate_value(bf, annotation).map(Cow::Owned)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        String::min_bytes_required()
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        String::max_bytes_allowed()
+    }
+}
+
 impl<T: Decodable> Decodable for Vec<T> {
     fn decode_value(bf: &mut Bufferfish) -> Result<Vec<T>, BufferfishError> {
-        let len = bf.read_u16()? as usize;
-        let mut vec = Vec::with_capacity(len);
+        bf.decode_nested(|bf| {
+            let len = bf.read_len()?;
+            bf.decode_state.collection(len, size_of::<T>())?;
+
+            // Every item takes at least one byte, unless it has no fields on
+            // the wire, so a length larger than the remaining bytes can't be
+            // trusted for preallocation.
+            let mut vec = Vec::with_capacity(len.min(bf.remaining()));
+
+            for i in 0..len {
+                let offset = bf.position();
+                vec.push(T::decode_value(bf).map_err(|e| {
+                    e.in_field(&annotate::type_name::<Vec<T>>(), &format!("[{i}]"), offset)
+                })?);
+            }
+
+            Ok(vec)
+        })
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<Vec<T>, BufferfishError> {
+        bf.decode_nested(|bf| {
+            let len = annotate_len(bf, annotation)?;
+            bf.decode_state.collection(len, size_of::<T>())?;
+
+            let mut vec = Vec::with_capacity(len.min(bf.remaining()));
+            let type_name = annotate::type_name::<T>();
+
+            for i in 0..len {
+                vec.push(annotation.field(
+                    bf,
+                    &format!("[{i}]"),
+                    Some(&type_name),
+                    T::annotate_value,
+                )?);
+            }
+
+            Ok(vec)
+        })
+    }
 
-        for _ in 0..len {
-            vec.push(T::decode_value(bf)?);
-        }
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_collection_bytes(max_value_bytes::<T>())
+    }
+}
+
+/// Always decoded as `Cow::Owned`.
+impl<T: Decodable + Clone> Decodable for Cow<'_, [T]> {
+    fn decode_value(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+        Vec::decode_value(bf).map(Cow::Owned)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<Self, BufferfishError> {
+        Vec::annotate_value(bf, annotation).map(Cow::Owned)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Vec::<T>::min_bytes_required()
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        Vec::<T>::max_bytes_allowed()
+    }
+}
+
+impl<T: Decodable, const N: usize> Decodable for [T; N] {
+    fn decode_value(bf: &mut Bufferfish) -> Result<[T; N], BufferfishError> {
+        bf.decode_nested(|bf| {
+            let mut items = Vec::with_capacity(N);
 
-        Ok(vec)
+            for i in 0..N {
+                let offset = bf.position();
+                items.push(T::decode_value(bf).map_err(|e| {
+                    e.in_field(&annotate::type_name::<[T; N]>(), &format!("[{i}]"), offset)
+                })?);
+            }
+
+            Ok(into_array(items))
+        })
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<[T; N], BufferfishError> {
+        bf.decode_nested(|bf| {
+            let mut items = Vec::with_capacity(N);
+            let type_name = annotate::type_name::<T>();
+
+            for i in 0..N {
+                items.push(annotation.field(
+                    bf,
+                    &format!("[{i}]"),
+                    Some(&type_name),
+                    T::annotate_value,
+                )?);
+            }
+
+            Ok(into_array(items))
+        })
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        T::min_bytes_required().map(|min| N.saturating_mul(min.saturating_sub(T::header_size())))
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_value_bytes::<T>()?.checked_mul(N)
+    }
+}
+
+impl<T: Decodable> Decodable for VecDeque<T> {
+    fn decode_value(bf: &mut Bufferfish) -> Result<VecDeque<T>, BufferfishError> {
+        decode_items(bf, T::decode_value)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<VecDeque<T>, BufferfishError> {
+        annotate_items(bf, annotation, T::annotate_value)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_collection_bytes(max_value_bytes::<T>())
+    }
+}
+
+/// Duplicate items are not rejected, and are only kept once.
+impl<T, S> Decodable for HashSet<T, S>
+where
+    T: Decodable + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn decode_value(bf: &mut Bufferfish) -> Result<HashSet<T, S>, BufferfishError> {
+        decode_items(bf, T::decode_value)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<HashSet<T, S>, BufferfishError> {
+        annotate_items(bf, annotation, T::annotate_value)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_collection_bytes(max_value_bytes::<T>())
+    }
+}
+
+/// Duplicate items are not rejected, and are only kept once.
+impl<T: Decodable + Ord> Decodable for BTreeSet<T> {
+    fn decode_value(bf: &mut Bufferfish) -> Result<BTreeSet<T>, BufferfishError> {
+        decode_items(bf, T::decode_value)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<BTreeSet<T>, BufferfishError> {
+        annotate_items(bf, annotation, T::annotate_value)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_collection_bytes(max_value_bytes::<T>())
+    }
+}
+
+/// Duplicate keys are not rejected, and the last value read for a key is
+/// kept.
+impl<K, V, S> Decodable for HashMap<K, V, S>
+where
+    K: Decodable + Eq + Hash,
+    V: Decodable,
+    S: BuildHasher + Default,
+{
+    fn decode_value(bf: &mut Bufferfish) -> Result<HashMap<K, V, S>, BufferfishError> {
+        decode_items(bf, decode_entry)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<HashMap<K, V, S>, BufferfishError> {
+        annotate_items(bf, annotation, annotate_entry)
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(2)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        max_collection_bytes(max_entry_bytes::<K, V>())
+    }
+}
+
+/// Duplicate keys are not rejected, and the last value read for a key is
+/// kept.
+impl<K: Decodable + Ord, V: Decodable> Decodable for BTreeMap<K, V> {
+    fn decode_value(bf: &mut Bufferfish) -> Result<BTreeMap<K, V>, BufferfishError> {
+        decode_items(bf, decode_entry)
+    }
+
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<BTreeMap<K, V>, BufferfishError> {
+        annotate_items(bf, annotation, annotate_entry)
     }
 
     fn min_bytes_required() -> Option<usize> {
-        T::min_bytes_required().map(|min_t_size| 2 + (u16::MAX as usize * min_t_size))
+        Some(2)
     }
 
     fn max_bytes_allowed() -> Option<usize> {
-        T::max_bytes_allowed().map(|max_t_size| 2 + (u16::MAX as usize * max_t_size))
+        max_collection_bytes(max_entry_bytes::<K, V>())
     }
 }
 
+/// Reads nothing.
+impl Decodable for () {
+    fn decode_value(_bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        Ok(())
+    }
+
+    fn min_bytes_required() -> Option<usize> {
+        Some(0)
+    }
+
+    fn max_bytes_allowed() -> Option<usize> {
+        Some(0)
+    }
+}
+
+/// Implements `Decodable` for a tuple, reading each element in order. The
+/// size bounds are the sum of the elements' bounds.
+macro_rules! impl_decodable_tuple {
+    ($($idx:tt $name:ident),+) => {
+        impl<$($name: Decodable),+> Decodable for ($($name,)+) {
+            fn decode_value(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+                bf.decode_nested(|bf| {
+                    Ok(($(
+                        {
+                            let offset = bf.position();
+                            $name::decode_value(bf).map_err(|e| {
+                                e.in_field(&annotate::type_name::<Self>(), stringify!($idx), offset)
+                            })?
+                        },
+                    )+))
+                })
+            }
+
+            fn annotate_value(
+                bf: &mut Bufferfish,
+                annotation: &mut Annotation,
+            ) -> Result<Self, BufferfishError> {
+                bf.decode_nested(|bf| {
+                    Ok(($(
+                        annotation.field(
+                            bf,
+                            stringify!($idx),
+                            Some(&annotate::type_name::<$name>()),
+                            $name::annotate_value,
+                        )?,
+                    )+))
+                })
+            }
+
+            fn min_bytes_required() -> Option<usize> {
+                Some(0usize $(
+                    .saturating_add($name::min_bytes_required()?.saturating_sub($name::header_size()))
+                )+)
+            }
+
+            fn max_bytes_allowed() -> Option<usize> {
+                let mut max = 0usize;
+                $(max = max.checked_add(max_value_bytes::<$name>()?)?;)+
+
+                Some(max)
+            }
+        }
+    };
+}
+
+impl_decodable_tuple!(0 A);
+impl_decodable_tuple!(0 A, 1 B);
+impl_decodable_tuple!(0 A, 1 B, 2 C);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_decodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+
 impl<T: Decodable> Decodable for Option<T> {
     fn decode_value(bf: &mut Bufferfish) -> Result<Option<T>, BufferfishError> {
         let flag = bf.read_u8()?;
@@ -254,11 +786,184 @@ impl<T: Decodable> Decodable for Option<T> {
         }
     }
 
+    fn annotate_value(
+        bf: &mut Bufferfish,
+        annotation: &mut Annotation,
+    ) -> Result<Option<T>, BufferfishError> {
+        let flag = annotation.field(bf, "flag", Some("u8"), u8::annotate_value)?;
+        match flag {
+            0 => Ok(None),
+            1 => Ok(Some(annotation.field(
+                bf,
+                "value",
+                Some(&annotate::type_name::<T>()),
+                T::annotate_value,
+            )?)),
+            _ => Err(BufferfishError::InvalidEnumVariant),
+        }
+    }
+
     fn min_bytes_required() -> Option<usize> {
         Some(1)
     }
 
     fn max_bytes_allowed() -> Option<usize> {
-        T::max_bytes_allowed().map(|max_t_size| 1 + max_t_size)
+        T::max_bytes_allowed().map(|max_t_size| 1 + max_t_size.saturating_sub(T::header_size()))
+    }
+}
+
+/// Implements `Decodable` for a smart pointer, read exactly as the value it
+/// points to, including any header.
+///
+/// No size bounds are given, as the pointer may be what makes a recursive type
+/// possible, and the bounds of the value would then never be resolved.
+macro_rules! impl_decodable_pointer {
+    ($($pointer:ident),+) => {
+        $(
+            impl<T: Decodable> Decodable for $pointer<T> {
+                fn decode(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+                    T::decode(bf).map($pointer::new)
+                }
+
+                fn decode_value(bf: &mut Bufferfish) -> Result<Self, BufferfishError> {
+                    T::decode_value(bf).map($pointer::new)
+                }
+
+                fn annotate(
+                    bf: &mut Bufferfish,
+                    annotation: &mut Annotation,
+                ) -> Result<Self, BufferfishError> {
+                    T::annotate(bf, annotation).map($pointer::new)
+                }
+
+                fn annotate_value(
+                    bf: &mut Bufferfish,
+                    annotation: &mut Annotation,
+                ) -> Result<Self, BufferfishError> {
+                    T::annotate_value(bf, annotation).map($pointer::new)
+                }
+
+                fn header_size() -> usize {
+                    T::header_size()
+                }
+            }
+        )+
+    };
+}
+
+impl_decodable_pointer!(Box, Rc, Arc);
+
+/// Converts the `N` items decoded for an array into the array.
+pub(crate) fn into_array<T, const N: usize>(items: Vec<T>) -> [T; N] {
+    match items.try_into() {
+        Ok(array) => array,
+        Err(_) => unreachable!("exactly {N} items are decoded for an array"),
     }
 }
+
+/// Reads a collection length using the buffer's length prefix, recording it
+/// in `annotation`.
+fn annotate_len(
+    bf: &mut Bufferfish,
+    annotation: &mut Annotation,
+) -> Result<usize, BufferfishError> {
+    let prefix = bf.length_prefix().to_string();
+
+    annotation.field(bf, "len", Some(&prefix), |bf, annotation| {
+        let len = bf.read_len()?;
+        annotation.value(len);
+
+        Ok(len)
+    })
+}
+
+/// Decodes a collection written as a length followed by each of its items,
+/// reading each item with `decode_item`. Errors are returned with the index
+/// of the item that failed.
+fn decode_items<C, T>(
+    bf: &mut Bufferfish,
+    decode_item: impl Fn(&mut Bufferfish) -> Result<T, BufferfishError>,
+) -> Result<C, BufferfishError>
+where
+    C: FromIterator<T>,
+{
+    bf.decode_nested(|bf| {
+        let len = bf.read_len()?;
+        bf.decode_state.collection(len, size_of::<T>())?;
+
+        (0..len)
+            .map(|i| {
+                let offset = bf.position();
+                decode_item(bf)
+                    .map_err(|e| e.in_field(&annotate::type_name::<C>(), &format!("[{i}]"), offset))
+            })
+            .collect()
+    })
+}
+
+/// Decodes a collection like `decode_items`, recording its length and each
+/// item in `annotation`.
+fn annotate_items<C, T>(
+    bf: &mut Bufferfish,
+    annotation: &mut Annotation,
+    annotate_item: impl Fn(&mut Bufferfish, &mut Annotation) -> Result<T, BufferfishError>,
+) -> Result<C, BufferfishError>
+where
+    C: FromIterator<T>,
+{
+    bf.decode_nested(|bf| {
+        let len = annotate_len(bf, annotation)?;
+        bf.decode_state.collection(len, size_of::<T>())?;
+
+        let type_name = annotate::type_name::<T>();
+
+        (0..len)
+            .map(|i| annotation.field(bf, &format!("[{i}]"), Some(&type_name), &annotate_item))
+            .collect()
+    })
+}
+
+/// Decodes a map entry, written as its key followed by its value.
+fn decode_entry<K: Decodable, V: Decodable>(
+    bf: &mut Bufferfish,
+) -> Result<(K, V), BufferfishError> {
+    Ok((K::decode_value(bf)?, V::decode_value(bf)?))
+}
+
+/// Decodes a map entry like `decode_entry`, recording the key and value in
+/// `annotation`.
+fn annotate_entry<K: Decodable, V: Decodable>(
+    bf: &mut Bufferfish,
+    annotation: &mut Annotation,
+) -> Result<(K, V), BufferfishError> {
+    let key = annotation.field(
+        bf,
+        "key",
+        Some(&annotate::type_name::<K>()),
+        K::annotate_value,
+    )?;
+    let value = annotation.field(
+        bf,
+        "value",
+        Some(&annotate::type_name::<V>()),
+        V::annotate_value,
+    )?;
+
+    Ok((key, value))
+}
+
+/// The most bytes `T` can occupy when nested, without its header.
+fn max_value_bytes<T: Decodable>() -> Option<usize> {
+    T::max_bytes_allowed().map(|max| max.saturating_sub(T::header_size()))
+}
+
+/// The most bytes a map entry can occupy.
+fn max_entry_bytes<K: Decodable, V: Decodable>() -> Option<usize> {
+    max_value_bytes::<K>()?.checked_add(max_value_bytes::<V>()?)
+}
+
+/// The most bytes a collection of items taking up to `item_size` bytes can
+/// occupy, with the default `u16` length prefix. `None` if that overflows.
+fn max_collection_bytes(item_size: Option<usize>) -> Option<usize> {
+    (u16::MAX as usize).checked_mul(item_size?)?.checked_add(2)
+}
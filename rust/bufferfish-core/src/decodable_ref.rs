@@ -0,0 +1,328 @@
+//! Trait for types that can be decoded from a borrowed `BufferfishRef`. Implements decoding for primitive types, as well as borrowed `&str` and `&[u8]`.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    hash::{BuildHasher, Hash},
+    rc::Rc,
+    sync::Arc,
+};
+
+use crate::{BufferfishError, BufferfishRef, annotate, decodable::into_array};
+
+/// Types implementing this trait are able to be decoded from a `BufferfishRef`
+/// without copying, borrowing from the input for the lifetime `'de`.
+pub trait DecodableRef<'de>: Sized {
+    /// Decode the type from a given `BufferfishRef`.
+    fn decode_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError> {
+        Self::decode_value_ref(bf)
+    }
+
+    /// Decodes a raw value from a given `BufferfishRef`.
+    ///
+    /// Note: This is generally not what you want to call on types
+    /// implementing `DecodableRef`, as it will not decode the header value.
+    /// Instead, use `decode_ref` to decode an entire type.
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError>;
+
+    /// Decodes the type from a borrowed byte slice.
+    fn from_bytes(bytes: &'de [u8]) -> Result<Self, BufferfishError> {
+        Self::decode_ref(&mut BufferfishRef::new(bytes))
+    }
+}
+
+impl<'de> DecodableRef<'de> for u8 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<u8, BufferfishError> {
+        bf.read_u8()
+    }
+}
+
+impl<'de> DecodableRef<'de> for u16 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<u16, BufferfishError> {
+        bf.read_u16()
+    }
+}
+
+impl<'de> DecodableRef<'de> for u32 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<u32, BufferfishError> {
+        bf.read_u32()
+    }
+}
+
+impl<'de> DecodableRef<'de> for u64 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<u64, BufferfishError> {
+        bf.read_u64()
+    }
+}
+
+impl<'de> DecodableRef<'de> for u128 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<u128, BufferfishError> {
+        bf.read_u128()
+    }
+}
+
+impl<'de> DecodableRef<'de> for i8 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<i8, BufferfishError> {
+        bf.read_i8()
+    }
+}
+
+impl<'de> DecodableRef<'de> for i16 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<i16, BufferfishError> {
+        bf.read_i16()
+    }
+}
+
+impl<'de> DecodableRef<'de> for i32 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<i32, BufferfishError> {
+        bf.read_i32()
+    }
+}
+
+impl<'de> DecodableRef<'de> for i64 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<i64, BufferfishError> {
+        bf.read_i64()
+    }
+}
+
+impl<'de> DecodableRef<'de> for i128 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<i128, BufferfishError> {
+        bf.read_i128()
+    }
+}
+
+impl<'de> DecodableRef<'de> for f32 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<f32, BufferfishError> {
+        bf.read_f32()
+    }
+}
+
+impl<'de> DecodableRef<'de> for f64 {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<f64, BufferfishError> {
+        bf.read_f64()
+    }
+}
+
+impl<'de> DecodableRef<'de> for bool {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<bool, BufferfishError> {
+        bf.read_bool()
+    }
+}
+
+impl<'de> DecodableRef<'de> for String {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<String, BufferfishError> {
+        bf.read_string()
+    }
+}
+
+impl<'de: 'a, 'a> DecodableRef<'de> for &'a str {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<&'a str, BufferfishError> {
+        bf.read_str()
+    }
+}
+
+/// Always decoded as `Cow::Borrowed`.
+impl<'de: 'a, 'a> DecodableRef<'de> for Cow<'a, str> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Cow<'a, str>, BufferfishError> {
+        bf.read_str().map(Cow::Borrowed)
+    }
+}
+
+impl<'de: 'a, 'a> DecodableRef<'de> for &'a [u8] {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<&'a [u8], BufferfishError> {
+        bf.read_bytes()
+    }
+}
+
+impl<'de, T: DecodableRef<'de>> DecodableRef<'de> for Vec<T> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Vec<T>, BufferfishError> {
+        bf.decode_nested(|bf| {
+            let len = bf.read_len()?;
+            bf.decode_state.collection(len, size_of::<T>())?;
+
+            // See `Vec<T>::decode_value` in `Decodable`.
+            let mut vec = Vec::with_capacity(len.min(bf.remaining()));
+
+            for i in 0..len {
+                let offset = bf.position();
+                vec.push(T::decode_value_ref(bf).map_err(|e| {
+                    e.in_field(&annotate::type_name::<Vec<T>>(), &format!("[{i}]"), offset)
+                })?);
+            }
+
+            Ok(vec)
+        })
+    }
+}
+
+/// Always decoded as `Cow::Owned`, as items other than bytes can't be
+/// borrowed. Use `&[u8]` to borrow bytes.
+impl<'de, T: DecodableRef<'de> + Clone> DecodableRef<'de> for Cow<'_, [T]> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError> {
+        Vec::decode_value_ref(bf).map(Cow::Owned)
+    }
+}
+
+impl<'de, T: DecodableRef<'de>, const N: usize> DecodableRef<'de> for [T; N] {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<[T; N], BufferfishError> {
+        bf.decode_nested(|bf| {
+            let mut items = Vec::with_capacity(N);
+
+            for i in 0..N {
+                let offset = bf.position();
+                items.push(T::decode_value_ref(bf).map_err(|e| {
+                    e.in_field(&annotate::type_name::<[T; N]>(), &format!("[{i}]"), offset)
+                })?);
+            }
+
+            Ok(into_array(items))
+        })
+    }
+}
+
+impl<'de, T: DecodableRef<'de>> DecodableRef<'de> for VecDeque<T> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<VecDeque<T>, BufferfishError> {
+        decode_items_ref(bf, T::decode_value_ref)
+    }
+}
+
+/// See `HashSet<T, S>` in `Decodable`.
+impl<'de, T, S> DecodableRef<'de> for HashSet<T, S>
+where
+    T: DecodableRef<'de> + Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<HashSet<T, S>, BufferfishError> {
+        decode_items_ref(bf, T::decode_value_ref)
+    }
+}
+
+/// See `BTreeSet<T>` in `Decodable`.
+impl<'de, T: DecodableRef<'de> + Ord> DecodableRef<'de> for BTreeSet<T> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<BTreeSet<T>, BufferfishError> {
+        decode_items_ref(bf, T::decode_value_ref)
+    }
+}
+
+/// See `HashMap<K, V, S>` in `Decodable`.
+impl<'de, K, V, S> DecodableRef<'de> for HashMap<K, V, S>
+where
+    K: DecodableRef<'de> + Eq + Hash,
+    V: DecodableRef<'de>,
+    S: BuildHasher + Default,
+{
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<HashMap<K, V, S>, BufferfishError> {
+        decode_items_ref(bf, decode_entry_ref)
+    }
+}
+
+/// See `BTreeMap<K, V>` in `Decodable`.
+impl<'de, K, V> DecodableRef<'de> for BTreeMap<K, V>
+where
+    K: DecodableRef<'de> + Ord,
+    V: DecodableRef<'de>,
+{
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<BTreeMap<K, V>, BufferfishError> {
+        decode_items_ref(bf, decode_entry_ref)
+    }
+}
+
+impl<'de> DecodableRef<'de> for () {
+    fn decode_value_ref(_bf: &mut BufferfishRef<'de>) -> Result<(), BufferfishError> {
+        Ok(())
+    }
+}
+
+/// Implements `DecodableRef` for a tuple, reading each element in order.
+macro_rules! impl_decodable_ref_tuple {
+    ($($idx:tt $name:ident),+) => {
+        impl<'de, $($name: DecodableRef<'de>),+> DecodableRef<'de> for ($($name,)+) {
+            fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError> {
+                bf.decode_nested(|bf| {
+                    Ok(($(
+                        {
+                            let offset = bf.position();
+                            $name::decode_value_ref(bf).map_err(|e| {
+                                e.in_field(&annotate::type_name::<Self>(), stringify!($idx), offset)
+                            })?
+                        },
+                    )+))
+                })
+            }
+        }
+    };
+}
+
+impl_decodable_ref_tuple!(0 A);
+impl_decodable_ref_tuple!(0 A, 1 B);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_decodable_ref_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+
+/// Implements `DecodableRef` for a smart pointer, read exactly as the value
+/// it points to, including any header.
+macro_rules! impl_decodable_ref_pointer {
+    ($($pointer:ident),+) => {
+        $(
+            impl<'de, T: DecodableRef<'de>> DecodableRef<'de> for $pointer<T> {
+                fn decode_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError> {
+                    T::decode_ref(bf).map($pointer::new)
+                }
+
+                fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Self, BufferfishError> {
+                    T::decode_value_ref(bf).map($pointer::new)
+                }
+            }
+        )+
+    };
+}
+
+impl_decodable_ref_pointer!(Box, Rc, Arc);
+
+impl<'de, T: DecodableRef<'de>> DecodableRef<'de> for Option<T> {
+    fn decode_value_ref(bf: &mut BufferfishRef<'de>) -> Result<Option<T>, BufferfishError> {
+        let flag = bf.read_u8()?;
+        match flag {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode_value_ref(bf)?)),
+            _ => Err(BufferfishError::InvalidEnumVariant),
+        }
+    }
+}
+
+/// Decodes a collection written as a length followed by each of its items.
+/// See `decode_items` in `Decodable`.
+fn decode_items_ref<'de, C, T>(
+    bf: &mut BufferfishRef<'de>,
+    decode_item: impl Fn(&mut BufferfishRef<'de>) -> Result<T, BufferfishError>,
+) -> Result<C, BufferfishError>
+where
+    C: FromIterator<T>,
+{
+    bf.decode_nested(|bf| {
+        let len = bf.read_len()?;
+        bf.decode_state.collection(len, size_of::<T>())?;
+
+        (0..len)
+            .map(|i| {
+                let offset = bf.position();
+                decode_item(bf)
+                    .map_err(|e| e.in_field(&annotate::type_name::<C>(), &format!("[{i}]"), offset))
+            })
+            .collect()
+    })
+}
+
+/// Decodes a map entry, written as its key followed by its value.
+fn decode_entry_ref<'de, K: DecodableRef<'de>, V: DecodableRef<'de>>(
+    bf: &mut BufferfishRef<'de>,
+) -> Result<(K, V), BufferfishError> {
+    Ok((K::decode_value_ref(bf)?, V::decode_value_ref(bf)?))
+}
@@ -1,5 +1,12 @@
 //! Types implementing this trait are able to be encoded to a `Bufferfish`. Implements encoding for primitive types.
 
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    rc::Rc,
+    sync::Arc,
+};
+
 use crate::{Bufferfish, BufferfishError};
 
 /// Types implementing this trait are able to be encoded to a `Bufferfish`.
@@ -88,6 +95,18 @@ impl Encodable for i128 {
     }
 }
 
+impl Encodable for f32 {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_f32(*self)
+    }
+}
+
+impl Encodable for f64 {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_f64(*self)
+    }
+}
+
 impl Encodable for bool {
     fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
         bf.write_bool(*self)
@@ -100,12 +119,115 @@ impl Encodable for String {
     }
 }
 
+impl Encodable for &str {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_string(self)
+    }
+}
+
+impl Encodable for Cow<'_, str> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_string(self)
+    }
+}
+
 impl<T: Encodable> Encodable for Vec<T> {
     fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
         bf.write_array(self)
     }
 }
 
+impl<T: Encodable> Encodable for &[T] {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_array(self)
+    }
+}
+
+impl<T: Encodable + Clone> Encodable for Cow<'_, [T]> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        bf.write_array(self)
+    }
+}
+
+/// Written as exactly `N` items, without a length prefix.
+impl<T: Encodable, const N: usize> Encodable for [T; N] {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        for item in self {
+            item.encode_value(bf)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Encodable> Encodable for VecDeque<T> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        encode_items(bf, self.iter())
+    }
+}
+
+/// Written like a `Vec<T>`, in iteration order.
+impl<T: Encodable, S> Encodable for HashSet<T, S> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        encode_items(bf, self.iter())
+    }
+}
+
+/// Written like a `Vec<T>`, in sorted order.
+impl<T: Encodable> Encodable for BTreeSet<T> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        encode_items(bf, self.iter())
+    }
+}
+
+/// Written as a length followed by each key and its value, in iteration
+/// order.
+impl<K: Encodable, V: Encodable, S> Encodable for HashMap<K, V, S> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        encode_entries(bf, self.iter())
+    }
+}
+
+/// Written as a length followed by each key and its value, in sorted order.
+impl<K: Encodable, V: Encodable> Encodable for BTreeMap<K, V> {
+    fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        encode_entries(bf, self.iter())
+    }
+}
+
+/// Writes nothing.
+impl Encodable for () {
+    fn encode_value(&self, _bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+        Ok(())
+    }
+}
+
+/// Implements `Encodable` for a tuple, writing each element in order.
+macro_rules! impl_encodable_tuple {
+    ($($idx:tt $name:ident),+) => {
+        impl<$($name: Encodable),+> Encodable for ($($name,)+) {
+            fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+                $(self.$idx.encode_value(bf)?;)+
+
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_encodable_tuple!(0 A);
+impl_encodable_tuple!(0 A, 1 B);
+impl_encodable_tuple!(0 A, 1 B, 2 C);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K);
+impl_encodable_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H, 8 I, 9 J, 10 K, 11 L);
+
 impl<T: Encodable> Encodable for Option<T> {
     fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
         match self {
@@ -117,3 +239,54 @@ impl<T: Encodable> Encodable for Option<T> {
         }
     }
 }
+
+/// Implements `Encodable` for a smart pointer, written exactly as the value it
+/// points to, including any header.
+macro_rules! impl_encodable_pointer {
+    ($($pointer:ident),+) => {
+        $(
+            impl<T: Encodable> Encodable for $pointer<T> {
+                fn encode(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+                    (**self).encode(bf)
+                }
+
+                fn encode_value(&self, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+                    (**self).encode_value(bf)
+                }
+            }
+        )+
+    };
+}
+
+impl_encodable_pointer!(Box, Rc, Arc);
+
+/// Writes a collection's length using the buffer's length prefix, followed by
+/// each of its items.
+fn encode_items<'a, T: Encodable + 'a>(
+    bf: &mut Bufferfish,
+    items: impl ExactSizeIterator<Item = &'a T>,
+) -> Result<(), BufferfishError> {
+    bf.write_len(items.len())?;
+
+    for item in items {
+        item.encode_value(bf)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a map's length using the buffer's length prefix, followed by each
+/// key and its value.
+fn encode_entries<'a, K: Encodable + 'a, V: Encodable + 'a>(
+    bf: &mut Bufferfish,
+    entries: impl ExactSizeIterator<Item = (&'a K, &'a V)>,
+) -> Result<(), BufferfishError> {
+    bf.write_len(entries.len())?;
+
+    for (key, value) in entries {
+        key.encode_value(bf)?;
+        value.encode_value(bf)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,241 @@
+//! Length-prefixed framing for stream transports (eg. raw TCP), where message
+//! boundaries are not preserved by the transport itself.
+
+use crate::{Bufferfish, BufferfishError, DecodeLimits, varint};
+
+/// The default maximum frame size (in bytes), matching the default max
+/// capacity of a `Bufferfish`.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 1024;
+
+/// The encoding of a length prefix, written before each frame by
+/// `FrameEncoder`, and before each string and collection by a `Bufferfish`.
+/// See `Bufferfish::set_length_prefix`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LengthPrefix {
+    /// A big-endian u16 length.
+    #[default]
+    U16,
+    /// A big-endian u32 length.
+    U32,
+    /// An unsigned LEB128 varint length, up to 5 bytes (u32).
+    Varint,
+}
+
+impl std::fmt::Display for LengthPrefix {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LengthPrefix::U16 => write!(f, "u16"),
+            LengthPrefix::U32 => write!(f, "u32"),
+            LengthPrefix::Varint => write!(f, "varint"),
+        }
+    }
+}
+
+impl LengthPrefix {
+    /// Returns the largest length this prefix is able to represent.
+    pub fn max_len(&self) -> usize {
+        match self {
+            LengthPrefix::U16 => u16::MAX as usize,
+            LengthPrefix::U32 | LengthPrefix::Varint => u32::MAX as usize,
+        }
+    }
+
+    /// Checks a frame of `len` bytes against a configured max frame size and
+    /// the limit of the prefix itself, where a max frame size of 0 means no
+    /// limit beyond the prefix.
+    pub(crate) fn check_len(
+        &self,
+        len: usize,
+        max_frame_size: usize,
+    ) -> Result<(), BufferfishError> {
+        let max_allowed = match max_frame_size {
+            0 => self.max_len(),
+            max => max.min(self.max_len()),
+        };
+
+        if len > max_allowed {
+            return Err(BufferfishError::ExcessiveBytes {
+                available: len,
+                max_allowed,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Writes the length prefix for a frame of `len` bytes to `dst`.
+    pub(crate) fn write<E: Extend<u8>>(&self, len: usize, dst: &mut E) {
+        match self {
+            LengthPrefix::U16 => dst.extend((len as u16).to_be_bytes()),
+            LengthPrefix::U32 => dst.extend((len as u32).to_be_bytes()),
+            LengthPrefix::Varint => {
+                let (bytes, n) = varint::encode(len as u32 as u64);
+                dst.extend(bytes[..n].iter().copied());
+            }
+        }
+    }
+
+    /// Attempts to read a length prefix from the start of `src`, returning the
+    /// size of the prefix and the frame length it describes. Returns `None` if
+    /// `src` does not yet contain a complete prefix.
+    pub(crate) fn read(&self, src: &[u8]) -> Result<Option<(usize, usize)>, BufferfishError> {
+        match self {
+            LengthPrefix::U16 => Ok(src
+                .first_chunk::<2>()
+                .map(|bytes| (2, u16::from_be_bytes(*bytes) as usize))),
+            LengthPrefix::U32 => Ok(src
+                .first_chunk::<4>()
+                .map(|bytes| (4, u32::from_be_bytes(*bytes) as usize))),
+            LengthPrefix::Varint => {
+                Ok(varint::decode(src, u32::BITS)?.map(|(n, len)| (n, len as usize)))
+            }
+        }
+    }
+}
+
+/// Splits a stream of bytes into complete `Bufferfish` frames.
+///
+/// Bytes may be fed in chunks of any size with `extend`; partial frames are
+/// held until the rest of their bytes arrive. Frame lengths are checked as
+/// soon as their prefix has been read, so an oversized frame is rejected
+/// without buffering its body.
+#[derive(Debug)]
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    prefix: LengthPrefix,
+    inner_prefix: LengthPrefix,
+    max_frame_size: usize,
+    decode_limits: DecodeLimits,
+}
+
+impl FrameDecoder {
+    /// Creates a new `FrameDecoder` with a default max frame size (1024 bytes).
+    pub fn new(prefix: LengthPrefix) -> Self {
+        Self {
+            buffer: Vec::new(),
+            prefix,
+            inner_prefix: LengthPrefix::default(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            decode_limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Set the max frame size (in bytes).
+    /// A value of 0 will allow frames up to the limit of the length prefix.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// Set the decode limits of each frame returned. See `DecodeLimits`.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_limits = limits;
+    }
+
+    /// Set the length prefix of strings and collections in each frame
+    /// returned, as set on the sender's buffers with
+    /// `Bufferfish::set_length_prefix`. Defaults to `LengthPrefix::U16`.
+    pub fn set_inner_length_prefix(&mut self, prefix: LengthPrefix) {
+        self.inner_prefix = prefix;
+    }
+
+    /// Returns the number of bytes buffered but not yet emitted as a frame.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Appends bytes read from the stream to the internal buffer.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Attempts to take the next complete frame from the internal buffer.
+    /// Returns `None` if more bytes are needed.
+    ///
+    /// Returns `BufferfishError::ExcessiveBytes` if the length prefix exceeds
+    /// the max frame size. The stream should be considered corrupt after any
+    /// error, as the decoder cannot resynchronize on a frame boundary.
+    pub fn decode(&mut self) -> Result<Option<Bufferfish>, BufferfishError> {
+        let Some((header_len, frame_len)) = self.prefix.read(&self.buffer)? else {
+            return Ok(None);
+        };
+
+        self.prefix.check_len(frame_len, self.max_frame_size)?;
+
+        if self.buffer.len() < header_len + frame_len {
+            return Ok(None);
+        }
+
+        let frame = self.buffer[header_len..header_len + frame_len].to_vec();
+        self.buffer.drain(..header_len + frame_len);
+
+        let mut bf = Bufferfish::from(frame);
+        bf.set_length_prefix(self.inner_prefix);
+        bf.set_decode_limits(self.decode_limits);
+
+        Ok(Some(bf))
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new(LengthPrefix::default())
+    }
+}
+
+impl Iterator for FrameDecoder {
+    type Item = Result<Bufferfish, BufferfishError>;
+
+    /// Yields each complete frame currently buffered. A `None` only means no
+    /// complete frame is available yet; the decoder can be extended and
+    /// iterated again.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.decode().transpose()
+    }
+}
+
+/// Writes `Bufferfish` frames with a length prefix for stream transports.
+#[derive(Debug)]
+pub struct FrameEncoder {
+    prefix: LengthPrefix,
+    max_frame_size: usize,
+}
+
+impl FrameEncoder {
+    /// Creates a new `FrameEncoder` with a default max frame size (1024 bytes).
+    pub fn new(prefix: LengthPrefix) -> Self {
+        Self {
+            prefix,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Set the max frame size (in bytes).
+    /// A value of 0 will allow frames up to the limit of the length prefix.
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    /// Writes the length prefix followed by the frame's bytes to `dst`.
+    ///
+    /// Returns `BufferfishError::ExcessiveBytes` if the frame is larger than
+    /// the max frame size or the length prefix can represent.
+    pub fn encode<T: AsRef<[u8]>>(
+        &self,
+        frame: T,
+        dst: &mut Vec<u8>,
+    ) -> Result<(), BufferfishError> {
+        let frame = frame.as_ref();
+        self.prefix.check_len(frame.len(), self.max_frame_size)?;
+
+        self.prefix.write(frame.len(), dst);
+        dst.extend_from_slice(frame);
+
+        Ok(())
+    }
+}
+
+impl Default for FrameEncoder {
+    fn default() -> Self {
+        Self::new(LengthPrefix::default())
+    }
+}
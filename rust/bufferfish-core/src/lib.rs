@@ -1,20 +1,39 @@
+pub mod annotate;
+pub mod bufferfish_ref;
+pub mod byte_buf;
+#[cfg(feature = "tokio-codec")]
+pub mod codec;
 pub mod decodable;
+pub mod decodable_ref;
 pub mod encodable;
+pub mod frame;
+pub mod limits;
+pub mod message;
+mod varint;
 
-use std::{
-    convert::TryFrom,
-    io::{Cursor, Read, Seek, Write},
-};
+use std::io::{Cursor, Seek, Write};
 
+use limits::DecodeState;
+
+pub use annotate::Annotation;
+pub use bufferfish_ref::BufferfishRef;
+pub use byte_buf::ByteBuf;
+#[cfg(feature = "tokio-codec")]
+pub use codec::BufferfishCodec;
 pub use decodable::Decodable;
+pub use decodable_ref::DecodableRef;
 pub use encodable::Encodable;
+pub use frame::{FrameDecoder, FrameEncoder, LengthPrefix};
+pub use limits::{DecodeLimits, Limit};
+pub use message::Message;
 
 /// Errors that can occur when encoding or decoding a `Bufferfish`.
 #[derive(Debug)]
 pub enum BufferfishError {
     /// std::io::Error that occurred during a write operation.
     FailedWrite(std::io::Error),
-    /// Invalid - typically non-u16 - message ID encountered during a write.
+    /// Invalid - typically non-u16 - message ID encountered during a write, or
+    /// an unexpected or unknown message ID encountered during a read.
     InvalidMessageId,
     /// Invalid enum variant encountered during encoding/decoding.
     InvalidEnumVariant,
@@ -25,6 +44,21 @@ pub enum BufferfishError {
         available: usize,
         max_allowed: usize,
     },
+    /// A string read from the buffer is not valid UTF-8.
+    InvalidUtf8(std::str::Utf8Error),
+    /// A varint read from the buffer is longer than its type allows, or its
+    /// value does not fit in the type.
+    InvalidVarint,
+    /// A length or nesting depth read from the buffer exceeds the
+    /// `DecodeLimits` of the reader.
+    LimitExceeded {
+        limit: Limit,
+        requested: usize,
+        max: usize,
+    },
+    /// An error occurred while decoding a field of a derived type. Wraps the
+    /// original error with the path to the field and its offset.
+    Decode(DecodeError),
 }
 
 impl std::fmt::Display for BufferfishError {
@@ -47,6 +81,17 @@ impl std::fmt::Display for BufferfishError {
                 f,
                 "excessive bytes in buffer: available {available}, maximum allowed {max_allowed}"
             ),
+            BufferfishError::InvalidUtf8(e) => write!(f, "invalid utf-8 in string: {e}"),
+            BufferfishError::InvalidVarint => write!(f, "invalid varint"),
+            BufferfishError::LimitExceeded {
+                limit,
+                requested,
+                max,
+            } => write!(
+                f,
+                "{limit} limit exceeded: requested {requested}, maximum allowed {max}"
+            ),
+            BufferfishError::Decode(e) => write!(f, "{e}"),
         }
     }
 }
@@ -65,7 +110,96 @@ impl std::error::Error for BufferfishError {
             BufferfishError::InvalidEnumVariant => None,
             BufferfishError::InsufficientBytes { .. } => None,
             BufferfishError::ExcessiveBytes { .. } => None,
+            BufferfishError::InvalidUtf8(e) => Some(e),
+            BufferfishError::InvalidVarint => None,
+            BufferfishError::LimitExceeded { .. } => None,
+            BufferfishError::Decode(e) => Some(e.kind()),
+        }
+    }
+}
+
+impl BufferfishError {
+    /// Records that this error occurred while decoding `field` of
+    /// `type_name`, starting at `offset`. Nested fields build up a path, eg.
+    /// `JoinMessage.user.name`, with the offset of the innermost field.
+    ///
+    /// This is called by the `Decode` derive, and is rarely needed directly.
+    pub fn in_field(self, type_name: &str, field: &str, offset: usize) -> Self {
+        match self {
+            BufferfishError::Decode(mut e) => {
+                e.type_name = type_name.to_string();
+                e.fields.insert(0, field.to_string());
+                BufferfishError::Decode(e)
+            }
+            e => BufferfishError::Decode(DecodeError {
+                type_name: type_name.to_string(),
+                fields: vec![field.to_string()],
+                offset,
+                kind: Box::new(e),
+            }),
+        }
+    }
+}
+
+/// An error that occurred while decoding a field, with the path to that field
+/// and the offset in the buffer where it started.
+#[derive(Debug)]
+pub struct DecodeError {
+    type_name: String,
+    fields: Vec<String>,
+    offset: usize,
+    kind: Box<BufferfishError>,
+}
+
+impl DecodeError {
+    /// Returns the name of the outermost type being decoded.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Returns the names of the fields leading to the one that failed,
+    /// outermost first. Array items are named by index, eg. `[2]`.
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Returns the full path to the field that failed, eg.
+    /// `JoinMessage.user.name` or `Lobby.players[2].name`.
+    pub fn path(&self) -> String {
+        let mut path = self.type_name.clone();
+
+        for field in &self.fields {
+            if !field.starts_with('[') {
+                path.push('.');
+            }
+
+            path.push_str(field);
         }
+
+        path
+    }
+
+    /// Returns the offset in the buffer of the start of the field that
+    /// failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the underlying error.
+    pub fn kind(&self) -> &BufferfishError {
+        &self.kind
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "failed to decode `{}` at byte {}: {}",
+            self.path(),
+            self.offset,
+            self.kind
+        )
     }
 }
 
@@ -80,6 +214,8 @@ pub struct Bufferfish {
     inner: Cursor<Vec<u8>>,
     reading: bool,
     max_capacity: usize,
+    length_prefix: LengthPrefix,
+    decode_state: DecodeState,
 }
 
 impl Write for Bufferfish {
@@ -116,6 +252,8 @@ impl Bufferfish {
             inner: Cursor::new(Vec::new()),
             reading: false,
             max_capacity: 1024,
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
         }
     }
 
@@ -126,6 +264,8 @@ impl Bufferfish {
             inner: Cursor::new(Vec::with_capacity(capacity)),
             reading: false,
             max_capacity: capacity,
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
         }
     }
 
@@ -151,11 +291,72 @@ impl Bufferfish {
         self.reading = true;
     }
 
+    /// Returns the current read position of the cursor. This is 0 after a
+    /// write, as reading starts again from the beginning of the buffer.
+    pub fn position(&self) -> usize {
+        if !self.reading {
+            return 0;
+        }
+
+        self.inner.position() as usize
+    }
+
+    /// Returns the number of bytes left to read.
+    fn remaining(&self) -> usize {
+        self.len().saturating_sub(self.position())
+    }
+
+    /// Advances the cursor by `n` bytes and returns the slice that was
+    /// skipped over.
+    fn take(&mut self, n: usize) -> Result<&[u8], BufferfishError> {
+        self.start_reading();
+
+        let pos = self.position();
+        if n > self.remaining() {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: n,
+            });
+        }
+
+        self.inner.set_position((pos + n) as u64);
+
+        Ok(self.inner.get_ref().get(pos..pos + n).unwrap_or_default())
+    }
+
+    /// Reads exactly `N` bytes into a fixed-size array.
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], BufferfishError> {
+        let mut bf = [0u8; N];
+        bf.copy_from_slice(self.take(N)?);
+
+        Ok(bf)
+    }
+
+    /// Reads a varint for an integer of `bits`.
+    fn take_varint(&mut self, bits: u32) -> Result<u64, BufferfishError> {
+        self.start_reading();
+
+        let pos = self.position();
+        let Some((len, value)) =
+            varint::decode(self.inner.get_ref().get(pos..).unwrap_or_default(), bits)?
+        else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: self.remaining() + 1,
+            });
+        };
+
+        self.inner.set_position((pos + len) as u64);
+
+        Ok(value)
+    }
+
     /// Clears the buffer and resets the cursor to the start position.
     pub fn reset(&mut self) {
         self.inner.get_mut().clear();
         self.inner.set_position(0);
         self.reading = false;
+        self.decode_state.reset();
     }
 
     /// Resizes the internal buffer to the given size (in bytes).
@@ -179,12 +380,84 @@ impl Bufferfish {
         self.inner.get_ref().as_slice()
     }
 
+    /// Decodes a `T` from the start of the buffer, recording the byte range
+    /// and value of each field read, eg. `[2..6] id: u32 = 1`. On error, the
+    /// annotation also records where decoding stopped and why. Useful for
+    /// debugging malformed messages.
+    ///
+    /// The cursor of this buffer is not moved.
+    pub fn annotate<T: Decodable>(&self) -> Annotation {
+        let mut bf = Bufferfish::from(self.as_bytes());
+        bf.set_length_prefix(self.length_prefix());
+        bf.set_decode_limits(self.decode_limits());
+        bf.start_reading();
+
+        annotate::annotate::<T>(&mut bf)
+    }
+
     /// Set the max capacity (in bytes) for the internal buffer.
     /// A value of 0 will allow the buffer to grow indefinitely.
     pub fn set_max_capacity(&mut self, capacity: usize) {
         self.max_capacity = capacity;
     }
 
+    /// Set how string and collection lengths are written to and read from
+    /// this buffer. Defaults to `LengthPrefix::U16`, which limits them to
+    /// 65535 bytes or items. Both sides must use the same prefix.
+    pub fn set_length_prefix(&mut self, prefix: LengthPrefix) {
+        self.length_prefix = prefix;
+    }
+
+    /// Returns how string and collection lengths are written to and read
+    /// from this buffer.
+    pub fn length_prefix(&self) -> LengthPrefix {
+        self.length_prefix
+    }
+
+    /// Encodes or decodes with `f` using `prefix` for string and collection
+    /// lengths, restoring the previous prefix afterwards.
+    ///
+    /// This is called by the derives for fields marked
+    /// `#[bufferfish(length_prefix = "..")]`, and is rarely needed directly.
+    pub fn with_length_prefix<T>(
+        &mut self,
+        prefix: LengthPrefix,
+        f: impl FnOnce(&mut Self) -> Result<T, BufferfishError>,
+    ) -> Result<T, BufferfishError> {
+        let previous = std::mem::replace(&mut self.length_prefix, prefix);
+        let result = f(self);
+        self.length_prefix = previous;
+
+        result
+    }
+
+    /// Set the limits enforced when decoding from this buffer. See
+    /// `DecodeLimits`.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_state.limits = limits;
+    }
+
+    /// Returns the limits enforced when decoding from this buffer.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        self.decode_state.limits
+    }
+
+    /// Decodes a nested value with `decode`, enforcing the max nesting depth
+    /// of the buffer's `DecodeLimits`.
+    ///
+    /// This is called by the `Decode` derive and collection impls, and is
+    /// rarely needed directly.
+    pub fn decode_nested<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> Result<T, BufferfishError>,
+    ) -> Result<T, BufferfishError> {
+        self.decode_state.enter()?;
+        let result = decode(self);
+        self.decode_state.exit();
+
+        result
+    }
+
     /// Adds a `Bufferfish` or `Vec<u8>` to the end of the buffer.
     /// See `try_extends` for a version that returns a `Result`.
     ///
@@ -207,41 +480,40 @@ impl Bufferfish {
     /// Returns the next byte in the buffer without advancing the cursor.
     /// Returns a `Result` if the cursor is at the end of the buffer.
     pub fn peek(&mut self) -> Result<u8, BufferfishError> {
-        self.start_reading();
-        let pos = self.inner.position();
-
-        let Some(byte) = self.inner.get_ref().get(pos as usize) else {
-            return Err(std::io::Error::other(format!(
-                "peek of 1 byte exceeds the max capacity of {} bytes on this Bufferfish",
-                self.max_capacity
-            )))?;
-        };
-
-        let byte = *byte;
-
-        self.inner.set_position(pos);
-
-        Ok(byte)
+        Ok(self.peek_n(1)?[0])
     }
 
     /// Returns the next n-bytes in the buffer without advancing the cursor.
     /// Returns a Result if the cursor is at the end of the buffer.
     pub fn peek_n(&mut self, n: usize) -> Result<Vec<u8>, BufferfishError> {
         self.start_reading();
-        let pos = self.inner.position();
+        let pos = self.position();
 
-        let Some(bytes) = self.inner.get_ref().get(pos as usize..pos as usize + n) else {
-            return Err(std::io::Error::other(format!(
-                "peek of {} bytes exceeds the max capacity of {} bytes on this Bufferfish",
-                n, self.max_capacity
-            )))?;
+        let Some(bytes) = self.inner.get_ref().get(pos..pos + n) else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: n,
+            });
         };
 
-        let bytes = bytes.to_vec();
+        Ok(bytes.to_vec())
+    }
+
+    /// Returns the next two bytes in the buffer as a u16 without advancing the
+    /// cursor. Useful for inspecting a message ID before decoding.
+    /// Returns a Result if the cursor is at the end of the buffer.
+    pub fn peek_u16(&mut self) -> Result<u16, BufferfishError> {
+        self.start_reading();
+        let pos = self.position();
 
-        self.inner.set_position(pos);
+        let Some(&[high, low]) = self.inner.get_ref().get(pos..pos + 2) else {
+            return Err(BufferfishError::InsufficientBytes {
+                available: self.remaining(),
+                required: 2,
+            });
+        };
 
-        Ok(bytes)
+        Ok(u16::from_be_bytes([high, low]))
     }
 
     /// Writes a u8 to the buffer as one byte.
@@ -314,6 +586,20 @@ impl Bufferfish {
         Ok(())
     }
 
+    /// Writes an f32 to the buffer as four bytes.
+    pub fn write_f32(&mut self, value: f32) -> Result<(), BufferfishError> {
+        self.write_all(&value.to_be_bytes())?;
+
+        Ok(())
+    }
+
+    /// Writes an f64 to the buffer as eight bytes.
+    pub fn write_f64(&mut self, value: f64) -> Result<(), BufferfishError> {
+        self.write_all(&value.to_be_bytes())?;
+
+        Ok(())
+    }
+
     /// Writes a bool to the buffer as one byte.
     pub fn write_bool(&mut self, value: bool) -> Result<(), BufferfishError> {
         self.write_u8(if value { 1 } else { 0 })?;
@@ -344,26 +630,50 @@ impl Bufferfish {
         Ok(())
     }
 
+    /// Writes a u64 to the buffer as an unsigned LEB128 varint, taking one
+    /// byte for every 7 bits of the value (1 to 10 bytes). Smaller unsigned
+    /// integers can be widened to a u64, as the encoding is the same.
+    pub fn write_varint_u64(&mut self, value: u64) -> Result<(), BufferfishError> {
+        let (bytes, len) = varint::encode(value);
+        self.write_all(&bytes[..len])?;
+
+        Ok(())
+    }
+
+    /// Writes an i64 to the buffer as a zigzag encoded varint, so that values
+    /// close to zero take few bytes whether positive or negative. Smaller
+    /// signed integers can be widened to an i64, as the encoding is the same.
+    pub fn write_varint_i64(&mut self, value: i64) -> Result<(), BufferfishError> {
+        self.write_varint_u64(varint::zigzag_encode(value))
+    }
+
+    /// Writes a string or collection length using the buffer's length
+    /// prefix. Returns `ExcessiveBytes` if the prefix can't represent `len`.
+    pub fn write_len(&mut self, len: usize) -> Result<(), BufferfishError> {
+        self.length_prefix.check_len(len, 0)?;
+
+        match self.length_prefix {
+            LengthPrefix::U16 => self.write_u16(len as u16),
+            LengthPrefix::U32 => self.write_u32(len as u32),
+            LengthPrefix::Varint => self.write_varint_u64(len as u64),
+        }
+    }
+
     /// Writes a variable length string to the buffer. It will be prefixed with
-    /// its length in bytes as a u16 (two bytes).
+    /// its length in bytes, as a u16 (two bytes) by default. See
+    /// `set_length_prefix`.
     pub fn write_string(&mut self, value: &str) -> Result<(), BufferfishError> {
-        let len = u16::try_from(value.len()).map_err(|_| {
-            std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "String length exceeds u16 max value",
-            )
-        })?;
-
-        self.write_u16(len)?;
+        self.write_len(value.len())?;
         self.write_all(value.as_bytes())?;
 
         Ok(())
     }
 
     /// Writes an array to the buffer, where the items implement the Encodable
-    /// trait. The array will be prefixed with its length as a u16 (two bytes).
+    /// trait. The array will be prefixed with its length, as a u16 (two
+    /// bytes) by default. See `set_length_prefix`.
     pub fn write_array<T: Encodable>(&mut self, vec: &[T]) -> Result<(), BufferfishError> {
-        self.write_u16(vec.len() as u16)?;
+        self.write_len(vec.len())?;
 
         for item in vec {
             item.encode_value(self)?;
@@ -372,6 +682,16 @@ impl Bufferfish {
         Ok(())
     }
 
+    /// Writes a run of bytes to the buffer in a single copy. It will be
+    /// prefixed with its length, as a u16 (two bytes) by default, matching
+    /// the encoding of a `Vec<u8>`.
+    pub fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), BufferfishError> {
+        self.write_len(bytes.len())?;
+        self.write_all(bytes)?;
+
+        Ok(())
+    }
+
     /// Writes an `Option<T>` to the buffer, where `T` implements the
     /// Encodable trait. If the option is `Some`, it will write a u8 with value
     /// 1, followed by the encoded value. If the option is `None`, it will write
@@ -402,102 +722,62 @@ impl Bufferfish {
 
     /// Reads a u8 from the buffer.
     pub fn read_u8(&mut self) -> Result<u8, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 1];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(bf[0])
+        Ok(self.take_array::<1>()?[0])
     }
 
     /// Reads a u16 from the buffer.
     pub fn read_u16(&mut self) -> Result<u16, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 2];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(u16::from_be_bytes(bf))
+        Ok(u16::from_be_bytes(self.take_array()?))
     }
 
     /// Reads a u32 from the buffer.
     pub fn read_u32(&mut self) -> Result<u32, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 4];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(u32::from_be_bytes(bf))
+        Ok(u32::from_be_bytes(self.take_array()?))
     }
 
     /// Reads a u64 from the buffer.
     pub fn read_u64(&mut self) -> Result<u64, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 8];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(u64::from_be_bytes(bf))
+        Ok(u64::from_be_bytes(self.take_array()?))
     }
 
     /// Reads a u128 from the buffer.
     pub fn read_u128(&mut self) -> Result<u128, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 16];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(u128::from_be_bytes(bf))
+        Ok(u128::from_be_bytes(self.take_array()?))
     }
 
     /// Reads an i8 from the buffer.
     pub fn read_i8(&mut self) -> Result<i8, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 1];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(i8::from_be_bytes(bf))
+        Ok(i8::from_be_bytes(self.take_array()?))
     }
 
     /// Reads an i16 from the buffer.
     pub fn read_i16(&mut self) -> Result<i16, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 2];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(i16::from_be_bytes(bf))
+        Ok(i16::from_be_bytes(self.take_array()?))
     }
 
     /// Reads an i32 from the buffer.
     pub fn read_i32(&mut self) -> Result<i32, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 4];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(i32::from_be_bytes(bf))
+        Ok(i32::from_be_bytes(self.take_array()?))
     }
 
     /// Reads an i64 from the buffer.
     pub fn read_i64(&mut self) -> Result<i64, BufferfishError> {
-        self.start_reading();
-
-        let mut bf = [0u8; 8];
-        self.inner.read_exact(&mut bf)?;
-
-        Ok(i64::from_be_bytes(bf))
+        Ok(i64::from_be_bytes(self.take_array()?))
     }
 
     /// Reads an i128 from the buffer.
     pub fn read_i128(&mut self) -> Result<i128, BufferfishError> {
-        self.start_reading();
+        Ok(i128::from_be_bytes(self.take_array()?))
+    }
 
-        let mut bf = [0u8; 16];
-        self.inner.read_exact(&mut bf)?;
+    /// Reads an f32 from the buffer.
+    pub fn read_f32(&mut self) -> Result<f32, BufferfishError> {
+        Ok(f32::from_be_bytes(self.take_array()?))
+    }
 
-        Ok(i128::from_be_bytes(bf))
+    /// Reads an f64 from the buffer.
+    pub fn read_f64(&mut self) -> Result<f64, BufferfishError> {
+        Ok(f64::from_be_bytes(self.take_array()?))
     }
 
     /// Reads a bool from the buffer.
@@ -507,14 +787,57 @@ impl Bufferfish {
         Ok(value != 0)
     }
 
+    /// Reads a string or collection length using the buffer's length prefix.
+    pub fn read_len(&mut self) -> Result<usize, BufferfishError> {
+        match self.length_prefix {
+            LengthPrefix::U16 => Ok(self.read_u16()? as usize),
+            LengthPrefix::U32 => Ok(self.read_u32()? as usize),
+            LengthPrefix::Varint => Ok(self.read_varint_u32()? as usize),
+        }
+    }
+
+    /// Reads a varint u16 from the buffer. See `write_varint_u64`.
+    pub fn read_varint_u16(&mut self) -> Result<u16, BufferfishError> {
+        Ok(self.take_varint(u16::BITS)? as u16)
+    }
+
+    /// Reads a varint u32 from the buffer. See `write_varint_u64`.
+    pub fn read_varint_u32(&mut self) -> Result<u32, BufferfishError> {
+        Ok(self.take_varint(u32::BITS)? as u32)
+    }
+
+    /// Reads a varint u64 from the buffer. See `write_varint_u64`.
+    pub fn read_varint_u64(&mut self) -> Result<u64, BufferfishError> {
+        self.take_varint(u64::BITS)
+    }
+
+    /// Reads a zigzag encoded varint i16 from the buffer. See
+    /// `write_varint_i64`.
+    pub fn read_varint_i16(&mut self) -> Result<i16, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i16::BITS)?) as i16)
+    }
+
+    /// Reads a zigzag encoded varint i32 from the buffer. See
+    /// `write_varint_i64`.
+    pub fn read_varint_i32(&mut self) -> Result<i32, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i32::BITS)?) as i32)
+    }
+
+    /// Reads a zigzag encoded varint i64 from the buffer. See
+    /// `write_varint_i64`.
+    pub fn read_varint_i64(&mut self) -> Result<i64, BufferfishError> {
+        Ok(varint::zigzag_decode(self.take_varint(i64::BITS)?))
+    }
+
     /// Attempts to read a packed array of booleans from the buffer.
     /// You must specify the number of booleans to read.
     pub fn read_packed_bools(&mut self, count: u8) -> Result<Vec<bool>, BufferfishError> {
+        // Only 8 booleans fit in the single packed byte.
         if count > 8 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Cannot pack more than 8 booleans into a single byte.",
-            ))?;
+            return Err(BufferfishError::InsufficientBytes {
+                available: 1,
+                required: (count as usize).div_ceil(8),
+            });
         }
 
         let packed = self.read_u8()?;
@@ -529,28 +852,21 @@ impl Bufferfish {
 
     /// Reads a variable length string from the buffer.
     pub fn read_string(&mut self) -> Result<String, BufferfishError> {
-        self.start_reading();
-
-        let len = self.read_u16()? as usize;
-        let pos = self.inner.position() as usize;
-        self.inner.set_position((pos + len) as u64);
+        let len = self.read_len()?;
+        self.decode_state.string(len)?;
+        self.decode_state.allocate(len)?;
+        let bytes = self.take(len)?.to_vec();
 
-        let Some(slice) = &mut self.inner.get_mut().get(pos..pos + len) else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "Unexpected EOF",
-            ))?;
-        };
+        String::from_utf8(bytes).map_err(|e| BufferfishError::InvalidUtf8(e.utf8_error()))
+    }
 
-        let string = String::from_utf8(slice.to_vec());
+    /// Reads a length-prefixed run of bytes from the buffer in a single copy.
+    /// This matches the encoding of a `Vec<u8>`.
+    pub fn read_bytes(&mut self) -> Result<Vec<u8>, BufferfishError> {
+        let len = self.read_len()?;
+        self.decode_state.collection(len, 1)?;
 
-        match string {
-            Ok(s) => Ok(s),
-            Err(e) => Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                e.to_string(),
-            ))?,
-        }
+        Ok(self.take(len)?.to_vec())
     }
 
     /// Reads an array from the buffer, where the items implement the Decodable
@@ -558,14 +874,7 @@ impl Bufferfish {
     pub fn read_array<T: Decodable>(&mut self) -> Result<Vec<T>, BufferfishError> {
         self.start_reading();
 
-        let len = self.read_u16()? as usize;
-        let mut vec = Vec::with_capacity(len);
-
-        for _ in 0..len {
-            vec.push(T::decode(self)?);
-        }
-
-        Ok(vec)
+        Vec::<T>::decode_value(self)
     }
 }
 
@@ -577,7 +886,7 @@ pub fn read_option<T: Decodable>(bf: &mut Bufferfish) -> Result<Option<T>, Buffe
     let flag = bf.read_u8()?;
     match flag {
         0 => Ok(None),
-        1 => Ok(Some(T::decode(bf)?)),
+        1 => Ok(Some(T::decode_value(bf)?)),
         _ => Err(BufferfishError::InvalidEnumVariant),
     }
 }
@@ -631,6 +940,8 @@ impl From<&[u8]> for Bufferfish {
             inner: Cursor::new(slice.to_vec()),
             reading: false,
             max_capacity: slice.len(),
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
         }
     }
 }
@@ -643,6 +954,8 @@ impl From<Vec<u8>> for Bufferfish {
             inner: Cursor::new(vec),
             reading: false,
             max_capacity,
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
         }
     }
 }
@@ -660,6 +973,8 @@ impl From<bytes::Bytes> for Bufferfish {
             inner: Cursor::new(bytes.to_vec()),
             reading: false,
             max_capacity: bytes.len(),
+            length_prefix: LengthPrefix::default(),
+            decode_state: DecodeState::default(),
         }
     }
 }
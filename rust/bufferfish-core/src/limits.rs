@@ -0,0 +1,141 @@
+//! Limits on the resources a single decode may use, to defend against
+//! hostile length prefixes in untrusted input.
+
+use crate::BufferfishError;
+
+/// Limits enforced while decoding from a `Bufferfish` or `BufferfishRef`.
+///
+/// Length prefixes are read from the input, so without limits a few bytes
+/// can request a large allocation, eg. a `Vec<Vec<T>>` where every inner
+/// length is the largest the length prefix allows. Set these when decoding
+/// untrusted input.
+///
+/// The defaults impose no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// The total bytes that may be allocated for collections and strings
+    /// while decoding a single value.
+    pub max_allocation: usize,
+    /// The max number of items in a single collection.
+    pub max_collection_len: usize,
+    /// The max length (in bytes) of a single string.
+    pub max_string_len: usize,
+    /// How deeply derived types and collections may be nested.
+    pub max_depth: usize,
+}
+
+impl DecodeLimits {
+    /// Limits which are not enforced.
+    pub const UNLIMITED: Self = Self {
+        max_allocation: usize::MAX,
+        max_collection_len: usize::MAX,
+        max_string_len: usize::MAX,
+        max_depth: usize::MAX,
+    };
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+/// The decode limit that was exceeded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// `DecodeLimits::max_allocation`.
+    Allocation,
+    /// `DecodeLimits::max_collection_len`.
+    CollectionLen,
+    /// `DecodeLimits::max_string_len`.
+    StringLen,
+    /// `DecodeLimits::max_depth`.
+    Depth,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Limit::Allocation => write!(f, "allocation"),
+            Limit::CollectionLen => write!(f, "collection length"),
+            Limit::StringLen => write!(f, "string length"),
+            Limit::Depth => write!(f, "nesting depth"),
+        }
+    }
+}
+
+/// Tracks usage against the `DecodeLimits` of a reader.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DecodeState {
+    pub(crate) limits: DecodeLimits,
+    allocated: usize,
+    depth: usize,
+}
+
+impl DecodeState {
+    /// Enters a nested value, checking the depth limit. Allocations are
+    /// counted from when the outermost value is entered.
+    pub(crate) fn enter(&mut self) -> Result<(), BufferfishError> {
+        check(Limit::Depth, self.depth + 1, self.limits.max_depth)?;
+
+        if self.depth == 0 {
+            self.allocated = 0;
+        }
+
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    /// Clears the tracked usage, keeping the limits.
+    pub(crate) fn reset(&mut self) {
+        self.allocated = 0;
+        self.depth = 0;
+    }
+
+    /// Checks a collection of `len` items of `size` bytes each, counting
+    /// its allocation.
+    pub(crate) fn collection(&mut self, len: usize, size: usize) -> Result<(), BufferfishError> {
+        check(Limit::CollectionLen, len, self.limits.max_collection_len)?;
+        self.allocate(len.saturating_mul(size))
+    }
+
+    /// Checks a string of `len` bytes. Its allocation, if it is copied out of
+    /// the buffer, is counted separately with `allocate`.
+    pub(crate) fn string(&self, len: usize) -> Result<(), BufferfishError> {
+        check(Limit::StringLen, len, self.limits.max_string_len)
+    }
+
+    /// Counts an allocation of `bytes`.
+    pub(crate) fn allocate(&mut self, bytes: usize) -> Result<(), BufferfishError> {
+        // Reads outside of any nested value are checked on their own.
+        let total = match self.depth {
+            0 => bytes,
+            _ => self.allocated.saturating_add(bytes),
+        };
+
+        check(Limit::Allocation, total, self.limits.max_allocation)?;
+
+        if self.depth > 0 {
+            self.allocated = total;
+        }
+
+        Ok(())
+    }
+}
+
+fn check(limit: Limit, requested: usize, max: usize) -> Result<(), BufferfishError> {
+    if requested > max {
+        return Err(BufferfishError::LimitExceeded {
+            limit,
+            requested,
+            max,
+        });
+    }
+
+    Ok(())
+}
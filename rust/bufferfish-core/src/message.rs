@@ -0,0 +1,16 @@
+//! Trait for types that are written with a message ID header.
+
+/// Types implementing this trait are encoded with a leading u16 message ID,
+/// as declared with the `#[bufferfish(..)]` attribute.
+///
+/// `#[derive(Decode)]` implements this automatically for any type with a
+/// message ID. As `From` cannot be called in a const context, the derived
+/// constant is taken with an `as u16` cast; for fieldless message ID enums
+/// this is the variant's discriminant. Message ID enums should therefore be
+/// `#[repr(u16)]`, with a `From` impl returning that discriminant, as encoding
+/// and decoding go through `u16::from`. IDs that don't fit in a `u16` fail to
+/// compile.
+pub trait Message {
+    /// The message ID written before the encoded value.
+    const MESSAGE_ID: u16;
+}
@@ -0,0 +1,74 @@
+//! Unsigned LEB128 varints, and the zigzag encoding used to write signed
+//! integers as varints.
+
+use crate::BufferfishError;
+
+/// The most bytes a varint can take, for a u64.
+pub(crate) const MAX_LEN: usize = 10;
+
+/// Returns the most bytes a varint for an integer of `bits` can take.
+pub(crate) const fn max_len(bits: u32) -> usize {
+    bits.div_ceil(7) as usize
+}
+
+/// Encodes `value` as a varint, returning the bytes and how many of them are
+/// used.
+pub(crate) fn encode(mut value: u64) -> ([u8; MAX_LEN], usize) {
+    let mut bytes = [0u8; MAX_LEN];
+    let mut len = 0;
+
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            bytes[len] = byte;
+            return (bytes, len + 1);
+        }
+
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+}
+
+/// Attempts to read a varint for an integer of `bits` from the start of
+/// `src`, returning the number of bytes read and the value. Returns `None` if
+/// `src` ends before the varint does.
+///
+/// Returns `InvalidVarint` if the varint is longer than `bits` allows, or its
+/// value does not fit in `bits`.
+pub(crate) fn decode(src: &[u8], bits: u32) -> Result<Option<(usize, u64)>, BufferfishError> {
+    let mut value: u64 = 0;
+
+    for (i, byte) in src.iter().take(max_len(bits)).enumerate() {
+        let shift = 7 * i as u32;
+        let payload = (byte & 0x7F) as u64;
+
+        if bits - shift < 7 && payload >> (bits - shift) != 0 {
+            return Err(BufferfishError::InvalidVarint);
+        }
+
+        value |= payload << shift;
+
+        if byte & 0x80 == 0 {
+            return Ok(Some((i + 1, value)));
+        }
+
+        if i + 1 == max_len(bits) {
+            return Err(BufferfishError::InvalidVarint);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Maps a signed integer to an unsigned one so that values near zero stay
+/// small, eg. 0, -1, 1, -2 become 0, 1, 2, 3.
+pub(crate) fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Reverses `zigzag_encode`.
+pub(crate) fn zigzag_decode(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
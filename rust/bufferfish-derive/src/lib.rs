@@ -4,13 +4,527 @@ use proc_macro_error::{abort, proc_macro_error};
 use proc_macro2::{Ident, Literal, Span, TokenStream};
 use quote::quote;
 use syn::{
-    Data, DataEnum, DeriveInput, Expr, Fields, Index, Type, TypePath, parse_macro_input,
-    spanned::Spanned,
+    Attribute, Data, DataEnum, DeriveInput, Expr, ExprLit, ExprUnary, Field, Fields, GenericParam,
+    Generics, Index, Lifetime, LifetimeParam, Lit, LitStr, MetaNameValue, Type, TypePath, UnOp,
+    WherePredicate, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned,
 };
 
+/// Selects which decoding trait a derive targets.
+#[derive(Clone, Copy)]
+enum DecodeMode {
+    /// `Decodable`, reading from an owned `Bufferfish`.
+    Owned,
+    /// `DecodableRef<'__bf>`, reading from a borrowed `BufferfishRef<'__bf>`.
+    Borrowed,
+    /// `Decodable::annotate_value`, recording each field read in an
+    /// `Annotation`.
+    Annotated,
+}
+
+impl DecodeMode {
+    /// Returns the expression reading a field from the wire, evaluating to a
+    /// `Result`.
+    fn field_decoder(self, field: &Field, attrs: &FieldAttrs) -> TokenStream {
+        let decoder = self.value_decoder(field, attrs);
+
+        match &attrs.length_prefix {
+            Some(prefix) => quote! { bf.with_length_prefix(#prefix, |bf| #decoder) },
+            None => decoder,
+        }
+    }
+
+    /// Returns the expression reading a field's value, without applying its
+    /// length prefix.
+    fn value_decoder(self, field: &Field, attrs: &FieldAttrs) -> TokenStream {
+        let ty = &field.ty;
+
+        if let Some(varint) = &attrs.varint {
+            let read_fn = varint.read_fn();
+            return match self {
+                DecodeMode::Owned | DecodeMode::Borrowed => quote! { bf.#read_fn() },
+                DecodeMode::Annotated => {
+                    quote! { bf.#read_fn().inspect(|value| annotation.value(value)) }
+                }
+            };
+        }
+
+        if let Some(with) = &attrs.with {
+            return match self {
+                DecodeMode::Owned | DecodeMode::Annotated => quote! { #with::decode(bf) },
+                DecodeMode::Borrowed => quote! { #with::decode_ref(bf) },
+            };
+        }
+
+        match (self, attrs.with_header) {
+            (DecodeMode::Owned, false) => {
+                quote! { <#ty as bufferfish::Decodable>::decode_value(bf) }
+            }
+            (DecodeMode::Owned, true) => quote! { <#ty as bufferfish::Decodable>::decode(bf) },
+            (DecodeMode::Borrowed, false) => {
+                quote! { <#ty as bufferfish::DecodableRef<'__bf>>::decode_value_ref(bf) }
+            }
+            (DecodeMode::Borrowed, true) => {
+                quote! { <#ty as bufferfish::DecodableRef<'__bf>>::decode_ref(bf) }
+            }
+            (DecodeMode::Annotated, false) => {
+                quote! { <#ty as bufferfish::Decodable>::annotate_value(bf, annotation) }
+            }
+            (DecodeMode::Annotated, true) => {
+                quote! { <#ty as bufferfish::Decodable>::annotate(bf, annotation) }
+            }
+        }
+    }
+
+    /// Returns the expression producing a field's value on decode, honouring
+    /// `#[bufferfish(skip)]` and `#[bufferfish(default = ..)]`.
+    ///
+    /// Errors are returned with the path to the field (`type_name.name`) and
+    /// its offset. When annotating, the bytes read are instead recorded
+    /// under `name`.
+    fn field_value(self, type_name: &str, name: &str, field: &Field) -> TokenStream {
+        let attrs = FieldAttrs::from_field(field);
+        if attrs.skip {
+            return match attrs.default {
+                Some(default) => quote! { #default },
+                None => quote! { ::core::default::Default::default() },
+            };
+        }
+
+        let decoder = self.field_decoder(field, &attrs);
+        match self {
+            DecodeMode::Annotated => {
+                let ty = &field.ty;
+                quote! {
+                    annotation.field(
+                        bf,
+                        #name,
+                        Some(&bufferfish::annotate::type_name::<#ty>()),
+                        |bf, annotation| #decoder,
+                    )?
+                }
+            }
+            DecodeMode::Owned | DecodeMode::Borrowed => quote! {
+                {
+                    let offset = bf.position();
+                    match #decoder {
+                        Ok(value) => value,
+                        Err(e) => return Err(e.in_field(#type_name, #name, offset)),
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// Field-level `#[bufferfish(..)]` options.
+#[derive(Default)]
+struct FieldAttrs {
+    /// The field is not written to or read from the wire.
+    skip: bool,
+    /// The value a skipped field is filled with on decode, in place of
+    /// `Default::default()`.
+    default: Option<Expr>,
+    /// A module providing `encode(&T, &mut Bufferfish)` and
+    /// `decode(&mut Bufferfish) -> T` (and `decode_ref` for `DecodeRef`),
+    /// used in place of the field type's own trait impls.
+    with: Option<syn::Path>,
+    /// The field is encoded and decoded with its message ID header, rather
+    /// than as a bare value.
+    with_header: bool,
+    /// The field is an integer encoded as a varint.
+    varint: Option<VarintField>,
+    /// The `bufferfish::LengthPrefix` used for strings and collections
+    /// within the field, in place of the buffer's.
+    length_prefix: Option<TokenStream>,
+    /// Whether `ts_type` or `ts_module` were given. These only configure the
+    /// TypeScript compiler, so are validated and otherwise ignored here.
+    has_ts_options: bool,
+}
+
+impl FieldAttrs {
+    fn from_field(field: &Field) -> Self {
+        let mut field_attrs = FieldAttrs::default();
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("bufferfish") {
+                continue;
+            }
+
+            let result = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    field_attrs.skip = true;
+                    Ok(())
+                } else if meta.path.is_ident("default") {
+                    field_attrs.default = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("with_header") {
+                    field_attrs.with_header = true;
+                    Ok(())
+                } else if meta.path.is_ident("varint") {
+                    field_attrs.varint = Some(VarintField::from_field(field));
+                    Ok(())
+                } else if meta.path.is_ident("length_prefix") {
+                    let prefix: LitStr = meta.value()?.parse()?;
+                    field_attrs.length_prefix = Some(parse_length_prefix(&prefix));
+                    Ok(())
+                } else if meta.path.is_ident("with") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    field_attrs.with = Some(path.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("ts_type") || meta.path.is_ident("ts_module") {
+                    meta.value()?.parse::<LitStr>()?;
+                    field_attrs.has_ts_options = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unknown bufferfish field attribute"))
+                }
+            });
+
+            if let Err(e) = result {
+                abort!(
+                    e.span(), "{}", e;
+                    help = "expected `skip`, `default = ..`, `with = \"..\"`, `with_header`, `varint`, `length_prefix = \"..\"`, `ts_type = \"..\"` or `ts_module = \"..\"`"
+                );
+            }
+        }
+
+        if let Some(with) = &field_attrs.with
+            && field_attrs.skip
+        {
+            abort!(with.span(), "`with` cannot be used on skipped fields");
+        }
+
+        if field_attrs.with_header && (field_attrs.skip || field_attrs.with.is_some()) {
+            abort!(
+                field.span(),
+                "`with_header` cannot be used on skipped fields or alongside `with`"
+            );
+        }
+
+        if field_attrs.varint.is_some()
+            && (field_attrs.skip || field_attrs.with.is_some() || field_attrs.with_header)
+        {
+            abort!(
+                field.span(),
+                "`varint` cannot be used on skipped fields or alongside `with` or `with_header`"
+            );
+        }
+
+        if field_attrs.length_prefix.is_some() && (field_attrs.skip || field_attrs.varint.is_some())
+        {
+            abort!(
+                field.span(),
+                "`length_prefix` cannot be used on skipped fields or alongside `varint`"
+            );
+        }
+
+        if field_attrs.has_ts_options && field_attrs.with.is_none() {
+            abort!(
+                field.span(),
+                "`ts_type` and `ts_module` can only be used alongside `with`"
+            );
+        }
+
+        if let Some(default) = &field_attrs.default
+            && !field_attrs.skip
+        {
+            abort!(
+                default.span(),
+                "`default` can only be used on skipped fields";
+                help = "add `skip`, eg. #[bufferfish(skip, default = ..)]"
+            );
+        }
+
+        field_attrs
+    }
+
+    /// Returns true if the field should not be written to or read from the
+    /// wire.
+    fn is_skipped(field: &Field) -> bool {
+        Self::from_field(field).skip
+    }
+
+    /// Returns the expression for a field's contribution to
+    /// `min_bytes_required`. Fields using a `with` codec or their own
+    /// `length_prefix` contribute nothing, as their size is unknown. Nested
+    /// headers are only counted for `with_header` fields.
+    fn min_size(field: &Field) -> TokenStream {
+        let attrs = Self::from_field(field);
+        if attrs.skip || attrs.with.is_some() || attrs.length_prefix.is_some() {
+            return quote! { 0 };
+        }
+
+        if attrs.varint.is_some() {
+            return quote! { 1 };
+        }
+
+        let ty = &field.ty;
+        if attrs.with_header {
+            return quote! { <#ty as bufferfish::Decodable>::min_bytes_required().unwrap_or(0) };
+        }
+
+        quote! {
+            <#ty as bufferfish::Decodable>::min_bytes_required()
+                .unwrap_or(0)
+                .saturating_sub(<#ty as bufferfish::Decodable>::header_size())
+        }
+    }
+
+    /// Returns the expression for a field's contribution to
+    /// `max_bytes_allowed`. Fields using a `with` codec or their own
+    /// `length_prefix` are unbounded, as their size is unknown.
+    fn max_size(field: &Field) -> TokenStream {
+        let attrs = Self::from_field(field);
+        if attrs.skip {
+            return quote! { Some(0) };
+        }
+
+        if attrs.with.is_some() || attrs.length_prefix.is_some() {
+            return quote! { None::<usize> };
+        }
+
+        if let Some(varint) = &attrs.varint {
+            let max_len = varint.max_len();
+            return quote! { Some(#max_len) };
+        }
+
+        let ty = &field.ty;
+        if attrs.with_header {
+            return quote! { <#ty as bufferfish::Decodable>::max_bytes_allowed() };
+        }
+
+        quote! {
+            <#ty as bufferfish::Decodable>::max_bytes_allowed()
+                .map(|max| max.saturating_sub(<#ty as bufferfish::Decodable>::header_size()))
+        }
+    }
+}
+
+/// Parses the value of `#[bufferfish(length_prefix = "..")]` into a
+/// `bufferfish::LengthPrefix`.
+fn parse_length_prefix(prefix: &LitStr) -> TokenStream {
+    match prefix.value().as_str() {
+        "u16" => quote! { bufferfish::LengthPrefix::U16 },
+        "u32" => quote! { bufferfish::LengthPrefix::U32 },
+        "varint" => quote! { bufferfish::LengthPrefix::Varint },
+        _ => abort!(
+            prefix.span(),
+            "unknown length prefix `{}`", prefix.value();
+            help = "expected one of `u16`, `u32` or `varint`"
+        ),
+    }
+}
+
+/// An integer field marked `#[bufferfish(varint)]`, written as a LEB128
+/// varint. Signed integers are zigzag encoded first.
+struct VarintField {
+    ty: Ident,
+    signed: bool,
+    bits: usize,
+}
+
+impl VarintField {
+    fn from_field(field: &Field) -> Self {
+        let ident = match &field.ty {
+            Type::Path(TypePath { path, .. }) => path.get_ident(),
+            _ => None,
+        };
+
+        let (signed, bits) = match ident.map(|ident| ident.to_string()).as_deref() {
+            Some("u16") => (false, 16),
+            Some("u32") => (false, 32),
+            Some("u64") => (false, 64),
+            Some("i16") => (true, 16),
+            Some("i32") => (true, 32),
+            Some("i64") => (true, 64),
+            _ => abort!(
+                field.ty.span(),
+                "`varint` can only be used on integer fields";
+                help = "expected one of `u16`, `u32`, `u64`, `i16`, `i32` or `i64`"
+            ),
+        };
+
+        Self {
+            ty: ident.unwrap().clone(),
+            signed,
+            bits,
+        }
+    }
+
+    fn read_fn(&self) -> Ident {
+        Ident::new(&format!("read_varint_{}", self.ty), Span::call_site())
+    }
+
+    /// The most bytes the varint can take.
+    fn max_len(&self) -> usize {
+        self.bits.div_ceil(7)
+    }
+
+    /// Returns the statement writing the field at `accessor`, which may be a
+    /// value or a reference to one.
+    fn encoder(&self, accessor: TokenStream) -> TokenStream {
+        let ty = &self.ty;
+        let (write_fn, wide) = match self.signed {
+            false => (quote! { write_varint_u64 }, quote! { u64 }),
+            true => (quote! { write_varint_i64 }, quote! { i64 }),
+        };
+
+        quote! {
+            {
+                let value: &#ty = &#accessor;
+                bf.#write_fn(#wide::from(*value))?;
+            }
+        }
+    }
+}
+
+/// The wire representation of an enum's variant tag, taken from its
+/// `#[repr(..)]` attribute. Enums without an integer repr use a `u8` tag.
+struct EnumTag {
+    repr: Ident,
+    size: usize,
+    min: i128,
+    max: i128,
+    explicit: bool,
+}
+
+impl EnumTag {
+    fn from_ast(ast: &DeriveInput) -> Self {
+        for attr in &ast.attrs {
+            if !attr.path().is_ident("repr") {
+                continue;
+            }
+
+            let Ok(reprs) =
+                attr.parse_args_with(Punctuated::<Ident, syn::Token![,]>::parse_terminated)
+            else {
+                continue;
+            };
+
+            for repr in reprs {
+                let (size, min, max) = match repr.to_string().as_str() {
+                    "u8" => (1, u8::MIN as i128, u8::MAX as i128),
+                    "u16" => (2, u16::MIN as i128, u16::MAX as i128),
+                    "u32" => (4, u32::MIN as i128, u32::MAX as i128),
+                    "u64" => (8, u64::MIN as i128, u64::MAX as i128),
+                    "u128" => (16, u128::MIN as i128, i128::MAX),
+                    "i8" => (1, i8::MIN as i128, i8::MAX as i128),
+                    "i16" => (2, i16::MIN as i128, i16::MAX as i128),
+                    "i32" => (4, i32::MIN as i128, i32::MAX as i128),
+                    "i64" => (8, i64::MIN as i128, i64::MAX as i128),
+                    "i128" => (16, i128::MIN, i128::MAX),
+                    "usize" | "isize" => abort!(
+                        repr.span(),
+                        "platform-sized enum reprs cannot be encoded into a bufferfish";
+                        help = "use a fixed-size repr such as `#[repr(u16)]`"
+                    ),
+                    _ => continue,
+                };
+
+                return Self {
+                    repr,
+                    size,
+                    min,
+                    max,
+                    explicit: true,
+                };
+            }
+        }
+
+        Self {
+            repr: Ident::new("u8", Span::call_site()),
+            size: 1,
+            min: u8::MIN as i128,
+            max: u8::MAX as i128,
+            explicit: false,
+        }
+    }
+
+    fn write_fn(&self) -> Ident {
+        Ident::new(&format!("write_{}", self.repr), Span::call_site())
+    }
+
+    fn read_fn(&self) -> Ident {
+        Ident::new(&format!("read_{}", self.repr), Span::call_site())
+    }
+
+    /// Resolves the wire value of each variant, following Rust's rules for
+    /// implicit discriminants: each one is the previous value plus one,
+    /// starting from zero.
+    fn discriminants(&self, data_enum: &DataEnum) -> Vec<TokenStream> {
+        if !self.explicit && data_enum.variants.len() > 256 {
+            abort!(
+                data_enum.enum_token.span(),
+                "enums with more than 256 variants cannot be encoded with the default u8 tag";
+                help = "add `#[repr(u16)]` (or a wider integer repr) to the enum"
+            );
+        }
+
+        let mut discriminants = Vec::new();
+        let mut next = 0i128;
+
+        for variant in &data_enum.variants {
+            let value = match &variant.discriminant {
+                Some((_, expr)) => parse_discriminant(expr),
+                None => next,
+            };
+
+            if value < self.min || value > self.max {
+                abort!(
+                    variant.span(),
+                    "discriminant {} does not fit in the {} enum tag", value, self.repr;
+                    help = "add a wider integer repr such as `#[repr(u16)]` to the enum"
+                );
+            }
+
+            discriminants.push(discriminant_literal(value));
+            next = value.wrapping_add(1);
+        }
+
+        discriminants
+    }
+}
+
+fn parse_discriminant(expr: &Expr) -> i128 {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit_int),
+            ..
+        }) => match lit_int.base10_parse::<i128>() {
+            Ok(value) => value,
+            Err(_) => abort!(lit_int.span(), "discriminant is out of range"),
+        },
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_discriminant(expr).wrapping_neg(),
+        Expr::Group(group) => parse_discriminant(&group.expr),
+        Expr::Paren(paren) => parse_discriminant(&paren.expr),
+        _ => abort!(
+            expr.span(),
+            "enum discriminants must be integer literals to be encoded into a bufferfish"
+        ),
+    }
+}
+
+fn discriminant_literal(value: i128) -> TokenStream {
+    let magnitude = Literal::u128_unsuffixed(value.unsigned_abs());
+
+    if value < 0 {
+        quote! { -#magnitude }
+    } else {
+        quote! { #magnitude }
+    }
+}
+
 fn extract_message_id(ast: &DeriveInput) -> Option<Expr> {
     for attr in &ast.attrs {
         if attr.path().is_ident("bufferfish") {
+            if extract_bound_attr(attr).is_some() {
+                continue;
+            }
+
             if let Ok(expr) = attr.parse_args::<syn::Expr>() {
                 return Some(expr);
             } else {
@@ -22,11 +536,66 @@ fn extract_message_id(ast: &DeriveInput) -> Option<Expr> {
     None
 }
 
+/// Returns the string literal of a `#[bufferfish(bound = "..")]` attribute.
+fn extract_bound_attr(attr: &Attribute) -> Option<LitStr> {
+    let name_value = attr.parse_args::<MetaNameValue>().ok()?;
+    if !name_value.path.is_ident("bound") {
+        return None;
+    }
+
+    match name_value.value {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit_str),
+            ..
+        }) => Some(lit_str),
+        _ => abort!(
+            name_value.value.span(),
+            "expected a string literal";
+            help = "eg. #[bufferfish(bound = \"T: bufferfish::Encodable\")]"
+        ),
+    }
+}
+
+/// Returns the type's generics with a `T: #bound` predicate added for each
+/// type parameter, or with the predicates from `#[bufferfish(bound = "..")]`
+/// in their place when given.
+fn bounded_generics(ast: &DeriveInput, bound: TokenStream) -> Generics {
+    let mut generics = ast.generics.clone();
+    let where_clause = generics.make_where_clause();
+
+    let custom_bound = ast
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("bufferfish"))
+        .find_map(extract_bound_attr);
+
+    match custom_bound {
+        Some(lit_str) => {
+            let predicates = lit_str
+                .parse_with(Punctuated::<WherePredicate, syn::Token![,]>::parse_terminated)
+                .unwrap_or_else(|e| abort!(lit_str.span(), "invalid bound: {}", e));
+            where_clause.predicates.extend(predicates);
+        }
+        None => {
+            for param in ast.generics.type_params() {
+                let ident = &param.ident;
+                where_clause
+                    .predicates
+                    .push(parse_quote! { #ident: #bound });
+            }
+        }
+    }
+
+    generics
+}
+
 #[proc_macro_derive(Encode, attributes(bufferfish))]
 #[proc_macro_error]
 pub fn bufferfish_impl_encodable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     let name = &ast.ident;
+    let generics = bounded_generics(&ast, quote! { bufferfish::Encodable });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let message_id = extract_message_id(&ast);
     let message_id_snippet = {
@@ -44,13 +613,14 @@ pub fn bufferfish_impl_encodable(input: proc_macro::TokenStream) -> proc_macro::
             encoded_snippets = generate_struct_field_encoders(data);
         }
         Data::Enum(data_enum) => {
-            encoded_snippets.push(generate_enum_variant_encoders(name, data_enum));
+            let tag = EnumTag::from_ast(&ast);
+            encoded_snippets.push(generate_enum_variant_encoders(name, data_enum, &tag));
         }
         Data::Union(_) => abort!(ast.span(), "encoding union types is not supported"),
     };
 
     let generated = quote! {
-        impl bufferfish::Encodable for #name {
+        impl #impl_generics bufferfish::Encodable for #name #ty_generics #where_clause {
             fn encode_value(&self, bf: &mut bufferfish::Bufferfish) -> Result<(), bufferfish::BufferfishError> {
                 #(#encoded_snippets)*
                 Ok(())
@@ -79,97 +649,370 @@ pub fn bufferfish_impl_encodable(input: proc_macro::TokenStream) -> proc_macro::
 pub fn bufferfish_impl_decodable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let ast = parse_macro_input!(input as DeriveInput);
     let name = &ast.ident;
+    let generics = bounded_generics(&ast, quote! { bufferfish::Decodable });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let message_id = extract_message_id(&ast);
     let has_message_id = message_id.is_some();
-    let message_id_snippet = {
-        if let Some(message_id) = message_id {
-            quote! {
-                let message_id = bf.read_u16()?;
-                if message_id != u16::from(#message_id) {
-                    return Err(bufferfish::BufferfishError::InvalidMessageId);
-                }
+    // `From` can't be called in a const context, so the constant is taken
+    // with a cast, which is checked not to truncate. Encoding and decoding use
+    // `u16::from`, which for a `#[repr(u16)]` enum should agree with it.
+    let message_impl = match &message_id {
+        Some(message_id) => quote! {
+            impl #impl_generics bufferfish::Message for #name #ty_generics #where_clause {
+                #[allow(clippy::unnecessary_cast)]
+                const MESSAGE_ID: u16 = (#message_id) as u16;
             }
-        } else {
-            quote! {}
-        }
+
+            #[allow(clippy::unnecessary_cast)]
+            const _: () = assert!(
+                (#message_id) as i128 == (#message_id) as u16 as i128,
+                "message IDs must fit in a u16; use `#[repr(u16)]` on message ID enums",
+            );
+        },
+        None => quote! {},
     };
+    let message_id_annotator = generate_message_id_annotator(message_id.as_ref());
+    let message_id_snippet = generate_message_id_decoder(message_id);
+    let decode_body = generate_decode_body(&ast, DecodeMode::Owned);
+    let annotate_body = generate_decode_body(&ast, DecodeMode::Annotated);
 
-    let decoded_snippets;
     let min_size_logic;
     let max_size_logic;
 
     match &ast.data {
         Data::Struct(data_struct) => {
-            decoded_snippets = generate_struct_field_decoders(data_struct);
             min_size_logic = generate_struct_min_size_logic(data_struct, has_message_id);
             max_size_logic = generate_struct_max_size_logic(data_struct, has_message_id);
         }
         Data::Enum(data_enum) => {
-            decoded_snippets = generate_enum_variant_decoders(data_enum);
-            min_size_logic = generate_enum_min_size_logic(data_enum, has_message_id);
-            max_size_logic = generate_enum_max_size_logic(data_enum, has_message_id);
+            let tag = EnumTag::from_ast(&ast);
+            min_size_logic = generate_enum_min_size_logic(data_enum, has_message_id, &tag);
+            max_size_logic = generate_enum_max_size_logic(data_enum, has_message_id, &tag);
         }
         Data::Union(_) => abort!(ast.span(), "unions are not supported"),
     };
 
-    let generated = match &ast.data {
-        Data::Struct(data_struct) => {
-            let construction = match &data_struct.fields {
-                Fields::Named(_) => quote! { Self #decoded_snippets },
-                Fields::Unnamed(_) => quote! { Self #decoded_snippets },
-                Fields::Unit => quote! { Self {} },
-            };
-            quote! {
-                impl bufferfish::Decodable for #name {
-                    fn decode(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
-                        #message_id_snippet
-                        Self::decode_value(bf)
-                    }
+    let generated = quote! {
+        impl #impl_generics bufferfish::Decodable for #name #ty_generics #where_clause {
+            fn decode(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
+                #message_id_snippet
+                Self::decode_value(bf)
+            }
 
-                    fn decode_value(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
-                        Ok(#construction)
-                    }
+            fn decode_value(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
+                bf.decode_nested(|bf| { #decode_body })
+            }
 
-                    fn min_bytes_required() -> Option<usize> {
-                        #min_size_logic
-                    }
+            fn annotate(
+                bf: &mut bufferfish::Bufferfish,
+                annotation: &mut bufferfish::Annotation,
+            ) -> Result<Self, bufferfish::BufferfishError> {
+                #message_id_annotator
+                Self::annotate_value(bf, annotation)
+            }
 
-                    fn max_bytes_allowed() -> Option<usize> {
-                        #max_size_logic
-                    }
+            fn annotate_value(
+                bf: &mut bufferfish::Bufferfish,
+                annotation: &mut bufferfish::Annotation,
+            ) -> Result<Self, bufferfish::BufferfishError> {
+                bf.decode_nested(|bf| { #annotate_body })
+            }
+
+            fn min_bytes_required() -> Option<usize> {
+                #min_size_logic
+            }
+
+            fn max_bytes_allowed() -> Option<usize> {
+                #max_size_logic
+            }
+
+            fn header_size() -> usize {
+                if #has_message_id { 2 } else { 0 }
+            }
+        }
+
+        #message_impl
+    };
+
+    generated.into()
+}
+
+#[proc_macro_derive(DecodeRef, attributes(bufferfish))]
+#[proc_macro_error]
+pub fn bufferfish_impl_decodable_ref(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let name = &ast.ident;
+
+    let message_id_snippet = generate_message_id_decoder(extract_message_id(&ast));
+    let decode_body = generate_decode_body(&ast, DecodeMode::Borrowed);
+
+    // The input lifetime `'__bf` must outlive every lifetime borrowed by the
+    // type, so that `&'__bf str` can be stored in a `&'a str` field.
+    let mut bf_lifetime = LifetimeParam::new(Lifetime::new("'__bf", Span::call_site()));
+    bf_lifetime
+        .bounds
+        .extend(ast.generics.lifetimes().map(|param| param.lifetime.clone()));
+
+    let mut generics = bounded_generics(&ast, quote! { bufferfish::DecodableRef<'__bf> });
+    generics
+        .params
+        .insert(0, GenericParam::Lifetime(bf_lifetime));
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let generated = quote! {
+        impl #impl_generics bufferfish::DecodableRef<'__bf> for #name #ty_generics #where_clause {
+            fn decode_ref(bf: &mut bufferfish::BufferfishRef<'__bf>) -> Result<Self, bufferfish::BufferfishError> {
+                #message_id_snippet
+                Self::decode_value_ref(bf)
+            }
+
+            fn decode_value_ref(bf: &mut bufferfish::BufferfishRef<'__bf>) -> Result<Self, bufferfish::BufferfishError> {
+                bf.decode_nested(|bf| { #decode_body })
+            }
+        }
+    };
+
+    generated.into()
+}
+
+#[proc_macro_derive(MessageSet)]
+#[proc_macro_error]
+pub fn bufferfish_impl_message_set(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let Data::Enum(data_enum) = &ast.data else {
+        abort!(ast.span(), "a message set must be an enum");
+    };
+
+    let mut variant_idents = Vec::new();
+    let mut message_types = Vec::new();
+
+    for variant in &data_enum.variants {
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                variant_idents.push(&variant.ident);
+                message_types.push(&fields.unnamed[0].ty);
+            }
+            _ => abort!(
+                variant.span(),
+                "message set variants must wrap exactly one message type";
+                help = "use a tuple variant such as `Join(JoinMessage)`"
+            ),
+        }
+    }
+
+    // Decoding picks the first variant with a matching message ID, so any
+    // later variant sharing it could never be decoded.
+    let distinct_ids = quote! {
+        let ids: &[u16] = &[#(<#message_types as bufferfish::Message>::MESSAGE_ID),*];
+        let mut i = 0;
+        while i < ids.len() {
+            let mut j = i + 1;
+            while j < ids.len() {
+                assert!(ids[i] != ids[j], "message set variants must have distinct message IDs");
+                j += 1;
+            }
+            i += 1;
+        }
+    };
+
+    // A generic message set's IDs may depend on its parameters, so they are
+    // only checked once decoding is instantiated.
+    let (distinct_ids_item, distinct_ids_check) = if ast.generics.params.is_empty() {
+        (quote! { const _: () = { #distinct_ids }; }, quote! {})
+    } else {
+        (quote! {}, quote! { const { #distinct_ids } })
+    };
+
+    let generated = quote! {
+        #distinct_ids_item
+
+        impl #impl_generics bufferfish::Encodable for #name #ty_generics #where_clause {
+            fn encode_value(&self, bf: &mut bufferfish::Bufferfish) -> Result<(), bufferfish::BufferfishError> {
+                // The message ID is what identifies the variant, so it is
+                // always written, even when encoded as a nested value.
+                self.encode(bf)
+            }
+
+            fn encode(&self, bf: &mut bufferfish::Bufferfish) -> Result<(), bufferfish::BufferfishError> {
+                match self {
+                    #(Self::#variant_idents(message) => bufferfish::Encodable::encode(message, bf),)*
                 }
             }
         }
-        Data::Enum(_) => {
-            quote! {
-                impl bufferfish::Decodable for #name {
-                    fn decode(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
-                        #message_id_snippet
-                        Self::decode_value(bf)
-                    }
 
-                    fn decode_value(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
-                        let variant_idx = bf.read_u8()?;
-                        #decoded_snippets
-                    }
+        impl #impl_generics bufferfish::Decodable for #name #ty_generics #where_clause {
+            fn decode_value(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
+                Self::decode(bf)
+            }
+
+            fn decode(bf: &mut bufferfish::Bufferfish) -> Result<Self, bufferfish::BufferfishError> {
+                #distinct_ids_check
+                let message_id = bf.peek_u16()?;
 
-                    fn min_bytes_required() -> Option<usize> {
-                        #min_size_logic
+                #(
+                    if message_id == <#message_types as bufferfish::Message>::MESSAGE_ID {
+                        return Ok(Self::#variant_idents(
+                            <#message_types as bufferfish::Decodable>::decode(bf)?,
+                        ));
                     }
+                )*
+
+                Err(bufferfish::BufferfishError::InvalidMessageId)
+            }
+
+            fn annotate_value(
+                bf: &mut bufferfish::Bufferfish,
+                annotation: &mut bufferfish::Annotation,
+            ) -> Result<Self, bufferfish::BufferfishError> {
+                Self::annotate(bf, annotation)
+            }
 
-                    fn max_bytes_allowed() -> Option<usize> {
-                        #max_size_logic
+            fn annotate(
+                bf: &mut bufferfish::Bufferfish,
+                annotation: &mut bufferfish::Annotation,
+            ) -> Result<Self, bufferfish::BufferfishError> {
+                #distinct_ids_check
+                let message_id = bf.peek_u16()?;
+
+                #(
+                    if message_id == <#message_types as bufferfish::Message>::MESSAGE_ID {
+                        return Ok(Self::#variant_idents(
+                            <#message_types as bufferfish::Decodable>::annotate(bf, annotation)?,
+                        ));
                     }
-                }
+                )*
+
+                Err(bufferfish::BufferfishError::InvalidMessageId)
+            }
+
+            fn min_bytes_required() -> Option<usize> {
+                [#(<#message_types as bufferfish::Decodable>::min_bytes_required().unwrap_or(2)),*]
+                    .into_iter()
+                    .min()
+            }
+
+            fn max_bytes_allowed() -> Option<usize> {
+                let mut max_size = Some(0);
+                #(
+                    max_size = max_size.zip(<#message_types as bufferfish::Decodable>::max_bytes_allowed()).map(|(a, b)| a.max(b));
+                )*
+                max_size
             }
         }
-        _ => abort!(ast.span(), "only structs and enums are supported"),
     };
 
     generated.into()
 }
 
+fn generate_message_id_decoder(message_id: Option<Expr>) -> TokenStream {
+    if let Some(message_id) = message_id {
+        quote! {
+            let message_id = bf.read_u16()?;
+            if message_id != u16::from(#message_id) {
+                return Err(bufferfish::BufferfishError::InvalidMessageId);
+            }
+        }
+    } else {
+        quote! {}
+    }
+}
+
+/// Reads and checks the message ID like `generate_message_id_decoder`,
+/// recording it as a `message_id` entry when annotating.
+fn generate_message_id_annotator(message_id: Option<&Expr>) -> TokenStream {
+    let Some(message_id) = message_id else {
+        return quote! {};
+    };
+
+    // Literal IDs are shown as-is; named ones, eg. `MessageId::Join`, are
+    // shown alongside their value.
+    let (value, mismatch) = match message_id {
+        Expr::Lit(_) => (
+            quote! { message_id },
+            quote! { format_args!("{} (expected {})", message_id, expected) },
+        ),
+        _ => {
+            let name = quote! { #message_id }.to_string().replace(' ', "");
+            (
+                quote! { format_args!("{} ({})", message_id, #name) },
+                quote! { format_args!("{} (expected {})", message_id, #name) },
+            )
+        }
+    };
+
+    quote! {
+        annotation.field(bf, "message_id", None, |bf, annotation| {
+            let message_id = bf.read_u16()?;
+            let expected = u16::from(#message_id);
+            if message_id != expected {
+                annotation.value(#mismatch);
+                return Err(bufferfish::BufferfishError::InvalidMessageId);
+            }
+
+            annotation.value(#value);
+
+            Ok(())
+        })?;
+    }
+}
+
+fn generate_decode_body(ast: &DeriveInput, mode: DecodeMode) -> TokenStream {
+    let type_name = ast.ident.to_string();
+
+    match &ast.data {
+        Data::Struct(data_struct) => {
+            let decoded_snippets = generate_struct_field_decoders(&type_name, data_struct, mode);
+            let construction = match &data_struct.fields {
+                Fields::Named(_) => quote! { Self #decoded_snippets },
+                Fields::Unnamed(_) => quote! { Self #decoded_snippets },
+                Fields::Unit => quote! { Self {} },
+            };
+
+            quote! { Ok(#construction) }
+        }
+        Data::Enum(data_enum) => {
+            let tag = EnumTag::from_ast(ast);
+            let read_fn = tag.read_fn();
+            let decoded_snippets =
+                generate_enum_variant_decoders(&type_name, data_enum, mode, &tag);
+
+            let read_tag = match mode {
+                DecodeMode::Annotated => {
+                    let repr = tag.repr.to_string();
+                    let discriminants = tag.discriminants(data_enum);
+                    let variant_names = data_enum.variants.iter().map(|v| v.ident.to_string());
+
+                    quote! {
+                        annotation.field(bf, "variant", Some(#repr), |bf, annotation| {
+                            let variant_idx = bf.#read_fn()?;
+                            let variant_name = match variant_idx {
+                                #(#discriminants => #variant_names,)*
+                                _ => "unknown",
+                            };
+                            annotation.value(format_args!("{} ({})", variant_idx, variant_name));
+
+                            Ok(variant_idx)
+                        })?
+                    }
+                }
+                _ => quote! { bf.#read_fn()? },
+            };
+
+            quote! {
+                let variant_idx = #read_tag;
+                #decoded_snippets
+            }
+        }
+        Data::Union(_) => abort!(ast.span(), "unions are not supported"),
+    }
+}
+
 fn generate_struct_field_encoders(data: &syn::DataStruct) -> Vec<TokenStream> {
     let mut encoded_snippets = Vec::new();
 
@@ -180,13 +1023,21 @@ fn generate_struct_field_encoders(data: &syn::DataStruct) -> Vec<TokenStream> {
                     abort!(field.span(), "named fields are required");
                 };
 
-                encode_type(quote! { self.#ident }, &field.ty, &mut encoded_snippets)
+                if FieldAttrs::is_skipped(field) {
+                    continue;
+                }
+
+                encode_field(quote! { self.#ident }, field, &mut encoded_snippets)
             }
         }
         Fields::Unnamed(fields) => {
             for (i, field) in fields.unnamed.iter().enumerate() {
+                if FieldAttrs::is_skipped(field) {
+                    continue;
+                }
+
                 let index = Index::from(i);
-                encode_type(quote! { self.#index }, &field.ty, &mut encoded_snippets)
+                encode_field(quote! { self.#index }, field, &mut encoded_snippets)
             }
         }
         Fields::Unit => {}
@@ -195,55 +1046,63 @@ fn generate_struct_field_encoders(data: &syn::DataStruct) -> Vec<TokenStream> {
     encoded_snippets
 }
 
-fn generate_enum_variant_encoders(name: &Ident, data_enum: &DataEnum) -> TokenStream {
+fn generate_enum_variant_encoders(
+    name: &Ident,
+    data_enum: &DataEnum,
+    tag: &EnumTag,
+) -> TokenStream {
     let mut arms = Vec::new();
+    let write_fn = tag.write_fn();
 
-    for (idx, variant) in data_enum.variants.iter().enumerate() {
+    for (variant, discrim) in data_enum.variants.iter().zip(tag.discriminants(data_enum)) {
         let v_ident = &variant.ident;
-        let discrim = Literal::u8_unsuffixed(idx as u8);
 
         match &variant.fields {
             Fields::Unit => {
                 arms.push(quote! {
                     #name::#v_ident => {
-                        bf.write_u8(#discrim)?;
+                        bf.#write_fn(#discrim)?;
                     }
                 });
             }
             Fields::Unnamed(fields) => {
-                let idents: Vec<Ident> = (0..fields.unnamed.len())
-                    .map(|i| Ident::new(&format!("f{i}"), Span::call_site()))
-                    .collect();
-
+                let mut patterns = Vec::new();
                 let mut encoders = Vec::new();
                 for (i, field) in fields.unnamed.iter().enumerate() {
-                    let fld = &idents[i];
-                    encode_type(quote! { #fld }, &field.ty, &mut encoders);
+                    if FieldAttrs::is_skipped(field) {
+                        patterns.push(quote! { _ });
+                        continue;
+                    }
+
+                    let fld = Ident::new(&format!("f{i}"), Span::call_site());
+                    encode_field(quote! { #fld }, field, &mut encoders);
+                    patterns.push(quote! { #fld });
                 }
 
                 arms.push(quote! {
-                    #name::#v_ident( #(#idents),* ) => {
-                        bf.write_u8(#discrim)?;
+                    #name::#v_ident( #(#patterns),* ) => {
+                        bf.#write_fn(#discrim)?;
                         #(#encoders)*
                     }
                 });
             }
             Fields::Named(fields) => {
-                let idents: Vec<Ident> = fields
-                    .named
-                    .iter()
-                    .map(|f| f.ident.clone().unwrap())
-                    .collect();
-
+                let mut patterns = Vec::new();
                 let mut encoders = Vec::new();
-                for (i, field) in fields.named.iter().enumerate() {
-                    let fld = &idents[i];
-                    encode_type(quote! { #fld }, &field.ty, &mut encoders);
+                for field in fields.named.iter() {
+                    let fld = field.ident.as_ref().unwrap();
+                    if FieldAttrs::is_skipped(field) {
+                        patterns.push(quote! { #fld: _ });
+                        continue;
+                    }
+
+                    encode_field(quote! { #fld }, field, &mut encoders);
+                    patterns.push(quote! { #fld });
                 }
 
                 arms.push(quote! {
-                    #name::#v_ident { #(#idents),* } => {
-                        bf.write_u8(#discrim)?;
+                    #name::#v_ident { #(#patterns),* } => {
+                        bf.#write_fn(#discrim)?;
                         #(#encoders)*
                     }
                 });
@@ -258,20 +1117,24 @@ fn generate_enum_variant_encoders(name: &Ident, data_enum: &DataEnum) -> TokenSt
     }
 }
 
-fn generate_struct_field_decoders(data: &syn::DataStruct) -> TokenStream {
+fn generate_struct_field_decoders(
+    type_name: &str,
+    data: &syn::DataStruct,
+    mode: DecodeMode,
+) -> TokenStream {
     match &data.fields {
         Fields::Named(fields) => {
             let field_initializers = fields.named.iter().map(|field| {
                 let ident = field.ident.as_ref().expect("named fields required");
-                let ty = &field.ty;
-                quote! { #ident: <#ty as bufferfish::Decodable>::decode_value(bf)?, }
+                let decoder = mode.field_value(type_name, &ident.to_string(), field);
+                quote! { #ident: #decoder, }
             });
             quote! { { #(#field_initializers)* } }
         }
         Fields::Unnamed(fields) => {
-            let field_initializers = fields.unnamed.iter().map(|field| {
-                let ty = &field.ty;
-                quote! { <#ty as bufferfish::Decodable>::decode_value(bf)?, }
+            let field_initializers = fields.unnamed.iter().enumerate().map(|(i, field)| {
+                let decoder = mode.field_value(type_name, &i.to_string(), field);
+                quote! { #decoder, }
             });
             quote! { ( #(#field_initializers)* ) }
         }
@@ -279,12 +1142,16 @@ fn generate_struct_field_decoders(data: &syn::DataStruct) -> TokenStream {
     }
 }
 
-fn generate_enum_variant_decoders(data_enum: &syn::DataEnum) -> TokenStream {
+fn generate_enum_variant_decoders(
+    type_name: &str,
+    data_enum: &syn::DataEnum,
+    mode: DecodeMode,
+    tag: &EnumTag,
+) -> TokenStream {
     let mut arms = Vec::new();
 
-    for (discriminant_value, variant) in data_enum.variants.iter().enumerate() {
+    for (variant, discriminant_lit) in data_enum.variants.iter().zip(tag.discriminants(data_enum)) {
         let variant_ident = &variant.ident;
-        let discriminant_lit = Index::from(discriminant_value);
 
         match &variant.fields {
             Fields::Unit => {
@@ -293,10 +1160,9 @@ fn generate_enum_variant_decoders(data_enum: &syn::DataEnum) -> TokenStream {
             Fields::Unnamed(fields) => {
                 let mut field_decoders = Vec::new();
 
-                for field in fields.unnamed.iter() {
-                    let ty = &field.ty;
-                    field_decoders
-                        .push(quote! { <#ty as bufferfish::Decodable>::decode_value(bf)? });
+                for (i, field) in fields.unnamed.iter().enumerate() {
+                    let name = format!("{variant_ident}.{i}");
+                    field_decoders.push(mode.field_value(type_name, &name, field));
                 }
                 arms.push(quote! {
                     #discriminant_lit => {
@@ -309,10 +1175,9 @@ fn generate_enum_variant_decoders(data_enum: &syn::DataEnum) -> TokenStream {
 
                 for field in fields.named.iter() {
                     let field_ident = field.ident.as_ref().unwrap();
-                    let ty = &field.ty;
-                    field_decoders.push(
-                        quote! { #field_ident: <#ty as bufferfish::Decodable>::decode_value(bf)? },
-                    );
+                    let name = format!("{variant_ident}.{field_ident}");
+                    let decoder = mode.field_value(type_name, &name, field);
+                    field_decoders.push(quote! { #field_ident: #decoder });
                 }
                 arms.push(quote! {
                     #discriminant_lit => {
@@ -333,19 +1198,27 @@ fn generate_enum_variant_decoders(data_enum: &syn::DataEnum) -> TokenStream {
 
 fn generate_struct_min_size_logic(data: &syn::DataStruct, has_message_id: bool) -> TokenStream {
     let struct_min_field_calcs = match &data.fields {
-        Fields::Named(fields) => fields.named.iter().map(|field| {
-            let ty = &field.ty;
-            quote! { min_size += <#ty as bufferfish::Decodable>::min_bytes_required().unwrap_or(0); }
-        }).collect::<Vec<_>>(),
-        Fields::Unnamed(fields) => fields.unnamed.iter().map(|field| {
-            let ty = &field.ty;
-            quote! { min_size += <#ty as bufferfish::Decodable>::min_bytes_required().unwrap_or(0); }
-        }).collect::<Vec<_>>(),
+        Fields::Named(fields) => fields
+            .named
+            .iter()
+            .map(|field| {
+                let field_min = FieldAttrs::min_size(field);
+                quote! { min_size = min_size.saturating_add(#field_min); }
+            })
+            .collect::<Vec<_>>(),
+        Fields::Unnamed(fields) => fields
+            .unnamed
+            .iter()
+            .map(|field| {
+                let field_min = FieldAttrs::min_size(field);
+                quote! { min_size = min_size.saturating_add(#field_min); }
+            })
+            .collect::<Vec<_>>(),
         Fields::Unit => Vec::new(),
     };
 
     quote! {
-        let mut min_size = if #has_message_id { 2 } else { 0 };
+        let mut min_size: usize = if #has_message_id { 2 } else { 0 };
         #(#struct_min_field_calcs)*
         Some(min_size)
     }
@@ -356,12 +1229,12 @@ fn generate_struct_max_size_logic(data: &syn::DataStruct, has_message_id: bool)
         Fields::Named(fields) => fields
             .named
             .iter()
-            .map(|field| generate_max_size_field_calc(&field.ty))
+            .map(generate_max_size_field_calc)
             .collect::<Vec<_>>(),
         Fields::Unnamed(fields) => fields
             .unnamed
             .iter()
-            .map(|field| generate_max_size_field_calc(&field.ty))
+            .map(generate_max_size_field_calc)
             .collect::<Vec<_>>(),
         Fields::Unit => Vec::new(),
     };
@@ -373,15 +1246,21 @@ fn generate_struct_max_size_logic(data: &syn::DataStruct, has_message_id: bool)
     }
 }
 
-fn generate_max_size_field_calc(ty: &Type) -> TokenStream {
+fn generate_max_size_field_calc(field: &Field) -> TokenStream {
+    let field_max = FieldAttrs::max_size(field);
     quote! {
         current_max_size = current_max_size.and_then(|acc_val| {
-            <#ty as bufferfish::Decodable>::max_bytes_allowed().map(|field_m| acc_val + field_m)
+            #field_max.and_then(|field_m| acc_val.checked_add(field_m))
         });
     }
 }
 
-fn generate_enum_min_size_logic(data: &syn::DataEnum, has_message_id: bool) -> TokenStream {
+fn generate_enum_min_size_logic(
+    data: &syn::DataEnum,
+    has_message_id: bool,
+    tag: &EnumTag,
+) -> TokenStream {
+    let tag_size = tag.size;
     let mut variant_min_field_sizes_calcs = Vec::new();
 
     for variant in data.variants.iter() {
@@ -393,48 +1272,43 @@ fn generate_enum_min_size_logic(data: &syn::DataEnum, has_message_id: bool) -> T
                 let mut current_variant_min_field_calcs = Vec::new();
 
                 for field in fields.unnamed.iter() {
-                    let ty = &field.ty;
-                    current_variant_min_field_calcs.push(quote! { <#ty as bufferfish::Decodable>::min_bytes_required().unwrap_or(0) });
+                    current_variant_min_field_calcs.push(FieldAttrs::min_size(field));
                 }
-                variant_min_field_sizes_calcs
-                    .push(quote! { 0 #( + #current_variant_min_field_calcs)* });
+                variant_min_field_sizes_calcs.push(
+                    quote! { 0usize #( .saturating_add(#current_variant_min_field_calcs) )* },
+                );
             }
             Fields::Named(fields) => {
                 let mut current_variant_min_field_calcs = Vec::new();
 
                 for field in fields.named.iter() {
-                    let ty = &field.ty;
-                    current_variant_min_field_calcs.push(quote! { <#ty as bufferfish::Decodable>::min_bytes_required().unwrap_or(0) });
+                    current_variant_min_field_calcs.push(FieldAttrs::min_size(field));
                 }
-                variant_min_field_sizes_calcs
-                    .push(quote! { 0 #( + #current_variant_min_field_calcs)* });
+                variant_min_field_sizes_calcs.push(
+                    quote! { 0usize #( .saturating_add(#current_variant_min_field_calcs) )* },
+                );
             }
         }
     }
 
     quote! {
-        let mut min_total_size = if #has_message_id { 2 } else { 0 };
-        min_total_size += 1;
+        let mut min_total_size: usize = if #has_message_id { 2 } else { 0 };
+        min_total_size += #tag_size;
 
-        let mut min_variant_fields_contribution = usize::MAX;
-        if [#(#variant_min_field_sizes_calcs),*].is_empty() {
-            min_variant_fields_contribution = 0;
-        } else {
-            for size_calc in [#(#variant_min_field_sizes_calcs),*] {
-                if size_calc < min_variant_fields_contribution {
-                    min_variant_fields_contribution = size_calc;
-                }
-            }
-        }
-        if min_variant_fields_contribution == usize::MAX {
-             min_variant_fields_contribution = 0;
-        }
-        min_total_size += min_variant_fields_contribution;
+        let variant_min_field_sizes: &[usize] = &[#(#variant_min_field_sizes_calcs),*];
+        let min_variant_fields_contribution =
+            variant_min_field_sizes.iter().copied().min().unwrap_or(0);
+        min_total_size = min_total_size.saturating_add(min_variant_fields_contribution);
         Some(min_total_size)
     }
 }
 
-fn generate_enum_max_size_logic(data: &syn::DataEnum, has_message_id: bool) -> TokenStream {
+fn generate_enum_max_size_logic(
+    data: &syn::DataEnum,
+    has_message_id: bool,
+    tag: &EnumTag,
+) -> TokenStream {
+    let tag_size = tag.size;
     let variant_max_field_sizes_calcs: Vec<TokenStream> = data
         .variants
         .iter()
@@ -445,7 +1319,7 @@ fn generate_enum_max_size_logic(data: &syn::DataEnum, has_message_id: bool) -> T
         let mut max_total_size_opt: Option<usize> = Some(if #has_message_id { 2 } else { 0 });
 
         if let Some(current_max) = max_total_size_opt {
-            max_total_size_opt = Some(current_max + 1);
+            max_total_size_opt = current_max.checked_add(#tag_size);
         } else {
             return None;
         }
@@ -472,7 +1346,7 @@ fn generate_enum_max_size_logic(data: &syn::DataEnum, has_message_id: bool) -> T
 
         if let Some(current_total_max) = max_total_size_opt {
             if let Some(fields_max) = overall_max_variant_fields_size {
-                max_total_size_opt = Some(current_total_max + fields_max);
+                max_total_size_opt = current_total_max.checked_add(fields_max);
             } else {
                 max_total_size_opt = None;
             }
@@ -486,20 +1360,13 @@ fn generate_enum_variant_max_size_calc(variant: &syn::Variant) -> TokenStream {
     match &variant.fields {
         Fields::Unit => quote! { Some(0) },
         Fields::Unnamed(fields) => {
-            let field_calcs: Vec<_> = fields
-                .unnamed
-                .iter()
-                .map(|f| {
-                    let ty = &f.ty;
-                    quote! { <#ty as bufferfish::Decodable>::max_bytes_allowed() }
-                })
-                .collect();
+            let field_calcs: Vec<_> = fields.unnamed.iter().map(FieldAttrs::max_size).collect();
             quote! {{
                 let mut acc: Option<usize> = Some(0);
                 #(
                     if let Some(sum) = acc {
                         if let Some(val) = #field_calcs {
-                            acc = Some(sum + val);
+                            acc = sum.checked_add(val);
                         } else {
                             acc = None;
                         }
@@ -509,20 +1376,13 @@ fn generate_enum_variant_max_size_calc(variant: &syn::Variant) -> TokenStream {
             }}
         }
         Fields::Named(fields) => {
-            let field_calcs: Vec<_> = fields
-                .named
-                .iter()
-                .map(|f| {
-                    let ty = &f.ty;
-                    quote! { <#ty as bufferfish::Decodable>::max_bytes_allowed() }
-                })
-                .collect();
+            let field_calcs: Vec<_> = fields.named.iter().map(FieldAttrs::max_size).collect();
             quote! {{
                 let mut acc: Option<usize> = Some(0);
                 #(
                     if let Some(sum) = acc {
                         if let Some(val) = #field_calcs {
-                            acc = Some(sum + val);
+                            acc = sum.checked_add(val);
                         } else {
                             acc = None;
                         }
@@ -534,6 +1394,47 @@ fn generate_enum_variant_max_size_calc(variant: &syn::Variant) -> TokenStream {
     }
 }
 
+fn encode_field(accessor: TokenStream, field: &Field, dst: &mut Vec<TokenStream>) {
+    let attrs = FieldAttrs::from_field(field);
+    if let Some(prefix) = &attrs.length_prefix {
+        let mut stmts = Vec::new();
+        encode_value(accessor, field, &attrs, &mut stmts);
+        dst.push(quote! {
+            bf.with_length_prefix(#prefix, |bf| {
+                #(#stmts)*
+                Ok(())
+            })?;
+        });
+        return;
+    }
+
+    encode_value(accessor, field, &attrs, dst);
+}
+
+/// Pushes the statements writing a field's value, without applying its
+/// length prefix.
+fn encode_value(
+    accessor: TokenStream,
+    field: &Field,
+    attrs: &FieldAttrs,
+    dst: &mut Vec<TokenStream>,
+) {
+    if let Some(varint) = &attrs.varint {
+        dst.push(varint.encoder(accessor));
+        return;
+    }
+
+    match &attrs.with {
+        Some(with) => dst.push(quote! {
+            #with::encode(&#accessor, bf)?;
+        }),
+        None if attrs.with_header => dst.push(quote! {
+            (#accessor).encode(bf)?;
+        }),
+        None => encode_type(accessor, &field.ty, dst),
+    }
+}
+
 fn encode_type(accessor: TokenStream, field_type: &Type, dst: &mut Vec<TokenStream>) {
     let effective_type = if let Type::Reference(type_ref) = field_type {
         &*type_ref.elem
@@ -544,7 +1445,7 @@ fn encode_type(accessor: TokenStream, field_type: &Type, dst: &mut Vec<TokenStre
     match effective_type {
         Type::Path(TypePath { path, .. }) if path.is_ident("String") => {
             dst.push(quote! {
-                (#accessor).encode(bf)?;
+                (#accessor).encode_value(bf)?;
             });
         }
         Type::Path(TypePath { path, .. })
@@ -556,29 +1457,43 @@ fn encode_type(accessor: TokenStream, field_type: &Type, dst: &mut Vec<TokenStre
                 || path.is_ident("i16")
                 || path.is_ident("i32")
                 || path.is_ident("i64")
+                || path.is_ident("f32")
+                || path.is_ident("f64")
                 || path.is_ident("bool") =>
         {
             dst.push(quote! {
-                (#accessor).encode(bf)?;
+                (#accessor).encode_value(bf)?;
             });
         }
         Type::Path(TypePath { path, .. })
             if path.segments.len() == 1 && path.segments[0].ident == "Vec" =>
         {
             dst.push(quote! {
-                (#accessor).encode(bf)?;
+                (#accessor).encode_value(bf)?;
             });
         }
         Type::Array(_type_array) => {
             dst.push(quote! {
-                (#accessor).encode(bf)?;
+                (#accessor).encode_value(bf)?;
+            });
+        }
+        Type::Tuple(_type_tuple) => {
+            dst.push(quote! {
+                (#accessor).encode_value(bf)?;
+            });
+        }
+        Type::Slice(_type_slice) => {
+            dst.push(quote! {
+                (#accessor).encode_value(bf)?;
             });
         }
         Type::Path(TypePath { .. }) => {
             // Catch-all for other user-defined types (structs/enums)
-            // These are assumed to implement Encodable.
+            // These are assumed to implement Encodable. Nested types are
+            // written without their message ID header, mirroring the
+            // `decode_value` calls on the decoding side.
             dst.push(quote! {
-                (#accessor).encode(bf)?;
+                (#accessor).encode_value(bf)?;
             });
         }
         _ => abort!(
@@ -0,0 +1,566 @@
+//! Compatibility checks between two versions of a protocol schema.
+//!
+//! Clients and servers built from different versions of a protocol can only
+//! talk to each other if the wire format has not changed underneath them.
+//! `check` compares a baseline `Schema` (eg. one committed to the repository)
+//! against the current one and reports every change that would break
+//! decoding, so a build can fail before the breakage ships.
+
+use std::collections::HashMap;
+
+use crate::compiler::schema::{FieldDef, FieldsKind, MessageIdDef, Schema, WireType};
+
+/// A change between two schemas that stops one side from decoding messages
+/// encoded by the other.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Incompatibility {
+    /// A struct or enum no longer exists.
+    TypeRemoved { name: String },
+    /// A field no longer exists.
+    FieldRemoved { owner: String, field: String },
+    /// A field was added, so older decoders do not read it.
+    FieldAdded { owner: String, field: String },
+    /// Fields kept between versions are written in a different order.
+    FieldsReordered { owner: String },
+    /// A field is written as a different wire type.
+    FieldTypeChanged {
+        owner: String,
+        field: String,
+        old: WireType,
+        new: WireType,
+    },
+    /// A field's `with` codec, `with_header`, `varint` or `length_prefix`
+    /// option changed.
+    FieldCodecChanged { owner: String, field: String },
+    /// A struct's message ID changed, was added or was removed.
+    MessageIdChanged {
+        name: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    /// A message ID now belongs to a different struct.
+    MessageIdReused {
+        id: String,
+        old: String,
+        new: String,
+    },
+    /// Two structs in the new schema share a message ID.
+    DuplicateMessageId {
+        id: String,
+        first: String,
+        second: String,
+    },
+    /// An enum's tag type (its `repr`) changed.
+    EnumReprChanged {
+        name: String,
+        old: WireType,
+        new: WireType,
+    },
+    /// An enum variant no longer exists.
+    VariantRemoved { name: String, variant: String },
+    /// An enum variant is written with a different tag.
+    VariantTagChanged {
+        name: String,
+        variant: String,
+        old: i128,
+        new: i128,
+    },
+}
+
+impl std::fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Incompatibility::TypeRemoved { name } => write!(f, "type `{name}` was removed"),
+            Incompatibility::FieldRemoved { owner, field } => {
+                write!(f, "field `{owner}.{field}` was removed")
+            }
+            Incompatibility::FieldAdded { owner, field } => {
+                write!(f, "field `{owner}.{field}` was added")
+            }
+            Incompatibility::FieldsReordered { owner } => {
+                write!(f, "fields of `{owner}` were reordered")
+            }
+            Incompatibility::FieldTypeChanged {
+                owner,
+                field,
+                old,
+                new,
+            } => write!(
+                f,
+                "field `{owner}.{field}` changed type from `{old}` to `{new}`"
+            ),
+            Incompatibility::FieldCodecChanged { owner, field } => write!(
+                f,
+                "field `{owner}.{field}` changed its `with`, `with_header`, `varint` or `length_prefix` option"
+            ),
+            Incompatibility::MessageIdChanged { name, old, new } => write!(
+                f,
+                "message id of `{name}` changed from {} to {}",
+                old.as_deref().unwrap_or("none"),
+                new.as_deref().unwrap_or("none")
+            ),
+            Incompatibility::MessageIdReused { id, old, new } => {
+                write!(f, "message id {id} moved from `{old}` to `{new}`")
+            }
+            Incompatibility::DuplicateMessageId { id, first, second } => {
+                write!(
+                    f,
+                    "message id {id} is used by both `{first}` and `{second}`"
+                )
+            }
+            Incompatibility::EnumReprChanged { name, old, new } => {
+                write!(f, "tag of enum `{name}` changed from `{old}` to `{new}`")
+            }
+            Incompatibility::VariantRemoved { name, variant } => {
+                write!(f, "variant `{name}::{variant}` was removed")
+            }
+            Incompatibility::VariantTagChanged {
+                name,
+                variant,
+                old,
+                new,
+            } => write!(
+                f,
+                "variant `{name}::{variant}` changed tag from {old} to {new}"
+            ),
+        }
+    }
+}
+
+/// Compares an `old` schema against a `new` one, returning every change that
+/// breaks the wire format. An empty result means the two are compatible.
+///
+/// Types and enum variants are matched by name, and fields by name (or by
+/// position for tuple fields). New types and variants are not reported, as
+/// messages using them were never sent by the old side.
+pub fn check(old: &Schema, new: &Schema) -> Vec<Incompatibility> {
+    let mut incompatibilities = Vec::new();
+
+    for old_struct in &old.structs {
+        let Some(new_struct) = new.get_struct(&old_struct.name) else {
+            incompatibilities.push(Incompatibility::TypeRemoved {
+                name: old_struct.name.clone(),
+            });
+            continue;
+        };
+
+        let old_id = old_struct.message_id.as_ref().map(get_message_id_key);
+        let new_id = new_struct.message_id.as_ref().map(get_message_id_key);
+        if old_id != new_id {
+            incompatibilities.push(Incompatibility::MessageIdChanged {
+                name: old_struct.name.clone(),
+                old: old_id,
+                new: new_id,
+            });
+        }
+
+        check_fields(
+            &old_struct.name,
+            (old_struct.kind, &old_struct.fields),
+            (new_struct.kind, &new_struct.fields),
+            &mut incompatibilities,
+        );
+    }
+
+    check_message_ids(old, new, &mut incompatibilities);
+
+    for old_enum in &old.enums {
+        let Some(new_enum) = new.get_enum(&old_enum.name) else {
+            incompatibilities.push(Incompatibility::TypeRemoved {
+                name: old_enum.name.clone(),
+            });
+            continue;
+        };
+
+        if old_enum.tag != new_enum.tag {
+            incompatibilities.push(Incompatibility::EnumReprChanged {
+                name: old_enum.name.clone(),
+                old: old_enum.tag.clone(),
+                new: new_enum.tag.clone(),
+            });
+        }
+
+        for old_variant in &old_enum.variants {
+            let Some(new_variant) = new_enum
+                .variants
+                .iter()
+                .find(|v| v.name == old_variant.name)
+            else {
+                incompatibilities.push(Incompatibility::VariantRemoved {
+                    name: old_enum.name.clone(),
+                    variant: old_variant.name.clone(),
+                });
+                continue;
+            };
+
+            if old_variant.discriminant != new_variant.discriminant {
+                incompatibilities.push(Incompatibility::VariantTagChanged {
+                    name: old_enum.name.clone(),
+                    variant: old_variant.name.clone(),
+                    old: old_variant.discriminant,
+                    new: new_variant.discriminant,
+                });
+            }
+
+            check_fields(
+                &format!("{}::{}", old_enum.name, old_variant.name),
+                (old_variant.kind, &old_variant.fields),
+                (new_variant.kind, &new_variant.fields),
+                &mut incompatibilities,
+            );
+        }
+    }
+
+    incompatibilities
+}
+
+/// Returns a message ID as its numeric value if it was resolved, otherwise as
+/// the expression it was written with.
+fn get_message_id_key(message_id: &MessageIdDef) -> String {
+    match message_id.value {
+        Some(value) => value.to_string(),
+        None => message_id.expr.clone(),
+    }
+}
+
+/// Reports message IDs that moved to a different struct between versions, or
+/// that are shared by more than one struct in the new schema.
+fn check_message_ids(old: &Schema, new: &Schema, incompatibilities: &mut Vec<Incompatibility>) {
+    let old_owners = old
+        .structs
+        .iter()
+        .filter_map(|s| Some((get_message_id_key(s.message_id.as_ref()?), &s.name)))
+        .collect::<HashMap<_, _>>();
+    let mut new_owners = HashMap::new();
+
+    for new_struct in &new.structs {
+        let Some(id) = new_struct.message_id.as_ref().map(get_message_id_key) else {
+            continue;
+        };
+
+        if let Some(old_owner) = old_owners.get(&id)
+            && **old_owner != new_struct.name
+        {
+            incompatibilities.push(Incompatibility::MessageIdReused {
+                id: id.clone(),
+                old: old_owner.to_string(),
+                new: new_struct.name.clone(),
+            });
+        }
+
+        if let Some(first) = new_owners.insert(id.clone(), &new_struct.name) {
+            incompatibilities.push(Incompatibility::DuplicateMessageId {
+                id,
+                first: first.clone(),
+                second: new_struct.name.clone(),
+            });
+        }
+    }
+}
+
+/// Compares the fields of a struct or enum variant. Fields are matched by
+/// name, unless the owner changed between named and tuple fields, in which
+/// case only their positions can be compared.
+fn check_fields(
+    owner: &str,
+    (old_kind, old_fields): (FieldsKind, &[FieldDef]),
+    (new_kind, new_fields): (FieldsKind, &[FieldDef]),
+    incompatibilities: &mut Vec<Incompatibility>,
+) {
+    let by_position = old_kind != new_kind;
+    let get_key = |i: usize, field: &FieldDef| match &field.name {
+        Some(name) if !by_position => name.clone(),
+        _ => i.to_string(),
+    };
+
+    let old_keys = old_fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| get_key(i, field))
+        .collect::<Vec<_>>();
+    let new_keys = new_fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| get_key(i, field))
+        .collect::<Vec<_>>();
+
+    for (key, old_field) in old_keys.iter().zip(old_fields) {
+        let Some(position) = new_keys.iter().position(|k| k == key) else {
+            incompatibilities.push(Incompatibility::FieldRemoved {
+                owner: owner.to_string(),
+                field: key.clone(),
+            });
+            continue;
+        };
+
+        let new_field = &new_fields[position];
+        if old_field.ty != new_field.ty {
+            incompatibilities.push(Incompatibility::FieldTypeChanged {
+                owner: owner.to_string(),
+                field: key.clone(),
+                old: old_field.ty.clone(),
+                new: new_field.ty.clone(),
+            });
+        } else if old_field.codec != new_field.codec
+            || old_field.with_header != new_field.with_header
+            || old_field.varint != new_field.varint
+            || old_field.length_prefix != new_field.length_prefix
+        {
+            incompatibilities.push(Incompatibility::FieldCodecChanged {
+                owner: owner.to_string(),
+                field: key.clone(),
+            });
+        }
+    }
+
+    let kept_old = old_keys.iter().filter(|k| new_keys.contains(k));
+    let kept_new = new_keys.iter().filter(|k| old_keys.contains(k));
+    if !kept_old.eq(kept_new) {
+        incompatibilities.push(Incompatibility::FieldsReordered {
+            owner: owner.to_string(),
+        });
+    }
+
+    for key in new_keys.iter().filter(|k| !old_keys.contains(k)) {
+        incompatibilities.push(Incompatibility::FieldAdded {
+            owner: owner.to_string(),
+            field: key.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::schema::build_schema;
+
+    fn schema(src: &str) -> Schema {
+        build_schema(syn::parse_file(src).unwrap().items)
+    }
+
+    const BASELINE: &str = r#"
+#[derive(Encode)]
+#[repr(u16)]
+pub enum MessageId {
+    Join = 0,
+    Leave = 1,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub id: u32,
+    pub name: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Leave)]
+pub struct LeaveMessage(u32);
+
+#[derive(Encode, Decode)]
+pub enum Shape {
+    Point,
+    Circle { radius: f32 },
+}
+    "#;
+
+    #[test]
+    fn test_identical_schemas_are_compatible() {
+        assert_eq!(check(&schema(BASELINE), &schema(BASELINE)), vec![]);
+    }
+
+    #[test]
+    fn test_additions_are_compatible() {
+        let new = BASELINE.to_string()
+            + r#"
+#[derive(Encode, Decode)]
+pub struct Extra {
+    pub value: u8,
+}
+"#;
+        let new = new.replace(
+            "    Circle { radius: f32 },",
+            "    Circle { radius: f32 },\n    Square,",
+        );
+
+        assert_eq!(check(&schema(BASELINE), &schema(&new)), vec![]);
+    }
+
+    #[test]
+    fn test_field_changes() {
+        let new = BASELINE.replace(
+            "    pub id: u32,\n    pub name: String,\n    pub tags: Vec<String>,",
+            "    pub name: String,\n    pub id: u64,\n    pub level: u8,",
+        );
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![
+                Incompatibility::FieldTypeChanged {
+                    owner: "JoinMessage".to_string(),
+                    field: "id".to_string(),
+                    old: WireType::U32,
+                    new: WireType::U64,
+                },
+                Incompatibility::FieldRemoved {
+                    owner: "JoinMessage".to_string(),
+                    field: "tags".to_string(),
+                },
+                Incompatibility::FieldsReordered {
+                    owner: "JoinMessage".to_string(),
+                },
+                Incompatibility::FieldAdded {
+                    owner: "JoinMessage".to_string(),
+                    field: "level".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_varint_changes() {
+        let new = BASELINE.replace(
+            "    pub id: u32,",
+            "    #[bufferfish(varint)]\n    pub id: u32,",
+        );
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![Incompatibility::FieldCodecChanged {
+                owner: "JoinMessage".to_string(),
+                field: "id".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_length_prefix_changes() {
+        let new = BASELINE.replace(
+            "    pub tags: Vec<String>,",
+            "    #[bufferfish(length_prefix = \"u32\")]\n    pub tags: Vec<String>,",
+        );
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![Incompatibility::FieldCodecChanged {
+                owner: "JoinMessage".to_string(),
+                field: "tags".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_message_id_changes() {
+        let new = BASELINE
+            .replace(
+                "#[bufferfish(MessageId::Leave)]",
+                "#[bufferfish(MessageId::Join)]",
+            )
+            .replace(
+                "#[bufferfish(MessageId::Join)]\npub struct JoinMessage",
+                "#[bufferfish(2_u16)]\npub struct JoinMessage",
+            );
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![
+                Incompatibility::MessageIdChanged {
+                    name: "JoinMessage".to_string(),
+                    old: Some("0".to_string()),
+                    new: Some("2".to_string()),
+                },
+                Incompatibility::MessageIdChanged {
+                    name: "LeaveMessage".to_string(),
+                    old: Some("1".to_string()),
+                    new: Some("0".to_string()),
+                },
+                Incompatibility::MessageIdReused {
+                    id: "0".to_string(),
+                    old: "JoinMessage".to_string(),
+                    new: "LeaveMessage".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_message_ids() {
+        let new = BASELINE.replace("#[bufferfish(MessageId::Leave)]", "#[bufferfish(0_u16)]");
+
+        let incompatibilities = check(&schema(BASELINE), &schema(&new));
+        assert!(
+            incompatibilities.contains(&Incompatibility::DuplicateMessageId {
+                id: "0".to_string(),
+                first: "JoinMessage".to_string(),
+                second: "LeaveMessage".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_enum_changes() {
+        let new = BASELINE.replace("#[repr(u16)]", "#[repr(u8)]").replace(
+            "    Point,\n    Circle { radius: f32 },",
+            "    Circle { radius: f64 },",
+        );
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![
+                Incompatibility::EnumReprChanged {
+                    name: "MessageId".to_string(),
+                    old: WireType::U16,
+                    new: WireType::U8,
+                },
+                Incompatibility::VariantRemoved {
+                    name: "Shape".to_string(),
+                    variant: "Point".to_string(),
+                },
+                Incompatibility::VariantTagChanged {
+                    name: "Shape".to_string(),
+                    variant: "Circle".to_string(),
+                    old: 1,
+                    new: 0,
+                },
+                Incompatibility::FieldTypeChanged {
+                    owner: "Shape::Circle".to_string(),
+                    field: "radius".to_string(),
+                    old: WireType::F32,
+                    new: WireType::F64,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removed_types() {
+        let new = BASELINE.replace("#[derive(Encode, Decode)]\n#[bufferfish(MessageId::Leave)]\npub struct LeaveMessage(u32);", "");
+
+        assert_eq!(
+            check(&schema(BASELINE), &schema(&new)),
+            vec![Incompatibility::TypeRemoved {
+                name: "LeaveMessage".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_incompatibility_display() {
+        let incompatibility = Incompatibility::FieldTypeChanged {
+            owner: "JoinMessage".to_string(),
+            field: "tags".to_string(),
+            old: WireType::Array {
+                item: Box::new(WireType::String),
+            },
+            new: WireType::Option {
+                item: Box::new(WireType::U8),
+            },
+        };
+
+        assert_eq!(
+            incompatibility.to_string(),
+            "field `JoinMessage.tags` changed type from `Vec<String>` to `Option<u8>`"
+        );
+    }
+}
@@ -7,17 +7,96 @@ use std::{
     path::Path,
 };
 
+use quote::ToTokens;
 use syn::{
-    Attribute, Expr, ExprLit, Fields, GenericArgument, Item, ItemEnum, ItemStruct, Lit, Meta,
-    PathArguments, Type, TypePath,
+    Attribute, Expr, ExprLit, ExprUnary, Field, Fields, GenericArgument, Generics, Item, ItemEnum,
+    ItemStruct, Lit, LitStr, Meta, PathArguments, Type, TypePath, UnOp, parse_quote,
+    spanned::Spanned,
 };
 
+#[cfg(feature = "schema")]
+pub mod schema;
+
 /// Generate a TypeScript file at `output_dst` from a directory of Rust source
 /// files at `src_path`.
 ///
 /// Requires Rust types to be annotated with `#[derive(Encode)]` and/or
 /// `#[derive(Decode]` macros.
 pub fn generate(src_path: &str, output_dst: &str) -> io::Result<()> {
+    let output = generate_to_string(src_path)?;
+    write_typescript_file(output_dst, &output)?;
+
+    Ok(())
+}
+
+/// Generate the contents of a TypeScript file from a directory of Rust source
+/// files at `src_path`, without writing it anywhere.
+pub fn generate_to_string(src_path: &str) -> io::Result<String> {
+    let files = collect_source_files(src_path)?;
+
+    let mut output = String::new();
+    generate_output_string(files, &mut output)?;
+
+    Ok(output)
+}
+
+/// A field or enum the TypeScript compiler is unable to generate code for,
+/// and where it was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// The name of the struct or enum the problem was found in.
+    pub item: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: in `{}`: {}",
+            self.file, self.line, self.column, self.item, self.message
+        )
+    }
+}
+
+/// Check a directory of Rust source files at `src_path` for types that
+/// `generate` can not produce working TypeScript for: types which do not
+/// derive `Encode` anywhere in the directory, module-qualified paths, arrays
+/// without a literal length, non-integer enum reprs, `varint` or
+/// `length_prefix` attributes on fields that can't use them, and message set
+/// variants wrapping a type with no message ID.
+///
+/// Fields using a `with` codec are not checked, as their TypeScript is
+/// provided separately.
+pub fn diagnose(src_path: &str) -> io::Result<Vec<Diagnostic>> {
+    let mut files = Vec::new();
+    for path in collect_source_files(src_path)? {
+        let items = parse_rust_source_file(&path)?;
+        files.push((path, items));
+    }
+
+    Ok(get_diagnostics(files))
+}
+
+/// Build a `Schema` describing the protocol defined by a directory of Rust
+/// source files at `src_path`.
+///
+/// Requires Rust types to be annotated with `#[derive(Encode)]` and/or
+/// `#[derive(MessageSet)]` macros.
+#[cfg(feature = "schema")]
+pub fn schema(src_path: &str) -> io::Result<schema::Schema> {
+    let mut items = Vec::new();
+    for path in collect_source_files(src_path)? {
+        items.extend(parse_rust_source_file(&path)?);
+    }
+
+    Ok(schema::build_schema(items))
+}
+
+fn collect_source_files(src_path: &str) -> io::Result<Vec<String>> {
     let mut files = Vec::new();
 
     fn visit_dirs(dir: &std::path::Path, files: &mut Vec<String>) -> io::Result<()> {
@@ -37,11 +116,7 @@ pub fn generate(src_path: &str, output_dst: &str) -> io::Result<()> {
 
     visit_dirs(std::path::Path::new(src_path), &mut files)?;
 
-    let mut output = String::new();
-    generate_output_string(files, &mut output)?;
-    write_typescript_file(output_dst, &output)?;
-
-    Ok(())
+    Ok(files)
 }
 
 fn snake_to_camel_case(s: String) -> String {
@@ -82,50 +157,54 @@ fn parse_rust_source_file(path: &str) -> io::Result<Vec<Item>> {
     file.read_to_string(&mut content)?;
 
     let syntax_tree = syn::parse_file(&content).map_err(|e| {
-        eprintln!("Failed to parse Rust source file: {e}");
+        let start = e.span().start();
         io::Error::new(
             io::ErrorKind::InvalidData,
-            "Failed to parse Rust source file",
+            format!(
+                "failed to parse Rust source file {path}:{}:{}: {e}",
+                start.line,
+                start.column + 1
+            ),
         )
     })?;
 
     Ok(syntax_tree.items)
 }
 
-fn get_items_implementing_encode(items: Vec<Item>) -> (Vec<ItemStruct>, Vec<ItemEnum>) {
-    let mut structs = Vec::new();
-    let mut enums = Vec::new();
-
-    let has_encode = |attrs: &[Attribute]| -> bool {
-        for attr in attrs {
-            if attr.path().is_ident("derive") {
-                match &attr.meta {
-                    Meta::List(list) => {
-                        for item in list.tokens.clone() {
-                            if item.to_string().contains("Encode") {
-                                return true;
-                            }
+fn has_derive(attrs: &[Attribute], derive: &str) -> bool {
+    for attr in attrs {
+        if attr.path().is_ident("derive") {
+            match &attr.meta {
+                Meta::List(list) => {
+                    for item in list.tokens.clone() {
+                        if item.to_string().contains(derive) {
+                            return true;
                         }
                     }
-                    _ => return false,
                 }
+                _ => return false,
             }
         }
+    }
 
-        false
-    };
+    false
+}
+
+fn get_items_implementing_encode(items: Vec<Item>) -> (Vec<ItemStruct>, Vec<ItemEnum>) {
+    let mut structs = Vec::new();
+    let mut enums = Vec::new();
 
     for item in items {
         match item {
-            Item::Struct(item_struct) => {
-                if has_encode(&item_struct.attrs) {
-                    structs.push(item_struct);
-                }
+            Item::Struct(mut item_struct) if has_derive(&item_struct.attrs, "Encode") => {
+                strip_skipped_fields(&mut item_struct.fields);
+                structs.push(item_struct);
             }
-            Item::Enum(item_enum) => {
-                if has_encode(&item_enum.attrs) {
-                    enums.push(item_enum);
+            Item::Enum(mut item_enum) if has_derive(&item_enum.attrs, "Encode") => {
+                for variant in &mut item_enum.variants {
+                    strip_skipped_fields(&mut variant.fields);
                 }
+                enums.push(item_enum);
             }
             _ => {}
         }
@@ -134,77 +213,463 @@ fn get_items_implementing_encode(items: Vec<Item>) -> (Vec<ItemStruct>, Vec<Item
     (structs, enums)
 }
 
+/// Remove fields annotated with `#[bufferfish(skip)]`, as they are never
+/// written to the wire and so have no TypeScript counterpart.
+fn strip_skipped_fields(fields: &mut Fields) {
+    match fields {
+        Fields::Named(fields_named) => {
+            fields_named.named = std::mem::take(&mut fields_named.named)
+                .into_iter()
+                .filter(|field| !is_skipped_field(field))
+                .collect();
+        }
+        Fields::Unnamed(fields_unnamed) => {
+            fields_unnamed.unnamed = std::mem::take(&mut fields_unnamed.unnamed)
+                .into_iter()
+                .filter(|field| !is_skipped_field(field))
+                .collect();
+        }
+        Fields::Unit => {}
+    }
+}
+
+/// Field-level `#[bufferfish(..)]` options.
+#[derive(Default)]
+struct FieldOptions {
+    skip: bool,
+    with: Option<String>,
+    with_header: bool,
+    varint: bool,
+    length_prefix: Option<String>,
+    ts_type: Option<String>,
+    ts_module: Option<String>,
+}
+
+fn get_field_options(field: &Field) -> FieldOptions {
+    let mut options = FieldOptions::default();
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("bufferfish") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else if meta.path.is_ident("default") {
+                    meta.value()?.parse::<Expr>()?;
+                } else if meta.path.is_ident("with_header") {
+                    options.with_header = true;
+                } else if meta.path.is_ident("varint") {
+                    options.varint = true;
+                } else if meta.path.is_ident("length_prefix") {
+                    options.length_prefix = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("with") {
+                    options.with = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("ts_type") {
+                    options.ts_type = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("ts_module") {
+                    options.ts_module = Some(meta.value()?.parse::<LitStr>()?.value());
+                }
+
+                Ok(())
+            });
+        }
+    }
+
+    options
+}
+
+fn is_skipped_field(field: &Field) -> bool {
+    get_field_options(field).skip
+}
+
+/// Returns the TypeScript type name for a field using a `with` codec. This is
+/// `ts_type` if given, otherwise the last segment of the Rust type (eg.
+/// `uuid::Uuid` becomes `Uuid`). The generated code expects matching
+/// `encode{Type}` and `decode{Type}` functions to be in scope, imported from
+/// `ts_module` when given.
+fn get_field_codec_type(field: &Field) -> Option<String> {
+    let options = get_field_options(field);
+    options.with.as_ref()?;
+
+    options.ts_type.or_else(|| match &field.ty {
+        Type::Path(TypePath { path, .. }) => path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    })
+}
+
+/// Returns the TypeScript runtime method suffix and value type for a field
+/// marked `varint`, eg. `VarintUint32` and `number` for a `u32`. Returns
+/// `None` for other fields, or if the field is not a supported integer.
+fn get_field_varint_type(field: &Field) -> Option<(&'static str, &'static str)> {
+    if !get_field_options(field).varint {
+        return None;
+    }
+
+    let Type::Path(TypePath { path, .. }) = &field.ty else {
+        return None;
+    };
+
+    match path.get_ident()?.to_string().as_str() {
+        "u16" => Some(("VarintUint16", "number")),
+        "u32" => Some(("VarintUint32", "number")),
+        "u64" => Some(("VarintUint64", "bigint")),
+        "i16" => Some(("VarintInt16", "number")),
+        "i32" => Some(("VarintInt32", "number")),
+        "i64" => Some(("VarintInt64", "bigint")),
+        _ => None,
+    }
+}
+
+fn get_field_typescript_type(field: &Field) -> String {
+    get_field_codec_type(field).unwrap_or_else(|| get_typescript_type(field.ty.clone()))
+}
+
+/// Returns the decoder for a field. Fields marked `with_header` are expected
+/// to be message types, so the message ID is read and discarded before the
+/// value.
+fn get_field_bufferfish_fn(field: &Field) -> String {
+    let read_fn = get_field_value_fn(field);
+
+    match get_field_options(field).length_prefix {
+        Some(prefix) => format!("bf.withLengthPrefix('{prefix}', () => {read_fn})"),
+        None => read_fn,
+    }
+}
+
+/// Returns the decoder for a field's value, without applying its length
+/// prefix.
+fn get_field_value_fn(field: &Field) -> String {
+    if let Some((varint, ts_type)) = get_field_varint_type(field) {
+        return format!("bf.read{varint}() as {ts_type}");
+    }
+
+    match get_field_codec_type(field) {
+        Some(codec_type) => format!("decode{codec_type}(bf)"),
+        None if get_field_options(field).with_header => {
+            format!("(bf.readUint16(), {})", get_bufferfish_fn(field.ty.clone()))
+        }
+        None => get_bufferfish_fn(field.ty.clone()),
+    }
+}
+
+/// Returns the writer for a field. Nested message types are written without
+/// their message ID unless the field is marked `with_header`.
+fn get_field_write_fn(field: &Field, value_accessor: &str, message_structs: &[String]) -> String {
+    let write_fn = get_field_value_write_fn(field, value_accessor, message_structs);
+
+    let Some(prefix) = get_field_options(field).length_prefix else {
+        return write_fn;
+    };
+
+    // Arrays and options are written with statements rather than a single
+    // expression, so are nested in a block.
+    if write_fn.contains('\n') {
+        let write_fn = write_fn.replace('\n', "\n    ");
+        format!("bf.withLengthPrefix('{prefix}', () => {{\n        {write_fn}\n    }})")
+    } else {
+        format!("bf.withLengthPrefix('{prefix}', () => {write_fn})")
+    }
+}
+
+/// Returns the writer for a field's value, without applying its length
+/// prefix.
+fn get_field_value_write_fn(
+    field: &Field,
+    value_accessor: &str,
+    message_structs: &[String],
+) -> String {
+    if let Some((varint, _)) = get_field_varint_type(field) {
+        return format!("bf.write{varint}({value_accessor})");
+    }
+
+    match get_field_codec_type(field) {
+        Some(codec_type) => format!("encode{codec_type}(bf, {value_accessor})"),
+        None if get_field_options(field).with_header => {
+            get_bufferfish_write_fn(field.ty.clone(), value_accessor, &[])
+        }
+        None => get_bufferfish_write_fn(field.ty.clone(), value_accessor, message_structs),
+    }
+}
+
+/// Collect the TypeScript imports needed by struct and enum variant fields
+/// using a `with` codec that declare a `ts_module`, as a sorted map of module
+/// to imported names.
+fn get_codec_imports(
+    structs: &[ItemStruct],
+    enums: &[ItemEnum],
+) -> std::collections::BTreeMap<String, std::collections::BTreeSet<String>> {
+    let mut imports = std::collections::BTreeMap::<_, std::collections::BTreeSet<_>>::new();
+
+    let fields = structs.iter().flat_map(|s| s.fields.iter()).chain(
+        enums
+            .iter()
+            .flat_map(|e| e.variants.iter().flat_map(|v| v.fields.iter())),
+    );
+    for field in fields {
+        if let Some(module) = get_field_options(field).ts_module
+            && let Some(codec_type) = get_field_codec_type(field)
+        {
+            let names = imports.entry(module).or_default();
+            names.insert(format!("decode{codec_type}"));
+            names.insert(format!("encode{codec_type}"));
+            names.insert(codec_type);
+        }
+    }
+
+    imports
+}
+
+fn get_message_sets(items: &[Item]) -> Vec<ItemEnum> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Enum(item_enum) if has_derive(&item_enum.attrs, "MessageSet") => {
+                Some(item_enum.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 fn generate_output_string(input: Vec<String>, output: &mut String) -> Result<(), std::io::Error> {
     output.push_str("/* AUTOGENERATED BUFFERFISH FILE, DO NOT EDIT */\n");
     output.push_str("import { Bufferfish } from 'bufferfish'\n");
 
-    let mut message_id_enum_names = Vec::new();
     let mut all_structs = Vec::new();
     let mut all_enums = Vec::new();
-    let mut generated_encoders = std::collections::HashSet::new();
+    let mut all_message_sets = Vec::new();
 
     for path in &input {
         let items = parse_rust_source_file(path)?;
+        all_message_sets.extend(get_message_sets(&items));
         let (structs, enums) = get_items_implementing_encode(items);
-
-        for item in &structs {
-            if let Some(message_id) = get_message_id(&item.attrs)
-                && let Some(enum_name) = extract_enum_name_from_message_id(&message_id)
-                && !message_id_enum_names.contains(&enum_name)
-            {
-                message_id_enum_names.push(enum_name);
-            }
-        }
-
         all_structs.extend(structs);
         all_enums.extend(enums);
     }
 
-    for item in &all_enums {
+    for (module, names) in get_codec_imports(&all_structs, &all_enums) {
+        let names = names.into_iter().collect::<Vec<_>>().join(", ");
+        output.push_str(format!("import {{ {names} }} from '{module}'\n").as_str());
+    }
+
+    generate_typescript_items(&all_structs, &all_enums, &all_message_sets, output);
+
+    Ok(())
+}
+
+/// Generate the TypeScript types, decoders and encoders for every struct,
+/// enum and message set, in that order.
+fn generate_typescript_items(
+    structs: &[ItemStruct],
+    enums: &[ItemEnum],
+    message_sets: &[ItemEnum],
+    output: &mut String,
+) {
+    let mut message_id_enum_names = Vec::new();
+    let attrs = structs
+        .iter()
+        .map(|item| &item.attrs)
+        .chain(enums.iter().map(|item| &item.attrs));
+    for attrs in attrs {
+        if let Some(message_id) = get_message_id(attrs)
+            && let Some(enum_name) = extract_enum_name_from_message_id(&message_id)
+            && !message_id_enum_names.contains(&enum_name)
+        {
+            message_id_enum_names.push(enum_name);
+        }
+    }
+
+    for item in enums {
         generate_typescript_enum_defs(item.clone(), output);
         generate_typescript_enum_decoders(item.clone(), output);
     }
 
-    for item in &all_enums {
+    let message_structs = get_message_structs(structs, enums, &message_id_enum_names);
+    let mut generated_encoders = std::collections::HashSet::new();
+
+    for item in enums {
         let enum_name = item.ident.to_string();
         if message_id_enum_names.contains(&enum_name) {
             generate_typescript_message_id_encoder(item.clone(), output);
             generated_encoders.insert(enum_name);
         } else if !generated_encoders.contains(&enum_name) {
-            generate_typescript_enum_encoders(item.clone(), output);
+            generate_typescript_enum_encoders(item.clone(), output, &message_structs);
         }
     }
 
-    for item in &all_structs {
+    for item in structs {
         generate_typescript_struct_defs(item.clone(), output);
         generate_typescript_struct_decoders(item.clone(), output);
-        generate_typescript_struct_encoders(item.clone(), output, &message_id_enum_names);
+        generate_typescript_struct_encoders(
+            item.clone(),
+            output,
+            &message_id_enum_names,
+            &message_structs,
+        );
     }
 
-    Ok(())
+    for item in message_sets {
+        generate_typescript_message_set(item, structs, enums, output);
+    }
+}
+
+/// Generate a TypeScript discriminated union for an enum annotated with
+/// `#[derive(MessageSet)]`, along with a decoder that switches on the message
+/// ID and an encoder that delegates to each message's encoder.
+fn generate_typescript_message_set(
+    item: &ItemEnum,
+    structs: &[ItemStruct],
+    enums: &[ItemEnum],
+    output: &mut String,
+) {
+    let set_name = item.ident.to_string();
+    let mut variants = Vec::new();
+
+    for variant in &item.variants {
+        let Fields::Unnamed(fields) = &variant.fields else {
+            continue;
+        };
+        let Some(Type::Path(TypePath { path, .. })) = fields.unnamed.first().map(|f| &f.ty) else {
+            continue;
+        };
+        let Some(segment) = path.segments.last() else {
+            continue;
+        };
+
+        let message_name = segment.ident.to_string();
+        let message = structs.iter().find(|s| s.ident == message_name);
+        let message_id = match message {
+            Some(message) => get_message_id(&message.attrs),
+            None => enums
+                .iter()
+                .find(|e| e.ident == message_name)
+                .and_then(|e| get_message_id(&e.attrs)),
+        };
+        let has_value = message.is_none_or(|s| !s.fields.is_empty());
+
+        variants.push((
+            variant.ident.to_string(),
+            message_name,
+            message_id,
+            has_value,
+        ));
+    }
+
+    output.push_str(format!("\nexport type {set_name} =\n").as_str());
+    for (kind, message_name, _, has_value) in &variants {
+        if *has_value {
+            output
+                .push_str(format!("    | {{ kind: '{kind}', value: {message_name} }}\n").as_str());
+        } else {
+            output.push_str(format!("    | {{ kind: '{kind}' }}\n").as_str());
+        }
+    }
+
+    output.push_str(
+        format!("\nexport function decode{set_name}(bf: Bufferfish): {set_name} {{\n").as_str(),
+    );
+    output.push_str("    const messageId = bf.readUint16()\n");
+    output.push_str("    switch (messageId) {\n");
+    for (kind, message_name, message_id, has_value) in &variants {
+        let Some(message_id) = message_id else {
+            continue;
+        };
+
+        output.push_str(format!("        case {}:\n", get_message_id_case(message_id)).as_str());
+        if *has_value {
+            output.push_str(
+                format!(
+                    "            return {{ kind: '{kind}', value: decode{message_name}(bf) }}\n"
+                )
+                .as_str(),
+            );
+        } else {
+            output.push_str(format!("            return {{ kind: '{kind}' }}\n").as_str());
+        }
+    }
+    output.push_str("        default:\n");
+    output.push_str(
+        format!(
+            "            throw new Error(`unknown message id ${{messageId}} for {set_name}`)\n"
+        )
+        .as_str(),
+    );
+    output.push_str("    }\n");
+    output.push_str("}\n");
+
+    output.push_str(
+        format!("\nexport function encode{set_name}(bf: Bufferfish, value: {set_name}): void {{\n")
+            .as_str(),
+    );
+    output.push_str("    switch (value.kind) {\n");
+    for (kind, message_name, _, has_value) in &variants {
+        output.push_str(format!("        case '{kind}':\n").as_str());
+        if *has_value {
+            output
+                .push_str(format!("            encode{message_name}(bf, value.value)\n").as_str());
+        } else {
+            output.push_str(format!("            encode{message_name}(bf)\n").as_str());
+        }
+        output.push_str("            break\n");
+    }
+    output.push_str("    }\n");
+    output.push_str("}\n");
+}
+
+/// Format a message ID for use as a TypeScript `case` label, stripping any
+/// type suffix from integer literals (eg. `0_u16` becomes `0`).
+fn get_message_id_case(id: &str) -> String {
+    match syn::parse_str::<syn::LitInt>(id) {
+        Ok(lit_int) => lit_int.base10_digits().to_string(),
+        Err(_) => id.to_string(),
+    }
 }
 
-fn generate_typescript_enum_encoders(item: ItemEnum, output: &mut String) {
+fn generate_typescript_enum_encoders(
+    item: ItemEnum,
+    output: &mut String,
+    message_structs: &[String],
+) {
     let enum_name = item.ident.to_string();
-    let repr_type = get_repr_type(&item.attrs).unwrap_or("u8".to_string());
-
-    let write_fn = match repr_type.as_str() {
-        "u8" => "writeUint8",
-        "u16" => "writeUint16",
-        "u32" => "writeUint32",
-        "u64" => "writeUint64",
-        "u128" => "writeUint128",
-        "i8" => "writeInt8",
-        "i16" => "writeInt16",
-        "i32" => "writeInt32",
-        "i64" => "writeInt64",
-        "i128" => "writeInt128",
-        _ => panic!("Unsupported repr type"),
+
+    // Message enums write their message ID header, then delegate to a value
+    // encoder, which is also used when the enum is nested in another type.
+    let encoder_name = if message_structs.contains(&enum_name)
+        && let Some(message_id) = get_message_id(&item.attrs)
+        && let Some(id_enum_name) = extract_enum_name_from_message_id(&message_id)
+    {
+        let type_params = get_type_params(&item.generics);
+        let generics = format_type_params(&type_params);
+        let codecs = format_encoder_params(&type_params);
+        let codec_args: String = type_params
+            .iter()
+            .map(|param| format!(", encode{param}"))
+            .collect();
+
+        output.push_str(
+            format!(
+                "\nexport function encode{enum_name}{generics}(bf: Bufferfish, value: {enum_name}{generics}{codecs}): void {{\n"
+            )
+            .as_str(),
+        );
+        output.push_str(format!("    encode{id_enum_name}(bf, {message_id})\n").as_str());
+        output.push_str(format!("    encode{enum_name}Value(bf, value{codec_args})\n").as_str());
+        output.push_str("}\n");
+
+        format!("{enum_name}Value")
+    } else {
+        enum_name.clone()
     };
 
+    if is_data_enum(&item) {
+        generate_typescript_union_encoders(&item, &encoder_name, output, message_structs);
+        return;
+    }
+
+    let (_, write_fn) = get_enum_tag_fns(&item.attrs);
+
     output.push_str(
         format!(
-            "\nexport function encode{enum_name}(bf: Bufferfish, value: {enum_name}): void {{\n",
+            "\nexport function encode{encoder_name}(bf: Bufferfish, value: {enum_name}): void {{\n",
         )
         .as_str(),
     );
@@ -214,12 +679,20 @@ fn generate_typescript_enum_encoders(item: ItemEnum, output: &mut String) {
 
 /// Extract the MessageID from struct attributes and format it for TypeScript
 fn get_message_id(attrs: &[Attribute]) -> Option<String> {
+    get_message_id_expr(attrs).map(|expr| expr.replace("::", "."))
+}
+
+/// Extract the MessageID expression from struct attributes as written, minus
+/// whitespace. Container options such as `bound = ".."` are skipped.
+fn get_message_id_expr(attrs: &[Attribute]) -> Option<String> {
     for attr in attrs {
         if attr.path().is_ident("bufferfish")
             && let Meta::List(list) = &attr.meta
         {
-            let tokens = list.tokens.to_string();
-            let cleaned = tokens.trim().replace(" ", "").replace("::", ".");
+            let cleaned = list.tokens.to_string().replace(" ", "");
+            if cleaned.starts_with("bound=") {
+                continue;
+            }
 
             return Some(cleaned);
         }
@@ -236,21 +709,7 @@ fn extract_enum_name_from_message_id(id: &str) -> Option<String> {
 /// This is used specifically for enum types that are used as message IDs
 fn generate_typescript_message_id_encoder(item: ItemEnum, output: &mut String) {
     let enum_name = item.ident.to_string();
-    let repr_type = get_repr_type(&item.attrs).unwrap_or("u8".to_string());
-
-    let write_fn = match repr_type.as_str() {
-        "u8" => "writeUint8",
-        "u16" => "writeUint16",
-        "u32" => "writeUint32",
-        "u64" => "writeUint64",
-        "u128" => "writeUint128",
-        "i8" => "writeInt8",
-        "i16" => "writeInt16",
-        "i32" => "writeInt32",
-        "i64" => "writeInt64",
-        "i128" => "writeInt128",
-        _ => panic!("Unsupported repr type"),
-    };
+    let (_, write_fn) = get_enum_tag_fns(&item.attrs);
 
     output.push_str(
         format!(
@@ -262,123 +721,340 @@ fn generate_typescript_message_id_encoder(item: ItemEnum, output: &mut String) {
     output.push_str("}\n");
 }
 
+/// Returns the names of structs and enums whose encoders write a message ID
+/// header. These get an additional `encode{Name}Value` function writing only
+/// their value, which is used wherever they are nested in another type.
+fn get_message_structs(
+    structs: &[ItemStruct],
+    enums: &[ItemEnum],
+    message_id_enums: &[String],
+) -> Vec<String> {
+    let has_header = |attrs: &[Attribute]| {
+        get_message_id(attrs)
+            .and_then(|id| extract_enum_name_from_message_id(&id))
+            .is_some_and(|enum_name| message_id_enums.contains(&enum_name))
+    };
+
+    let struct_names = structs
+        .iter()
+        .filter(|item| !item.fields.is_empty() && has_header(&item.attrs))
+        .map(|item| item.ident.to_string());
+    let enum_names = enums
+        .iter()
+        .filter(|item| has_header(&item.attrs))
+        .map(|item| item.ident.to_string());
+
+    struct_names.chain(enum_names).collect()
+}
+
+/// Returns the name of the function encoding a nested value of a custom type,
+/// which skips the message ID header for message structs.
+fn get_nested_encoder_name(name: &str, message_structs: &[String]) -> String {
+    if message_structs.iter().any(|s| s == name) {
+        format!("encode{name}Value")
+    } else {
+        format!("encode{name}")
+    }
+}
+
 fn generate_typescript_struct_encoders(
     item: ItemStruct,
     output: &mut String,
     message_id_enums: &[String],
+    message_structs: &[String],
 ) {
     let struct_name = item.ident.to_string();
     let message_id = get_message_id(&item.attrs);
-
-    match &item.fields {
-        Fields::Named(fields_named) => {
-            if fields_named.named.is_empty() && message_id.is_none() {
-                return;
+    let type_params = get_type_params(&item.generics);
+    let generics = format_type_params(&type_params);
+    let codecs = format_encoder_params(&type_params);
+
+    let header = message_id.as_ref().and_then(|id| {
+        let enum_name = extract_enum_name_from_message_id(id)?;
+        message_id_enums
+            .contains(&enum_name)
+            .then(|| format!("    encode{enum_name}(bf, {id})\n"))
+    });
+
+    let writes = match &item.fields {
+        Fields::Named(fields_named) => fields_named
+            .named
+            .iter()
+            .filter_map(|field| {
+                let field_ts_name = snake_to_camel_case(field.ident.as_ref()?.to_string());
+                Some(get_field_write_fn(
+                    field,
+                    &format!("value.{field_ts_name}"),
+                    message_structs,
+                ))
+            })
+            .collect::<Vec<_>>(),
+        Fields::Unnamed(fields_unnamed) => fields_unnamed
+            .unnamed
+            .iter()
+            .enumerate()
+            .map(|(i, field)| get_field_write_fn(field, &format!("value[{i}]"), message_structs))
+            .collect::<Vec<_>>(),
+        Fields::Unit => {
+            if let Some(header) = header {
+                output.push_str(
+                    format!("\nexport function encode{struct_name}(bf: Bufferfish): void {{\n",)
+                        .as_str(),
+                );
+                output.push_str(&header);
+                output.push_str("}\n");
             }
 
-            output.push_str(
-                format!(
-                    "\nexport function encode{struct_name}(bf: Bufferfish, value: {struct_name}): void {{\n",
+            return;
+        }
+    };
 
-                )
-                .as_str(),
-            );
+    if writes.is_empty() && message_id.is_none() {
+        return;
+    }
 
-            if let Some(id) = &message_id
-                && let Some(enum_name) = extract_enum_name_from_message_id(id)
-                && message_id_enums.contains(&enum_name)
-            {
-                output.push_str(format!("    encode{enum_name}(bf, {id})\n").as_str());
-            }
+    output.push_str(
+        format!(
+            "\nexport function encode{struct_name}{generics}(bf: Bufferfish, value: {struct_name}{generics}{codecs}): void {{\n",
+        )
+        .as_str(),
+    );
 
-            for field in &fields_named.named {
-                if let Some(field_name) = &field.ident {
-                    let field_ts_name = snake_to_camel_case(field_name.to_string());
-                    output.push_str(
-                        format!(
-                            "    {}\n",
-                            get_bufferfish_write_fn(
-                                field.ty.clone(),
-                                &format!("value.{field_ts_name}",)
-                            )
-                        )
-                        .as_str(),
-                    );
-                }
-            }
+    if let Some(header) = header {
+        output.push_str(&header);
+
+        // Message structs delegate to a value encoder, which is also used
+        // when the struct is nested in another type.
+        if !writes.is_empty() {
+            let codec_args: String = type_params
+                .iter()
+                .map(|param| format!(", encode{param}"))
+                .collect();
 
+            output.push_str(
+                format!("    encode{struct_name}Value(bf, value{codec_args})\n").as_str(),
+            );
             output.push_str("}\n");
-        }
-        Fields::Unnamed(fields_unnamed) => {
-            if fields_unnamed.unnamed.is_empty() && message_id.is_none() {
-                return;
-            }
 
             output.push_str(
                 format!(
-                    "\nexport function encode{struct_name}(bf: Bufferfish, value: {struct_name}): void {{\n",
-
+                    "\nexport function encode{struct_name}Value{generics}(bf: Bufferfish, value: {struct_name}{generics}{codecs}): void {{\n",
                 )
                 .as_str(),
             );
+        }
+    }
 
-            if let Some(id) = &message_id
-                && let Some(enum_name) = extract_enum_name_from_message_id(id)
-                && message_id_enums.contains(&enum_name)
-            {
-                output.push_str(format!("    encode{enum_name}(bf, {id})\n").as_str());
-            }
+    for write in writes {
+        output.push_str(format!("    {write}\n").as_str());
+    }
 
-            for (i, field) in fields_unnamed.unnamed.iter().enumerate() {
-                output.push_str(
-                    format!(
-                        "    {}\n",
-                        get_bufferfish_write_fn(field.ty.clone(), &format!("value[{i}]",))
-                    )
-                    .as_str(),
-                );
-            }
+    output.push_str("}\n");
+}
 
-            output.push_str("}\n");
+/// Returns the names of a type's generic type parameters.
+fn get_type_params(generics: &Generics) -> Vec<String> {
+    generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect()
+}
+
+/// Formats type parameters as a TypeScript generic list, eg. `<T, U>`.
+fn format_type_params(type_params: &[String]) -> String {
+    if type_params.is_empty() {
+        String::new()
+    } else {
+        format!("<{}>", type_params.join(", "))
+    }
+}
+
+/// Formats the element decoder parameters taken by a generic type's decoder.
+/// A field of type `T` is decoded by calling `decodeT(bf)`.
+fn format_decoder_params(type_params: &[String]) -> String {
+    type_params
+        .iter()
+        .map(|param| format!(", decode{param}: (bf: Bufferfish) => {param}"))
+        .collect()
+}
+
+/// Formats the element encoder parameters taken by a generic type's encoder.
+/// A field of type `T` is encoded by calling `encodeT(bf, value)`.
+fn format_encoder_params(type_params: &[String]) -> String {
+    type_params
+        .iter()
+        .map(|param| format!(", encode{param}: (bf: Bufferfish, value: {param}) => void"))
+        .collect()
+}
+
+/// Returns the type arguments of a generic user-defined type, such as
+/// `Envelope<Chat>`, or `None` for anything else.
+fn get_generic_args(path: &syn::Path) -> Option<Vec<Type>> {
+    let segment = path.segments.last()?;
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    Some(
+        args.args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect(),
+    )
+}
+
+/// Returns the key and value types of a `HashMap` or `BTreeMap`, including
+/// when written with its path, eg. `std::collections::HashMap<K, V>`.
+fn get_map_types(path: &syn::Path) -> Option<(Type, Type)> {
+    let segment = path.segments.last()?;
+    if segment.ident != "HashMap" && segment.ident != "BTreeMap" {
+        return None;
+    }
+
+    let mut args = get_generic_args(path)?.into_iter();
+    Some((args.next()?, args.next()?))
+}
+
+/// Returns the item type of a `HashSet` or `BTreeSet`, including when written
+/// with its path.
+fn get_set_type(path: &syn::Path) -> Option<Type> {
+    let segment = path.segments.last()?;
+    if segment.ident != "HashSet" && segment.ident != "BTreeSet" {
+        return None;
+    }
+
+    get_generic_args(path)?.into_iter().next()
+}
+
+/// Returns the type written in place of a reference, `Box`, `Rc`, `Arc` or
+/// `Cow`, which are written exactly like the value they point to. `str` and
+/// slices are written like `String` and `Vec`.
+fn get_transparent_type(ty: &Type) -> Option<Type> {
+    let inner = match ty {
+        Type::Reference(type_ref) => (*type_ref.elem).clone(),
+        Type::Slice(type_slice) => {
+            let elem = &type_slice.elem;
+            return Some(parse_quote!(Vec<#elem>));
         }
-        Fields::Unit => {
-            if let Some(id) = message_id {
-                output.push_str(
-                    format!("\nexport function encode{struct_name}(bf: Bufferfish): void {{\n",)
-                        .as_str(),
-                );
+        Type::Path(TypePath { path, .. }) if path.is_ident("str") => {
+            return Some(parse_quote!(String));
+        }
+        Type::Path(TypePath { path, .. })
+            if path.segments.len() == 1
+                && matches!(
+                    path.segments[0].ident.to_string().as_str(),
+                    "Box" | "Rc" | "Arc" | "Cow"
+                ) =>
+        {
+            get_generic_args(path)?.into_iter().next()?
+        }
+        _ => return None,
+    };
 
-                if let Some(enum_name) = extract_enum_name_from_message_id(&id)
-                    && message_id_enums.contains(&enum_name)
-                {
-                    output.push_str(format!("    encode{enum_name}(bf, {id})\n").as_str());
-                }
+    Some(get_transparent_type(&inner).unwrap_or(inner))
+}
 
-                output.push_str("}\n");
-            }
+/// Returns the item type and length of a fixed-size array such as `[u8; 32]`.
+/// Lengths given by a constant or expression are not supported.
+fn get_array_type(ty: &Type) -> Option<(Type, usize)> {
+    let Type::Array(array) = ty else {
+        return None;
+    };
+
+    let Expr::Lit(ExprLit {
+        lit: Lit::Int(len), ..
+    }) = &array.len
+    else {
+        return None;
+    };
+
+    Some((*array.elem.clone(), len.base10_parse().ok()?))
+}
+
+/// Returns whether `ty` is `u8`, for arrays written as raw bytes.
+fn is_byte_type(ty: &Type) -> bool {
+    matches!(ty, Type::Path(TypePath { path, .. }) if path.is_ident("u8"))
+}
+
+fn get_bufferfish_write_fn(ty: Type, value_accessor: &str, message_structs: &[String]) -> String {
+    if let Some(ty) = get_transparent_type(&ty) {
+        return get_bufferfish_write_fn(ty, value_accessor, message_structs);
+    }
+
+    // Tuples are written element by element. `()` writes nothing, so is an
+    // empty block to remain valid as a statement or an arrow function body.
+    if let Type::Tuple(tuple) = &ty {
+        let writes = tuple
+            .elems
+            .iter()
+            .enumerate()
+            .map(|(i, elem)| {
+                get_bufferfish_write_fn(
+                    elem.clone(),
+                    &format!("{value_accessor}[{i}]"),
+                    message_structs,
+                )
+            })
+            .filter(|write| write != "{}")
+            .collect::<Vec<_>>();
+
+        if writes.is_empty() {
+            return "{}".to_string();
         }
+
+        return writes.join("\n    ");
+    }
+
+    if let Some((item_ty, len)) = get_array_type(&ty) {
+        if is_byte_type(&item_ty) {
+            return format!("bf.writeFixedBytes({value_accessor}, {len})");
+        }
+
+        let item = get_loop_var("item", value_accessor);
+        let item_write_call = get_element_write_call(item_ty, &item, message_structs);
+
+        return format!(
+            "for (const {item} of {value_accessor}) {{\n        {item_write_call}\n    }}",
+        );
     }
-}
 
-fn get_bufferfish_write_fn(ty: Type, value_accessor: &str) -> String {
     match ty {
         Type::Path(TypePath { path, .. }) => {
             if path.segments.len() == 1
-                && path.segments[0].ident == "Vec"
+                && (path.segments[0].ident == "Vec" || path.segments[0].ident == "VecDeque")
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
                 && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
             {
-                let inner_write_fn = get_element_write_fn(inner_ty.clone());
+                let item = get_loop_var("item", value_accessor);
+                let inner_write_call =
+                    get_element_write_call(inner_ty.clone(), &item, message_structs);
 
-                if is_primitive_type(inner_ty) {
-                    return format!(
-                        "bf.writeUint16({value_accessor}.length)\n    for (const item of {value_accessor}) {{\n        bf.{inner_write_fn}(item)\n    }}",
-                    );
-                } else {
-                    return format!(
-                        "bf.writeUint16({value_accessor}.length)\n    for (const item of {value_accessor}) {{\n        {inner_write_fn}(bf, item)\n    }}",
-                    );
-                }
+                return format!(
+                    "bf.writeLength({value_accessor}.length)\n    for (const {item} of {value_accessor}) {{\n        {inner_write_call}\n    }}",
+                );
+            }
+
+            if let Some((key_ty, value_ty)) = get_map_types(&path) {
+                let key = get_loop_var("key", value_accessor);
+                let item = get_loop_var("item", value_accessor);
+                let key_write_call = get_element_write_call(key_ty, &key, message_structs);
+                let value_write_call = get_element_write_call(value_ty, &item, message_structs);
+
+                return format!(
+                    "bf.writeLength({value_accessor}.size)\n    for (const [{key}, {item}] of {value_accessor}) {{\n        {key_write_call}\n        {value_write_call}\n    }}",
+                );
+            }
+
+            if let Some(item_ty) = get_set_type(&path) {
+                let item = get_loop_var("item", value_accessor);
+                let item_write_call = get_element_write_call(item_ty, &item, message_structs);
+
+                return format!(
+                    "bf.writeLength({value_accessor}.size)\n    for (const {item} of {value_accessor}) {{\n        {item_write_call}\n    }}",
+                );
             }
 
             if path.segments.len() == 1
@@ -386,19 +1062,38 @@ fn get_bufferfish_write_fn(ty: Type, value_accessor: &str) -> String {
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
                 && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
             {
-                let inner_write_call = if is_primitive_type(inner_ty) {
-                    let inner_write_fn = get_element_write_fn(inner_ty.clone());
-                    format!("bf.{inner_write_fn}({value_accessor})")
-                } else {
-                    let inner_write_fn = get_element_write_fn(inner_ty.clone());
-                    format!("{inner_write_fn}(bf, {value_accessor})")
-                };
+                let inner_write_call =
+                    get_element_write_call(inner_ty.clone(), value_accessor, message_structs);
 
                 return format!(
                     "if ({value_accessor} !== null) {{\n        bf.writeUint8(1)\n        {inner_write_call}\n    }} else {{\n        bf.writeUint8(0)\n    }}",
                 );
             }
 
+            if let Some(args) = get_generic_args(&path)
+                && let Some(segment) = path.segments.last()
+            {
+                let name = &segment.ident;
+                let codecs: String = args
+                    .into_iter()
+                    .map(|arg| {
+                        let ts_type = get_typescript_type(arg.clone());
+                        let write_fn = get_bufferfish_write_fn(arg, "value", message_structs);
+
+                        // Arrays and options are written with statements
+                        // rather than a single expression.
+                        if write_fn.contains('\n') {
+                            format!(", (bf: Bufferfish, value: {ts_type}) => {{ {write_fn} }}")
+                        } else {
+                            format!(", (bf: Bufferfish, value: {ts_type}) => {write_fn}")
+                        }
+                    })
+                    .collect();
+
+                let encoder = get_nested_encoder_name(&name.to_string(), message_structs);
+                return format!("{encoder}(bf, {value_accessor}{codecs})");
+            }
+
             match path.get_ident().map(|ident| ident.to_string()).as_deref() {
                 Some("u8") => format!("bf.writeUint8({value_accessor})"),
                 Some("u16") => format!("bf.writeUint16({value_accessor})"),
@@ -410,9 +1105,15 @@ fn get_bufferfish_write_fn(ty: Type, value_accessor: &str) -> String {
                 Some("i32") => format!("bf.writeInt32({value_accessor})",),
                 Some("i64") => format!("bf.writeInt64({value_accessor})",),
                 Some("i128") => format!("bf.writeInt128({value_accessor})",),
+                Some("f32") => format!("bf.writeFloat32({value_accessor})",),
+                Some("f64") => format!("bf.writeFloat64({value_accessor})",),
                 Some("bool") => format!("bf.writeBool({value_accessor})",),
                 Some("String") => format!("bf.writeString({value_accessor})",),
-                Some(custom) => format!("encode{custom}(bf, {value_accessor})",),
+                Some("ByteBuf") | Some("Bytes") => format!("bf.writeBytes({value_accessor})"),
+                Some(custom) => format!(
+                    "{}(bf, {value_accessor})",
+                    get_nested_encoder_name(custom, message_structs)
+                ),
                 _ => format!("/* Unsupported type for {value_accessor} */",),
             }
         }
@@ -434,74 +1135,125 @@ fn is_primitive_type(ty: &Type) -> bool {
                 | Some("i32")
                 | Some("i64")
                 | Some("i128")
+                | Some("f32")
+                | Some("f64")
                 | Some("bool")
                 | Some("String")
+                | Some("ByteBuf")
+                | Some("Bytes")
         )
     } else {
         false
     }
 }
 
-fn get_element_write_fn(ty: Type) -> String {
-    match ty {
-        Type::Path(TypePath { path, .. }) => {
-            match path.get_ident().map(|ident| ident.to_string()).as_deref() {
-                Some("u8") => "writeUint8".to_string(),
-                Some("u16") => "writeUint16".to_string(),
-                Some("u32") => "writeUint32".to_string(),
-                Some("u64") => "writeUint64".to_string(),
-                Some("u128") => "writeUint128".to_string(),
-                Some("i8") => "writeInt8".to_string(),
-                Some("i16") => "writeInt16".to_string(),
-                Some("i32") => "writeInt32".to_string(),
-                Some("i64") => "writeInt64".to_string(),
-                Some("i128") => "writeInt128".to_string(),
-                Some("bool") => "writeBool".to_string(),
-                Some("String") => "writeString".to_string(),
-                Some(custom) => format!("encode{custom}"),
-                _ => "unknown".to_string(),
-            }
+/// Returns the statement writing a map key, or a collection item, at
+/// `accessor`, indented for the body of the loop over the collection.
+fn get_element_write_call(ty: Type, accessor: &str, message_structs: &[String]) -> String {
+    get_bufferfish_write_fn(ty, accessor, message_structs).replace('\n', "\n    ")
+}
+
+/// Returns the name of the loop variable for the items of the collection at
+/// `accessor`. Items of a collection that is itself an item are numbered, so
+/// the inner loop doesn't shadow the variable it iterates over.
+fn get_loop_var(name: &str, accessor: &str) -> String {
+    let root = accessor.split(['.', '[']).next().unwrap_or(accessor);
+
+    let depth = ["item", "key"].iter().find_map(|prefix| {
+        let suffix = root.strip_prefix(prefix)?;
+        if suffix.is_empty() {
+            Some(1)
+        } else {
+            suffix.parse::<usize>().ok()
         }
-        _ => "unknown".to_string(),
+    });
+
+    match depth {
+        Some(depth) => format!("{name}{}", depth + 1),
+        None => name.to_string(),
     }
 }
 
 fn generate_typescript_enum_defs(item: ItemEnum, lines: &mut String) {
+    if is_data_enum(&item) {
+        generate_typescript_union_defs(&item, lines);
+        return;
+    }
+
     let enum_name = item.ident.to_string();
-    let mut variants = Vec::new();
-    let mut discriminant = 0;
 
-    for variant in item.variants {
+    lines.push_str(format!("\nexport enum {enum_name} {{\n").as_str());
+    for (variant, discriminant) in item.variants.iter().zip(get_enum_discriminants(&item)) {
         let variant_name = variant.ident.to_string();
+        lines.push_str(format!("    {variant_name} = {discriminant},\n").as_str());
+    }
 
-        if let Some((
-            _,
-            Expr::Lit(ExprLit {
-                lit: Lit::Int(lit_int),
-                ..
-            }),
-        )) = &variant.discriminant
+    lines.push_str("}\n");
+}
+
+/// Returns the tag value of each variant, following Rust's rules for implicit
+/// discriminants.
+fn get_enum_discriminants(item: &ItemEnum) -> Vec<i128> {
+    let mut discriminants = Vec::new();
+    let mut discriminant: i128 = 0;
+
+    for variant in &item.variants {
+        if let Some((_, expr)) = &variant.discriminant
+            && let Some(value) = parse_discriminant(expr)
         {
-            discriminant = lit_int.base10_parse().expect("Invalid discriminant value");
+            discriminant = value;
         }
 
-        variants.push((variant_name, discriminant));
+        discriminants.push(discriminant);
         discriminant += 1;
     }
 
-    lines.push_str(format!("\nexport enum {enum_name} {{\n").as_str());
-    for (variant_name, discriminant) in variants {
-        lines.push_str(format!("    {variant_name} = {discriminant},\n").as_str());
-    }
+    discriminants
+}
 
-    lines.push_str("}\n");
+/// Parse an integer literal enum discriminant, including negative values.
+fn parse_discriminant(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit_int),
+            ..
+        }) => Some(lit_int.base10_parse().expect("Invalid discriminant value")),
+        Expr::Unary(ExprUnary {
+            op: UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_discriminant(expr).map(|value| -value),
+        _ => None,
+    }
 }
 
 fn get_typescript_type(ty: Type) -> String {
+    if let Some(ty) = get_transparent_type(&ty) {
+        return get_typescript_type(ty);
+    }
+
+    if let Type::Tuple(tuple) = &ty {
+        let elem_ts_types = tuple
+            .elems
+            .iter()
+            .map(|elem| get_typescript_type(elem.clone()))
+            .collect::<Vec<_>>();
+        return format!("[{}]", elem_ts_types.join(", "));
+    }
+
+    if let Some((item_ty, len)) = get_array_type(&ty) {
+        if is_byte_type(&item_ty) {
+            return "Uint8Array".to_string();
+        }
+
+        let item_ts_type = get_typescript_type(item_ty);
+        return format!("[{}]", vec![item_ts_type; len].join(", "));
+    }
+
     match ty {
         Type::Path(TypePath { path, .. }) => {
             if path.segments.len() == 1
-                && path.segments[0].ident == "Vec"
+                && (path.segments[0].ident == "Vec" || path.segments[0].ident == "VecDeque")
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
                 && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
             {
@@ -509,6 +1261,17 @@ fn get_typescript_type(ty: Type) -> String {
                 return format!("Array<{inner_ts_type}>");
             }
 
+            if let Some((key_ty, value_ty)) = get_map_types(&path) {
+                let key_ts_type = get_typescript_type(key_ty);
+                let value_ts_type = get_typescript_type(value_ty);
+                return format!("Map<{key_ts_type}, {value_ts_type}>");
+            }
+
+            if let Some(item_ty) = get_set_type(&path) {
+                let item_ts_type = get_typescript_type(item_ty);
+                return format!("Set<{item_ts_type}>");
+            }
+
             if path.segments.len() == 1
                 && path.segments[0].ident == "Option"
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
@@ -520,19 +1283,33 @@ fn get_typescript_type(ty: Type) -> String {
 
             match path.get_ident().map(|ident| ident.to_string()).as_deref() {
                 #[rustfmt::skip]
-                Some("u8") | Some("u16") | Some("u32") | Some("i8") | Some("i16") | Some("i32") 
-                 => {
+                Some("u8") | Some("u16") | Some("u32") | Some("i8") | Some("i16") | Some("i32")
+                | Some("f32") | Some("f64") => {
                     "number".to_string()
                 },
                 Some("u64") | Some("u128") | Some("i64") | Some("i128") => "bigint".to_string(),
                 Some("bool") => "boolean".to_string(),
                 Some("String") => "string".to_string(),
-                _ => path
-                    .segments
-                    .iter()
-                    .map(|seg| seg.ident.to_string())
-                    .collect::<Vec<_>>()
-                    .join("::"),
+                Some("ByteBuf") | Some("Bytes") => "Uint8Array".to_string(),
+                _ => {
+                    let name = path
+                        .segments
+                        .iter()
+                        .map(|seg| seg.ident.to_string())
+                        .collect::<Vec<_>>()
+                        .join("::");
+
+                    match get_generic_args(&path) {
+                        Some(args) => {
+                            let args = args
+                                .into_iter()
+                                .map(get_typescript_type)
+                                .collect::<Vec<_>>();
+                            format!("{name}{}", format_type_params(&args))
+                        }
+                        None => name,
+                    }
+                }
             }
         }
         _ => "unknown".to_string(),
@@ -545,13 +1322,14 @@ fn generate_typescript_struct_defs(item: ItemStruct, lines: &mut String) {
     }
 
     let struct_name = item.ident.to_string();
+    let generics = format_type_params(&get_type_params(&item.generics));
 
     match &item.fields {
         Fields::Named(fields_named) => {
-            lines.push_str(format!("\nexport interface {struct_name} {{\n").as_str());
+            lines.push_str(format!("\nexport interface {struct_name}{generics} {{\n").as_str());
             for field in &fields_named.named {
                 if let Some(field_name) = &field.ident {
-                    let field_type = get_typescript_type(field.ty.clone());
+                    let field_type = get_field_typescript_type(field);
                     lines.push_str(
                         format!(
                             "    {}: {}\n",
@@ -565,11 +1343,11 @@ fn generate_typescript_struct_defs(item: ItemStruct, lines: &mut String) {
             lines.push_str("}\n");
         }
         Fields::Unnamed(fields_unnamed) => {
-            lines.push_str(format!("\nexport type {struct_name} = [").as_str());
+            lines.push_str(format!("\nexport type {struct_name}{generics} = [").as_str());
             let field_types: Vec<String> = fields_unnamed
                 .unnamed
                 .iter()
-                .map(|f| get_typescript_type(f.ty.clone()))
+                .map(get_field_typescript_type)
                 .collect();
             lines.push_str(&field_types.join(", "));
             lines.push_str("]\n");
@@ -583,6 +1361,9 @@ fn generate_typescript_struct_defs(item: ItemStruct, lines: &mut String) {
 
 fn generate_typescript_struct_decoders(item: ItemStruct, lines: &mut String) {
     let struct_name = item.ident.to_string();
+    let type_params = get_type_params(&item.generics);
+    let generics = format_type_params(&type_params);
+    let codecs = format_decoder_params(&type_params);
 
     match &item.fields {
         Fields::Named(fields_named) => {
@@ -592,7 +1373,7 @@ fn generate_typescript_struct_decoders(item: ItemStruct, lines: &mut String) {
 
             lines.push_str(
                 format!(
-                    "\nexport function decode{struct_name}(bf: Bufferfish): {struct_name} {{\n"
+                    "\nexport function decode{struct_name}{generics}(bf: Bufferfish{codecs}): {struct_name}{generics} {{\n"
                 )
                 .as_str(),
             );
@@ -604,7 +1385,7 @@ fn generate_typescript_struct_decoders(item: ItemStruct, lines: &mut String) {
                         format!(
                             "        {}: {},\n",
                             snake_to_camel_case(field_name.to_string()),
-                            get_bufferfish_fn(field.ty.clone())
+                            get_field_bufferfish_fn(field)
                         )
                         .as_str(),
                     );
@@ -621,16 +1402,14 @@ fn generate_typescript_struct_decoders(item: ItemStruct, lines: &mut String) {
 
             lines.push_str(
                 format!(
-                    "\nexport function decode{struct_name}(bf: Bufferfish): {struct_name} {{\n"
+                    "\nexport function decode{struct_name}{generics}(bf: Bufferfish{codecs}): {struct_name}{generics} {{\n"
                 )
                 .as_str(),
             );
             lines.push_str("    return [\n");
 
             for field in &fields_unnamed.unnamed {
-                lines.push_str(
-                    format!("        {},\n", get_bufferfish_fn(field.ty.clone())).as_str(),
-                );
+                lines.push_str(format!("        {},\n", get_field_bufferfish_fn(field)).as_str());
             }
 
             lines.push_str("    ]\n");
@@ -641,10 +1420,36 @@ fn generate_typescript_struct_decoders(item: ItemStruct, lines: &mut String) {
 }
 
 fn get_bufferfish_fn(ty: Type) -> String {
+    if let Some(ty) = get_transparent_type(&ty) {
+        return get_bufferfish_fn(ty);
+    }
+
+    // Array literal elements are evaluated in order, so a tuple's elements are
+    // read in the order they were written.
+    if let Type::Tuple(tuple) = &ty {
+        let elem_fns = tuple
+            .elems
+            .iter()
+            .map(|elem| get_bufferfish_fn(elem.clone()))
+            .collect::<Vec<_>>();
+        let ts_type = get_typescript_type(ty.clone());
+        return format!("[{}] as {ts_type}", elem_fns.join(", "));
+    }
+
+    if let Some((item_ty, len)) = get_array_type(&ty) {
+        if is_byte_type(&item_ty) {
+            return format!("bf.readFixedBytes({len}) as Uint8Array");
+        }
+
+        let item_fn = get_bufferfish_fn(item_ty.clone());
+        let ts_type = get_typescript_type(ty);
+        return format!("bf.readFixedArray({len}, () => {item_fn}) as {ts_type}");
+    }
+
     match ty {
         Type::Path(TypePath { path, .. }) => {
             if path.segments.len() == 1
-                && path.segments[0].ident == "Vec"
+                && (path.segments[0].ident == "Vec" || path.segments[0].ident == "VecDeque")
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
                 && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
             {
@@ -653,6 +1458,23 @@ fn get_bufferfish_fn(ty: Type) -> String {
                 return format!("bf.readArray(() => {inner_fn}) as Array<{inner_ts_type}>");
             }
 
+            if let Some((key_ty, value_ty)) = get_map_types(&path) {
+                let key_fn = get_bufferfish_fn(key_ty.clone());
+                let value_fn = get_bufferfish_fn(value_ty.clone());
+                let ts_type = format!(
+                    "Map<{}, {}>",
+                    get_typescript_type(key_ty),
+                    get_typescript_type(value_ty)
+                );
+                return format!("bf.readMap(() => {key_fn}, () => {value_fn}) as {ts_type}");
+            }
+
+            if let Some(item_ty) = get_set_type(&path) {
+                let item_fn = get_bufferfish_fn(item_ty.clone());
+                let item_ts_type = get_typescript_type(item_ty);
+                return format!("bf.readSet(() => {item_fn}) as Set<{item_ts_type}>");
+            }
+
             if path.segments.len() == 1
                 && path.segments[0].ident == "Option"
                 && let PathArguments::AngleBracketed(ref args) = path.segments[0].arguments
@@ -665,6 +1487,18 @@ fn get_bufferfish_fn(ty: Type) -> String {
                 );
             }
 
+            if let Some(args) = get_generic_args(&path)
+                && let Some(segment) = path.segments.last()
+            {
+                let name = &segment.ident;
+                let codecs: String = args
+                    .into_iter()
+                    .map(|arg| format!(", (bf: Bufferfish) => {}", get_bufferfish_fn(arg)))
+                    .collect();
+
+                return format!("decode{name}(bf{codecs})");
+            }
+
             match path.get_ident().map(|ident| ident.to_string()).as_deref() {
                 Some("u8") => "bf.readUint8() as number".to_string(),
                 Some("u16") => "bf.readUint16() as number".to_string(),
@@ -676,8 +1510,11 @@ fn get_bufferfish_fn(ty: Type) -> String {
                 Some("i32") => "bf.readInt32() as number".to_string(),
                 Some("i64") => "bf.readInt64() as bigint".to_string(),
                 Some("i128") => "bf.readInt128() as bigint".to_string(),
+                Some("f32") => "bf.readFloat32() as number".to_string(),
+                Some("f64") => "bf.readFloat64() as number".to_string(),
                 Some("bool") => "bf.readBool() as boolean".to_string(),
                 Some("String") => "bf.readString() as string".to_string(),
+                Some("ByteBuf") | Some("Bytes") => "bf.readBytes() as Uint8Array".to_string(),
                 Some(custom) => format!("decode{custom}(bf)"),
                 _ => "unknown".to_string(),
             }
@@ -687,22 +1524,13 @@ fn get_bufferfish_fn(ty: Type) -> String {
 }
 
 fn generate_typescript_enum_decoders(item: ItemEnum, output: &mut String) {
+    if is_data_enum(&item) {
+        generate_typescript_union_decoders(&item, output);
+        return;
+    }
+
     let enum_name = item.ident.to_string();
-    let repr_type = get_repr_type(&item.attrs).unwrap_or("u8".to_string());
-
-    let read_fn = match repr_type.as_str() {
-        "u8" => "readUint8",
-        "u16" => "readUint16",
-        "u32" => "readUint32",
-        "u64" => "readUint64",
-        "u128" => "readUint128",
-        "i8" => "readInt8",
-        "i16" => "readInt16",
-        "i32" => "readInt32",
-        "i64" => "readInt64",
-        "i128" => "readInt128",
-        _ => panic!("Unsupported repr type"),
-    };
+    let (read_fn, _) = get_enum_tag_fns(&item.attrs);
 
     output.push_str(
         format!("\nexport function decode{enum_name}(bf: Bufferfish): {enum_name} {{\n").as_str(),
@@ -711,111 +1539,626 @@ fn generate_typescript_enum_decoders(item: ItemEnum, output: &mut String) {
     output.push_str("}\n");
 }
 
-fn get_repr_type(attrs: &[Attribute]) -> Option<String> {
-    for attr in attrs {
-        if attr.path().is_ident("repr")
-            && let Meta::List(list) = &attr.meta
-        {
-            for item in list.tokens.clone() {
-                if let Some(ident) = item.to_string().split_whitespace().next() {
-                    return Some(ident.to_string());
-                }
-            }
-        }
-    }
-    None
+/// Returns true if any variant of the enum carries data, in which case it is
+/// generated as a discriminated union rather than a TypeScript `enum`.
+fn is_data_enum(item: &ItemEnum) -> bool {
+    item.variants
+        .iter()
+        .any(|variant| !matches!(variant.fields, Fields::Unit))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_ts_generation() {
-        let test_file = r#"
-#[derive(Encode)]
-#[repr(u16)]
-pub enum MessageId {
-    Join = 0,
-    Leave,
-    Ping,
-    Unknown = 255,
+/// Returns the Bufferfish read and write functions for an enum's tag.
+fn get_enum_tag_fns(attrs: &[Attribute]) -> (&'static str, &'static str) {
+    let repr_type = get_repr_type(attrs).unwrap_or("u8".to_string());
+
+    match repr_type.as_str() {
+        "u8" => ("readUint8", "writeUint8"),
+        "u16" => ("readUint16", "writeUint16"),
+        "u32" => ("readUint32", "writeUint32"),
+        "u64" => ("readUint64", "writeUint64"),
+        "u128" => ("readUint128", "writeUint128"),
+        "i8" => ("readInt8", "writeInt8"),
+        "i16" => ("readInt16", "writeInt16"),
+        "i32" => ("readInt32", "writeInt32"),
+        "i64" => ("readInt64", "writeInt64"),
+        "i128" => ("readInt128", "writeInt128"),
+        _ => panic!("Unsupported repr type"),
+    }
 }
 
-#[derive(Encode)]
-#[bufferfish(MessageId::Join)]
-pub struct JoinMessage {
-    pub id: u8,
-    pub username: String,
+/// Formats a tag value as a TypeScript literal. 64-bit and wider tags are read
+/// as a `bigint`.
+fn format_enum_tag(attrs: &[Attribute], discriminant: i128) -> String {
+    match get_repr_type(attrs).as_deref() {
+        Some("u64") | Some("u128") | Some("i64") | Some("i128") => format!("{discriminant}n"),
+        _ => discriminant.to_string(),
+    }
 }
 
-#[derive(Encode)]
-#[bufferfish(MessageId::Leave)]
-pub struct LeaveMessage;
+/// Generate a TypeScript discriminated union for an enum with data-carrying
+/// variants. Each member is tagged with `kind` set to the variant name. Named
+/// fields are spread into the member, while tuple fields are stored under
+/// `value` (as a tuple when there is more than one).
+fn generate_typescript_union_defs(item: &ItemEnum, lines: &mut String) {
+    let enum_name = item.ident.to_string();
+    let generics = format_type_params(&get_type_params(&item.generics));
 
-#[derive(Encode)]
-#[bufferfish(MessageId::Unknown)]
-pub struct UnknownMessage(pub u8, pub u16);
+    lines.push_str(format!("\nexport type {enum_name}{generics} =\n").as_str());
+    for variant in &item.variants {
+        let kind = variant.ident.to_string();
 
-#[derive(Encode, Decode)]
-#[bufferfish(MessageId::Ping)]
-pub struct PingMessage {
-    pub timestamp: Option<u64>,
-}
-    "#;
+        match &variant.fields {
+            Fields::Named(fields_named) => {
+                let fields = fields_named
+                    .named
+                    .iter()
+                    .filter_map(|field| {
+                        let field_name = field.ident.as_ref()?;
+                        Some(format!(
+                            ", {}: {}",
+                            snake_to_camel_case(field_name.to_string()),
+                            get_field_typescript_type(field)
+                        ))
+                    })
+                    .collect::<String>();
 
-        let expected_output = r#"/* AUTOGENERATED BUFFERFISH FILE, DO NOT EDIT */
-import { Bufferfish } from 'bufferfish'
+                lines.push_str(format!("    | {{ kind: '{kind}'{fields} }}\n").as_str());
+            }
+            Fields::Unnamed(fields_unnamed) => {
+                let field_types = fields_unnamed
+                    .unnamed
+                    .iter()
+                    .map(get_field_typescript_type)
+                    .collect::<Vec<_>>();
 
-export enum MessageId {
-    Join = 0,
-    Leave = 1,
-    Ping = 2,
-    Unknown = 255,
-}
+                let value_type = match field_types.as_slice() {
+                    [] => None,
+                    [field_type] => Some(field_type.clone()),
+                    _ => Some(format!("[{}]", field_types.join(", "))),
+                };
 
-export function decodeMessageId(bf: Bufferfish): MessageId {
-    return bf.readUint16() as MessageId
+                match value_type {
+                    Some(value_type) => lines.push_str(
+                        format!("    | {{ kind: '{kind}', value: {value_type} }}\n").as_str(),
+                    ),
+                    None => lines.push_str(format!("    | {{ kind: '{kind}' }}\n").as_str()),
+                }
+            }
+            Fields::Unit => {
+                lines.push_str(format!("    | {{ kind: '{kind}' }}\n").as_str());
+            }
+        }
+    }
 }
 
-export function encodeMessageId(bf: Bufferfish, value: MessageId): void {
-    bf.writeUint16(value)
-}
+fn generate_typescript_union_decoders(item: &ItemEnum, output: &mut String) {
+    let enum_name = item.ident.to_string();
+    let type_params = get_type_params(&item.generics);
+    let generics = format_type_params(&type_params);
+    let codecs = format_decoder_params(&type_params);
+    let (read_fn, _) = get_enum_tag_fns(&item.attrs);
 
-export interface JoinMessage {
-    id: number
-    username: string
-}
+    output.push_str(
+        format!(
+            "\nexport function decode{enum_name}{generics}(bf: Bufferfish{codecs}): {enum_name}{generics} {{\n"
+        )
+        .as_str(),
+    );
+    output.push_str(format!("    const tag = bf.{read_fn}()\n").as_str());
+    output.push_str("    switch (tag) {\n");
 
-export function decodeJoinMessage(bf: Bufferfish): JoinMessage {
-    return {
-        id: bf.readUint8() as number,
-        username: bf.readString() as string,
-    }
-}
+    for (variant, discriminant) in item.variants.iter().zip(get_enum_discriminants(item)) {
+        let kind = variant.ident.to_string();
+        let tag = format_enum_tag(&item.attrs, discriminant);
 
-export function encodeJoinMessage(bf: Bufferfish, value: JoinMessage): void {
-    encodeMessageId(bf, MessageId.Join)
-    bf.writeUint8(value.id)
-    bf.writeString(value.username)
-}
+        let fields = match &variant.fields {
+            Fields::Named(fields_named) => fields_named
+                .named
+                .iter()
+                .filter_map(|field| {
+                    let field_name = field.ident.as_ref()?;
+                    Some(format!(
+                        ", {}: {}",
+                        snake_to_camel_case(field_name.to_string()),
+                        get_field_bufferfish_fn(field)
+                    ))
+                })
+                .collect::<String>(),
+            Fields::Unnamed(fields_unnamed) => {
+                let decoders = fields_unnamed
+                    .unnamed
+                    .iter()
+                    .map(get_field_bufferfish_fn)
+                    .collect::<Vec<_>>();
 
-export function encodeLeaveMessage(bf: Bufferfish): void {
-    encodeMessageId(bf, MessageId.Leave)
-}
+                match decoders.as_slice() {
+                    [] => String::new(),
+                    [decoder] => format!(", value: {decoder}"),
+                    _ => format!(", value: [{}]", decoders.join(", ")),
+                }
+            }
+            Fields::Unit => String::new(),
+        };
 
-export type UnknownMessage = [number, number]
+        output.push_str(format!("        case {tag}:\n").as_str());
+        output.push_str(format!("            return {{ kind: '{kind}'{fields} }}\n").as_str());
+    }
 
-export function decodeUnknownMessage(bf: Bufferfish): UnknownMessage {
-    return [
-        bf.readUint8() as number,
-        bf.readUint16() as number,
-    ]
+    output.push_str("        default:\n");
+    output.push_str(
+        format!("            throw new Error(`unknown variant ${{tag}} for {enum_name}`)\n")
+            .as_str(),
+    );
+    output.push_str("    }\n");
+    output.push_str("}\n");
 }
 
-export function encodeUnknownMessage(bf: Bufferfish, value: UnknownMessage): void {
-    encodeMessageId(bf, MessageId.Unknown)
-    bf.writeUint8(value[0])
+fn generate_typescript_union_encoders(
+    item: &ItemEnum,
+    encoder_name: &str,
+    output: &mut String,
+    message_structs: &[String],
+) {
+    let enum_name = item.ident.to_string();
+    let type_params = get_type_params(&item.generics);
+    let generics = format_type_params(&type_params);
+    let codecs = format_encoder_params(&type_params);
+    let (_, write_fn) = get_enum_tag_fns(&item.attrs);
+
+    output.push_str(
+        format!(
+            "\nexport function encode{encoder_name}{generics}(bf: Bufferfish, value: {enum_name}{generics}{codecs}): void {{\n"
+        )
+        .as_str(),
+    );
+    output.push_str("    switch (value.kind) {\n");
+
+    for (variant, discriminant) in item.variants.iter().zip(get_enum_discriminants(item)) {
+        let kind = variant.ident.to_string();
+        let tag = format_enum_tag(&item.attrs, discriminant);
+
+        let mut writes = Vec::new();
+        match &variant.fields {
+            Fields::Named(fields_named) => {
+                for field in &fields_named.named {
+                    if let Some(field_name) = &field.ident {
+                        let field_ts_name = snake_to_camel_case(field_name.to_string());
+                        writes.push(get_field_write_fn(
+                            field,
+                            &format!("value.{field_ts_name}"),
+                            message_structs,
+                        ));
+                    }
+                }
+            }
+            Fields::Unnamed(fields_unnamed) => {
+                let is_tuple = fields_unnamed.unnamed.len() > 1;
+                for (i, field) in fields_unnamed.unnamed.iter().enumerate() {
+                    let accessor = if is_tuple {
+                        format!("value.value[{i}]")
+                    } else {
+                        "value.value".to_string()
+                    };
+                    writes.push(get_field_write_fn(field, &accessor, message_structs));
+                }
+            }
+            Fields::Unit => {}
+        }
+
+        output.push_str(format!("        case '{kind}':\n").as_str());
+        output.push_str(format!("            bf.{write_fn}({tag})\n").as_str());
+        for write in writes {
+            // Field writers are indented for a function body, so nest any
+            // continuation lines under the `case`.
+            let write = write.replace('\n', "\n        ");
+            output.push_str(format!("            {write}\n").as_str());
+        }
+        output.push_str("            break\n");
+    }
+
+    output.push_str("    }\n");
+    output.push_str("}\n");
+}
+
+fn get_diagnostics(files: Vec<(String, Vec<Item>)>) -> Vec<Diagnostic> {
+    let mut parsed = Vec::new();
+    let mut known_types = Vec::new();
+
+    let mut message_types = Vec::new();
+
+    for (path, items) in files {
+        let message_sets = get_message_sets(&items);
+        let (structs, enums) = get_items_implementing_encode(items);
+        known_types.extend(structs.iter().map(|item| item.ident.to_string()));
+        known_types.extend(enums.iter().map(|item| item.ident.to_string()));
+
+        let attrs = structs
+            .iter()
+            .map(|item| (&item.ident, &item.attrs))
+            .chain(enums.iter().map(|item| (&item.ident, &item.attrs)));
+        for (ident, attrs) in attrs {
+            if get_message_id(attrs).is_some() {
+                message_types.push(ident.to_string());
+            }
+        }
+
+        parsed.push((path, structs, enums, message_sets));
+    }
+
+    let mut diagnostics = Vec::new();
+    for (path, structs, enums, message_sets) in &parsed {
+        let mut push = |item: &syn::Ident, span: proc_macro2::Span, message: String| {
+            let start = span.start();
+            diagnostics.push(Diagnostic {
+                file: path.clone(),
+                line: start.line,
+                column: start.column + 1,
+                item: item.to_string(),
+                message,
+            });
+        };
+
+        for item in structs {
+            let type_params = get_type_params(&item.generics);
+            for field in &item.fields {
+                if let Some((ty, message)) = check_field_type(field, &type_params, &known_types) {
+                    push(&item.ident, ty.span(), message);
+                }
+            }
+        }
+
+        for item in enums {
+            if let Some(repr) = get_repr_type(&item.attrs)
+                && !matches!(
+                    repr.as_str(),
+                    "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+                )
+            {
+                push(
+                    &item.ident,
+                    item.ident.span(),
+                    format!("unsupported repr `{repr}`, expected an integer type"),
+                );
+            }
+
+            let type_params = get_type_params(&item.generics);
+            for field in item.variants.iter().flat_map(|v| v.fields.iter()) {
+                if let Some((ty, message)) = check_field_type(field, &type_params, &known_types) {
+                    push(&item.ident, ty.span(), message);
+                }
+            }
+        }
+
+        // The decoder switches on each variant's message ID, so a variant
+        // without one could be encoded but never decoded.
+        for item in message_sets {
+            let type_params = get_type_params(&item.generics);
+            for field in item.variants.iter().flat_map(|v| v.fields.iter()) {
+                if let Some((ty, message)) = check_type(&field.ty, &type_params, &known_types) {
+                    push(&item.ident, ty.span(), message);
+                } else if let Type::Path(TypePath { path, .. }) = &field.ty
+                    && let Some(segment) = path.segments.last()
+                    && !message_types.contains(&segment.ident.to_string())
+                {
+                    push(
+                        &item.ident,
+                        field.ty.span(),
+                        format!(
+                            "`{}` has no message ID; add one with `#[bufferfish(..)]`",
+                            segment.ident
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn check_field_type<'a>(
+    field: &'a Field,
+    type_params: &[String],
+    known_types: &[String],
+) -> Option<(&'a Type, String)> {
+    if get_field_codec_type(field).is_some() {
+        return None;
+    }
+
+    if let Some(prefix) = get_field_options(field).length_prefix
+        && !matches!(prefix.as_str(), "u16" | "u32" | "varint")
+    {
+        return Some((
+            &field.ty,
+            format!("unknown length prefix `{prefix}`, expected `u16`, `u32` or `varint`"),
+        ));
+    }
+
+    if get_field_options(field).varint && get_field_varint_type(field).is_none() {
+        return Some((
+            &field.ty,
+            format!(
+                "`varint` can only be used on `u16`, `u32`, `u64`, `i16`, `i32` or `i64` fields, not `{}`",
+                format_type(&field.ty)
+            ),
+        ));
+    }
+
+    check_type(&field.ty, type_params, known_types)
+}
+
+/// Returns the first part of a type the TypeScript compiler can not handle,
+/// along with a message describing why.
+fn check_type<'a>(
+    ty: &'a Type,
+    type_params: &[String],
+    known_types: &[String],
+) -> Option<(&'a Type, String)> {
+    match ty {
+        Type::Reference(type_ref) => return check_type(&type_ref.elem, type_params, known_types),
+        Type::Slice(type_slice) => return check_type(&type_slice.elem, type_params, known_types),
+        _ => {}
+    }
+
+    if let Type::Tuple(tuple) = ty {
+        return tuple
+            .elems
+            .iter()
+            .find_map(|elem| check_type(elem, type_params, known_types));
+    }
+
+    if let Type::Array(array) = ty {
+        if get_array_type(ty).is_none() {
+            return Some((
+                ty,
+                format!(
+                    "unsupported array `{}`; the length must be an integer literal",
+                    format_type(ty)
+                ),
+            ));
+        }
+
+        return check_type(&array.elem, type_params, known_types);
+    }
+
+    let Type::Path(TypePath { path, .. }) = ty else {
+        return Some((ty, format!("unsupported type `{}`", format_type(ty))));
+    };
+
+    if path.segments.len() == 1
+        && (path.segments[0].ident == "Vec"
+            || path.segments[0].ident == "VecDeque"
+            || path.segments[0].ident == "Option"
+            || path.segments[0].ident == "Box"
+            || path.segments[0].ident == "Rc"
+            || path.segments[0].ident == "Arc")
+        && let PathArguments::AngleBracketed(args) = &path.segments[0].arguments
+        && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+    {
+        return check_type(inner_ty, type_params, known_types);
+    }
+
+    // A `Cow`'s lifetime comes before the type it borrows.
+    if path.segments.len() == 1
+        && path.segments[0].ident == "Cow"
+        && let PathArguments::AngleBracketed(args) = &path.segments[0].arguments
+        && let Some(inner_ty) = args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(arg) => Some(arg),
+            _ => None,
+        })
+    {
+        return check_type(inner_ty, type_params, known_types);
+    }
+
+    if get_set_type(path).is_some()
+        && let Some(segment) = path.segments.last()
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+        && let Some(GenericArgument::Type(item_ty)) = args.args.first()
+    {
+        return check_type(item_ty, type_params, known_types);
+    }
+
+    // Any hasher given as a third argument is not checked, as it is not
+    // written to the wire.
+    if get_map_types(path).is_some()
+        && let Some(segment) = path.segments.last()
+        && let PathArguments::AngleBracketed(args) = &segment.arguments
+    {
+        return args.args.iter().take(2).find_map(|arg| match arg {
+            GenericArgument::Type(arg) => check_type(arg, type_params, known_types),
+            _ => None,
+        });
+    }
+
+    // Any other type is referred to by its name alone in the generated
+    // TypeScript, so can't be written with its module path.
+    if path.leading_colon.is_some() || path.segments.len() != 1 {
+        return Some((
+            ty,
+            format!(
+                "unsupported type `{}`; import it and refer to it by name",
+                format_type(ty)
+            ),
+        ));
+    }
+
+    if let Some(ident) = path.get_ident() {
+        let name = ident.to_string();
+        if is_primitive_type(ty) || name == "str" || type_params.contains(&name) {
+            return None;
+        }
+    }
+
+    let segment = path.segments.last()?;
+    if !known_types.contains(&segment.ident.to_string()) {
+        return Some((
+            ty,
+            format!(
+                "unknown type `{}`; derive `Encode` for it, or use `#[bufferfish(with = \"..\")]`",
+                segment.ident
+            ),
+        ));
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    args.args.iter().find_map(|arg| match arg {
+        GenericArgument::Type(arg) => check_type(arg, type_params, known_types),
+        _ => None,
+    })
+}
+
+/// Formats a Rust type as it would usually be written, eg. `&'static str`
+/// rather than the token spacing of `& 'static str`.
+fn format_type(ty: &Type) -> String {
+    let mut formatted = ty.to_token_stream().to_string();
+    for (from, to) in [
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        (" ;", ";"),
+        (" :: ", "::"),
+        ("& ", "&"),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+    ] {
+        formatted = formatted.replace(from, to);
+    }
+
+    formatted
+}
+
+fn get_repr_type(attrs: &[Attribute]) -> Option<String> {
+    for attr in attrs {
+        if attr.path().is_ident("repr")
+            && let Meta::List(list) = &attr.meta
+        {
+            for item in list.tokens.clone() {
+                if let Some(ident) = item.to_string().split_whitespace().next() {
+                    return Some(ident.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Generates TypeScript for every item in `src`, as `generate` would, and
+    /// compares it to `expected`, ignoring leading and trailing whitespace.
+    fn assert_ts_output(src: &str, expected: &str) {
+        let items = syn::parse_file(src)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let message_sets = get_message_sets(&items);
+        let (structs, enums) = get_items_implementing_encode(items);
+
+        let mut output = String::new();
+        generate_typescript_items(&structs, &enums, &message_sets, &mut output);
+
+        assert_eq!(output.trim(), expected.trim());
+    }
+
+    #[test]
+    fn test_ts_generation() {
+        let test_file = r#"
+#[derive(Encode)]
+#[repr(u16)]
+pub enum MessageId {
+    Join = 0,
+    Leave,
+    Ping,
+    Unknown = 255,
+}
+
+#[derive(Encode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub id: u8,
+    pub username: String,
+}
+
+#[derive(Encode)]
+#[bufferfish(MessageId::Leave)]
+pub struct LeaveMessage;
+
+#[derive(Encode)]
+#[bufferfish(MessageId::Unknown)]
+pub struct UnknownMessage(pub u8, pub u16);
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Ping)]
+pub struct PingMessage {
+    pub timestamp: Option<u64>,
+}
+    "#;
+
+        let expected_output = r#"
+export enum MessageId {
+    Join = 0,
+    Leave = 1,
+    Ping = 2,
+    Unknown = 255,
+}
+
+export function decodeMessageId(bf: Bufferfish): MessageId {
+    return bf.readUint16() as MessageId
+}
+
+export function encodeMessageId(bf: Bufferfish, value: MessageId): void {
+    bf.writeUint16(value)
+}
+
+export interface JoinMessage {
+    id: number
+    username: string
+}
+
+export function decodeJoinMessage(bf: Bufferfish): JoinMessage {
+    return {
+        id: bf.readUint8() as number,
+        username: bf.readString() as string,
+    }
+}
+
+export function encodeJoinMessage(bf: Bufferfish, value: JoinMessage): void {
+    encodeMessageId(bf, MessageId.Join)
+    encodeJoinMessageValue(bf, value)
+}
+
+export function encodeJoinMessageValue(bf: Bufferfish, value: JoinMessage): void {
+    bf.writeUint8(value.id)
+    bf.writeString(value.username)
+}
+
+export function encodeLeaveMessage(bf: Bufferfish): void {
+    encodeMessageId(bf, MessageId.Leave)
+}
+
+export type UnknownMessage = [number, number]
+
+export function decodeUnknownMessage(bf: Bufferfish): UnknownMessage {
+    return [
+        bf.readUint8() as number,
+        bf.readUint16() as number,
+    ]
+}
+
+export function encodeUnknownMessage(bf: Bufferfish, value: UnknownMessage): void {
+    encodeMessageId(bf, MessageId.Unknown)
+    encodeUnknownMessageValue(bf, value)
+}
+
+export function encodeUnknownMessageValue(bf: Bufferfish, value: UnknownMessage): void {
+    bf.writeUint8(value[0])
     bf.writeUint16(value[1])
 }
 
@@ -831,6 +2174,10 @@ export function decodePingMessage(bf: Bufferfish): PingMessage {
 
 export function encodePingMessage(bf: Bufferfish, value: PingMessage): void {
     encodeMessageId(bf, MessageId.Ping)
+    encodePingMessageValue(bf, value)
+}
+
+export function encodePingMessageValue(bf: Bufferfish, value: PingMessage): void {
     if (value.timestamp !== null) {
         bf.writeUint8(1)
         bf.writeUint64(value.timestamp)
@@ -839,52 +2186,1414 @@ export function encodePingMessage(bf: Bufferfish, value: PingMessage): void {
     }
 }"#;
 
-        let items = syn::parse_file(test_file)
-            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
-            .items;
-        let (structs, enums) = get_items_implementing_encode(items);
+        assert_ts_output(test_file, expected_output);
+    }
 
-        let mut output = String::new();
-        output.push_str("/* AUTOGENERATED BUFFERFISH FILE, DO NOT EDIT */\n");
-        output.push_str("import { Bufferfish } from 'bufferfish'\n");
+    #[test]
+    fn test_ts_generation_floats() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Position {
+    pub x: f32,
+    pub y: f64,
+    pub history: Vec<f32>,
+}
+    "#;
 
-        let mut message_id_enum_names = Vec::new();
+        let expected_output = r#"
+export interface Position {
+    x: number
+    y: number
+    history: Array<number>
+}
 
-        for item in &structs {
-            if let Some(message_id) = get_message_id(&item.attrs)
-                && let Some(enum_name) = extract_enum_name_from_message_id(&message_id)
-                && !message_id_enum_names.contains(&enum_name)
-            {
-                message_id_enum_names.push(enum_name);
-            }
-        }
+export function decodePosition(bf: Bufferfish): Position {
+    return {
+        x: bf.readFloat32() as number,
+        y: bf.readFloat64() as number,
+        history: bf.readArray(() => bf.readFloat32() as number) as Array<number>,
+    }
+}
 
-        for item in &enums {
-            generate_typescript_enum_defs(item.clone(), &mut output);
-            generate_typescript_enum_decoders(item.clone(), &mut output);
-        }
+export function encodePosition(bf: Bufferfish, value: Position): void {
+    bf.writeFloat32(value.x)
+    bf.writeFloat64(value.y)
+    bf.writeLength(value.history.length)
+    for (const item of value.history) {
+        bf.writeFloat32(item)
+    }
+}"#;
 
-        let mut generated_encoders = std::collections::HashSet::new();
-        for item in &enums {
-            let enum_name = item.ident.to_string();
-            if message_id_enum_names.contains(&enum_name) {
-                generate_typescript_message_id_encoder(item.clone(), &mut output);
-                generated_encoders.insert(enum_name);
-            } else if !generated_encoders.contains(&enum_name) {
-                generate_typescript_enum_encoders(item.clone(), &mut output);
-            }
-        }
+        assert_ts_output(test_file, expected_output);
+    }
 
-        for item in &structs {
-            generate_typescript_struct_defs(item.clone(), &mut output);
-            generate_typescript_struct_decoders(item.clone(), &mut output);
-            generate_typescript_struct_encoders(item.clone(), &mut output, &message_id_enum_names);
-        }
+    #[test]
+    fn test_ts_enum_negative_discriminants() {
+        let item: ItemEnum = syn::parse_str(
+            r#"
+#[derive(Encode)]
+#[repr(i8)]
+pub enum Direction {
+    Left = -1,
+    None,
+    Right,
+}"#,
+        )
+        .unwrap();
 
-        if output.trim() != expected_output.trim() {
-            println!("Expected:\n{expected_output}");
-            println!("Got:\n{output}");
-            panic!("Output does not match expected output");
-        }
+        let mut output = String::new();
+        generate_typescript_enum_defs(item, &mut output);
+
+        assert_eq!(
+            output,
+            "\nexport enum Direction {\n    Left = -1,\n    None = 0,\n    Right = 1,\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_ts_message_set_generation() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub id: u8,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Leave)]
+pub struct LeaveMessage;
+
+#[derive(MessageSet)]
+pub enum ClientMessage {
+    Join(JoinMessage),
+    Leave(LeaveMessage),
+}
+    "#;
+
+        let expected_output = r#"
+export interface JoinMessage {
+    id: number
+}
+
+export function decodeJoinMessage(bf: Bufferfish): JoinMessage {
+    return {
+        id: bf.readUint8() as number,
+    }
+}
+
+export function encodeJoinMessage(bf: Bufferfish, value: JoinMessage): void {
+    encodeMessageId(bf, MessageId.Join)
+    encodeJoinMessageValue(bf, value)
+}
+
+export function encodeJoinMessageValue(bf: Bufferfish, value: JoinMessage): void {
+    bf.writeUint8(value.id)
+}
+
+export function encodeLeaveMessage(bf: Bufferfish): void {
+    encodeMessageId(bf, MessageId.Leave)
+}
+
+export type ClientMessage =
+    | { kind: 'Join', value: JoinMessage }
+    | { kind: 'Leave' }
+
+export function decodeClientMessage(bf: Bufferfish): ClientMessage {
+    const messageId = bf.readUint16()
+    switch (messageId) {
+        case MessageId.Join:
+            return { kind: 'Join', value: decodeJoinMessage(bf) }
+        case MessageId.Leave:
+            return { kind: 'Leave' }
+        default:
+            throw new Error(`unknown message id ${messageId} for ClientMessage`)
+    }
+}
+
+export function encodeClientMessage(bf: Bufferfish, value: ClientMessage): void {
+    switch (value.kind) {
+        case 'Join':
+            encodeJoinMessage(bf, value.value)
+            break
+        case 'Leave':
+            encodeLeaveMessage(bf)
+            break
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_message_id_case() {
+        assert_eq!(get_message_id_case("MessageId.Join"), "MessageId.Join");
+        assert_eq!(get_message_id_case("3_u16"), "3");
+    }
+
+    #[test]
+    fn test_ts_skipped_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub id: u8,
+    #[bufferfish(skip)]
+    pub cache: Vec<u8>,
+    #[bufferfish(skip, default = 5)]
+    pub retries: u8,
+    pub username: String,
+}
+
+#[derive(Encode, Decode)]
+pub struct Position(u16, #[bufferfish(skip)] u32, u16);
+    "#;
+
+        let expected_output = r#"
+export interface JoinMessage {
+    id: number
+    username: string
+}
+
+export function decodeJoinMessage(bf: Bufferfish): JoinMessage {
+    return {
+        id: bf.readUint8() as number,
+        username: bf.readString() as string,
+    }
+}
+
+export function encodeJoinMessage(bf: Bufferfish, value: JoinMessage): void {
+    encodeMessageId(bf, MessageId.Join)
+    encodeJoinMessageValue(bf, value)
+}
+
+export function encodeJoinMessageValue(bf: Bufferfish, value: JoinMessage): void {
+    bf.writeUint8(value.id)
+    bf.writeString(value.username)
+}
+
+export type Position = [number, number]
+
+export function decodePosition(bf: Bufferfish): Position {
+    return [
+        bf.readUint16() as number,
+        bf.readUint16() as number,
+    ]
+}
+
+export function encodePosition(bf: Bufferfish, value: Position): void {
+    bf.writeUint16(value[0])
+    bf.writeUint16(value[1])
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_with_field_codec() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Spawn)]
+pub struct SpawnMessage {
+    #[bufferfish(with = "uuid_codec", ts_module = "./codecs")]
+    pub id: uuid::Uuid,
+    #[bufferfish(with = "vec3_codec", ts_type = "Vector3", ts_module = "./codecs")]
+    pub position: glam::Vec3,
+}
+    "#;
+
+        let expected_output = r#"
+export interface SpawnMessage {
+    id: Uuid
+    position: Vector3
+}
+
+export function decodeSpawnMessage(bf: Bufferfish): SpawnMessage {
+    return {
+        id: decodeUuid(bf),
+        position: decodeVector3(bf),
+    }
+}
+
+export function encodeSpawnMessage(bf: Bufferfish, value: SpawnMessage): void {
+    encodeMessageId(bf, MessageId.Spawn)
+    encodeSpawnMessageValue(bf, value)
+}
+
+export function encodeSpawnMessageValue(bf: Bufferfish, value: SpawnMessage): void {
+    encodeUuid(bf, value.id)
+    encodeVector3(bf, value.position)
+}"#;
+
+        let items = syn::parse_file(test_file)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let (structs, _) = get_items_implementing_encode(items);
+
+        let imports = get_codec_imports(&structs, &[]);
+        assert_eq!(imports.len(), 1);
+        assert_eq!(
+            imports["./codecs"].iter().cloned().collect::<Vec<_>>(),
+            vec![
+                "Uuid",
+                "Vector3",
+                "decodeUuid",
+                "decodeVector3",
+                "encodeUuid",
+                "encodeVector3"
+            ]
+        );
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_varint_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Move {
+    #[bufferfish(varint)]
+    pub entity: u32,
+    #[bufferfish(varint)]
+    pub dx: i16,
+    #[bufferfish(varint)]
+    pub tick: u64,
+    pub speed: u8,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Move {
+    entity: number
+    dx: number
+    tick: bigint
+    speed: number
+}
+
+export function decodeMove(bf: Bufferfish): Move {
+    return {
+        entity: bf.readVarintUint32() as number,
+        dx: bf.readVarintInt16() as number,
+        tick: bf.readVarintUint64() as bigint,
+        speed: bf.readUint8() as number,
+    }
+}
+
+export function encodeMove(bf: Bufferfish, value: Move): void {
+    bf.writeVarintUint32(value.entity)
+    bf.writeVarintInt16(value.dx)
+    bf.writeVarintUint64(value.tick)
+    bf.writeUint8(value.speed)
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(
+            "#[derive(Encode)]\npub struct Bad {\n    #[bufferfish(varint)]\n    pub x: f32,\n}",
+        )
+        .unwrap()
+        .items;
+
+        assert_eq!(
+            get_diagnostics(vec![("src/bad.rs".to_string(), items)]),
+            vec![Diagnostic {
+                file: "src/bad.rs".to_string(),
+                line: 4,
+                column: 12,
+                item: "Bad".to_string(),
+                message: "`varint` can only be used on `u16`, `u32`, `u64`, `i16`, `i32` or `i64` fields, not `f32`".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ts_length_prefix_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Snapshot {
+    pub name: String,
+    #[bufferfish(length_prefix = "u32")]
+    pub tiles: Vec<u8>,
+    #[bufferfish(length_prefix = "varint")]
+    pub log: String,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Snapshot {
+    name: string
+    tiles: Array<number>
+    log: string
+}
+
+export function decodeSnapshot(bf: Bufferfish): Snapshot {
+    return {
+        name: bf.readString() as string,
+        tiles: bf.withLengthPrefix('u32', () => bf.readArray(() => bf.readUint8() as number) as Array<number>),
+        log: bf.withLengthPrefix('varint', () => bf.readString() as string),
+    }
+}
+
+export function encodeSnapshot(bf: Bufferfish, value: Snapshot): void {
+    bf.writeString(value.name)
+    bf.withLengthPrefix('u32', () => {
+        bf.writeLength(value.tiles.length)
+        for (const item of value.tiles) {
+            bf.writeUint8(item)
+        }
+    })
+    bf.withLengthPrefix('varint', () => bf.writeString(value.log))
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(
+            "#[derive(Encode)]\npub struct Bad {\n    #[bufferfish(length_prefix = \"u64\")]\n    pub x: String,\n}",
+        )
+        .unwrap()
+        .items;
+
+        assert_eq!(
+            get_diagnostics(vec![("src/bad.rs".to_string(), items)]),
+            vec![Diagnostic {
+                file: "src/bad.rs".to_string(),
+                line: 4,
+                column: 12,
+                item: "Bad".to_string(),
+                message: "unknown length prefix `u64`, expected `u16`, `u32` or `varint`"
+                    .to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ts_map_and_set_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Inventory {
+    pub items: HashMap<u32, Item>,
+    pub names: BTreeMap<String, u16>,
+    pub tags: HashSet<String>,
+    pub history: VecDeque<u8>,
+}
+
+#[derive(Encode, Decode)]
+pub struct Item {
+    pub count: u16,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Inventory {
+    items: Map<number, Item>
+    names: Map<string, number>
+    tags: Set<string>
+    history: Array<number>
+}
+
+export function decodeInventory(bf: Bufferfish): Inventory {
+    return {
+        items: bf.readMap(() => bf.readUint32() as number, () => decodeItem(bf)) as Map<number, Item>,
+        names: bf.readMap(() => bf.readString() as string, () => bf.readUint16() as number) as Map<string, number>,
+        tags: bf.readSet(() => bf.readString() as string) as Set<string>,
+        history: bf.readArray(() => bf.readUint8() as number) as Array<number>,
+    }
+}
+
+export function encodeInventory(bf: Bufferfish, value: Inventory): void {
+    bf.writeLength(value.items.size)
+    for (const [key, item] of value.items) {
+        bf.writeUint32(key)
+        encodeItem(bf, item)
+    }
+    bf.writeLength(value.names.size)
+    for (const [key, item] of value.names) {
+        bf.writeString(key)
+        bf.writeUint16(item)
+    }
+    bf.writeLength(value.tags.size)
+    for (const item of value.tags) {
+        bf.writeString(item)
+    }
+    bf.writeLength(value.history.length)
+    for (const item of value.history) {
+        bf.writeUint8(item)
+    }
+}
+
+export interface Item {
+    count: number
+}
+
+export function decodeItem(bf: Bufferfish): Item {
+    return {
+        count: bf.readUint16() as number,
+    }
+}
+
+export function encodeItem(bf: Bufferfish, value: Item): void {
+    bf.writeUint16(value.count)
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/inventory.rs".to_string(), items)]),
+            vec![]
+        );
+
+        let items = syn::parse_file(
+            "#[derive(Encode)]\npub struct Bad {\n    pub x: HashMap<u8, Missing, FxBuildHasher>,\n}",
+        )
+        .unwrap()
+        .items;
+
+        assert_eq!(
+            get_diagnostics(vec![("src/bad.rs".to_string(), items)])
+                .into_iter()
+                .map(|d| d.message)
+                .collect::<Vec<_>>(),
+            vec![
+                "unknown type `Missing`; derive `Encode` for it, or use `#[bufferfish(with = \"..\")]`"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ts_qualified_map_and_set_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Scores {
+    pub totals: std::collections::HashMap<String, u32>,
+    pub seen: std::collections::BTreeSet<u16>,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Scores {
+    totals: Map<string, number>
+    seen: Set<number>
+}
+
+export function decodeScores(bf: Bufferfish): Scores {
+    return {
+        totals: bf.readMap(() => bf.readString() as string, () => bf.readUint32() as number) as Map<string, number>,
+        seen: bf.readSet(() => bf.readUint16() as number) as Set<number>,
+    }
+}
+
+export function encodeScores(bf: Bufferfish, value: Scores): void {
+    bf.writeLength(value.totals.size)
+    for (const [key, item] of value.totals) {
+        bf.writeString(key)
+        bf.writeUint32(item)
+    }
+    bf.writeLength(value.seen.size)
+    for (const item of value.seen) {
+        bf.writeUint16(item)
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/scores.rs".to_string(), items)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ts_nested_collection_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Groups {
+    pub members: HashMap<String, Vec<u32>>,
+    pub grid: Vec<Vec<u8>>,
+    pub pages: BTreeSet<Option<Page<u8>>>,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Groups {
+    members: Map<string, Array<number>>
+    grid: Array<Array<number>>
+    pages: Set<Page<number> | null>
+}
+
+export function decodeGroups(bf: Bufferfish): Groups {
+    return {
+        members: bf.readMap(() => bf.readString() as string, () => bf.readArray(() => bf.readUint32() as number) as Array<number>) as Map<string, Array<number>>,
+        grid: bf.readArray(() => bf.readArray(() => bf.readUint8() as number) as Array<number>) as Array<Array<number>>,
+        pages: bf.readSet(() => (bf.readUint8() === 1 ? decodePage(bf, (bf: Bufferfish) => bf.readUint8() as number) : null) as Page<number> | null) as Set<Page<number> | null>,
+    }
+}
+
+export function encodeGroups(bf: Bufferfish, value: Groups): void {
+    bf.writeLength(value.members.size)
+    for (const [key, item] of value.members) {
+        bf.writeString(key)
+        bf.writeLength(item.length)
+        for (const item2 of item) {
+            bf.writeUint32(item2)
+        }
+    }
+    bf.writeLength(value.grid.length)
+    for (const item of value.grid) {
+        bf.writeLength(item.length)
+        for (const item2 of item) {
+            bf.writeUint8(item2)
+        }
+    }
+    bf.writeLength(value.pages.size)
+    for (const item of value.pages) {
+        if (item !== null) {
+            bf.writeUint8(1)
+            encodePage(bf, item, (bf: Bufferfish, value: number) => bf.writeUint8(value))
+        } else {
+            bf.writeUint8(0)
+        }
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_fixed_array_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Block {
+    pub hash: [u8; 32],
+    pub position: [f32; 3],
+    pub corners: [Point; 2],
+}
+
+#[derive(Encode, Decode)]
+pub struct Point {
+    pub x: u16,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Block {
+    hash: Uint8Array
+    position: [number, number, number]
+    corners: [Point, Point]
+}
+
+export function decodeBlock(bf: Bufferfish): Block {
+    return {
+        hash: bf.readFixedBytes(32) as Uint8Array,
+        position: bf.readFixedArray(3, () => bf.readFloat32() as number) as [number, number, number],
+        corners: bf.readFixedArray(2, () => decodePoint(bf)) as [Point, Point],
+    }
+}
+
+export function encodeBlock(bf: Bufferfish, value: Block): void {
+    bf.writeFixedBytes(value.hash, 32)
+    for (const item of value.position) {
+        bf.writeFloat32(item)
+    }
+    for (const item of value.corners) {
+        encodePoint(bf, item)
+    }
+}
+
+export interface Point {
+    x: number
+}
+
+export function decodePoint(bf: Bufferfish): Point {
+    return {
+        x: bf.readUint16() as number,
+    }
+}
+
+export function encodePoint(bf: Bufferfish, value: Point): void {
+    bf.writeUint16(value.x)
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/block.rs".to_string(), items)]),
+            vec![]
+        );
+
+        let items = syn::parse_file(
+            "#[derive(Encode)]\npub struct Bad {\n    pub x: [u8; LEN],\n    pub y: [Missing; 2],\n}",
+        )
+        .unwrap()
+        .items;
+
+        assert_eq!(
+            get_diagnostics(vec![("src/bad.rs".to_string(), items)])
+                .into_iter()
+                .map(|d| d.message)
+                .collect::<Vec<_>>(),
+            vec![
+                "unsupported array `[u8; LEN]`; the length must be an integer literal".to_string(),
+                "unknown type `Missing`; derive `Encode` for it, or use `#[bufferfish(with = \"..\")]`"
+                    .to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ts_nested_array_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Tiles {
+    pub keys: [[u8; 2]; 3],
+    pub hashes: Vec<[u8; 4]>,
+    pub matrix: [[f32; 2]; 2],
+}
+    "#;
+
+        let expected_output = r#"
+export interface Tiles {
+    keys: [Uint8Array, Uint8Array, Uint8Array]
+    hashes: Array<Uint8Array>
+    matrix: [[number, number], [number, number]]
+}
+
+export function decodeTiles(bf: Bufferfish): Tiles {
+    return {
+        keys: bf.readFixedArray(3, () => bf.readFixedBytes(2) as Uint8Array) as [Uint8Array, Uint8Array, Uint8Array],
+        hashes: bf.readArray(() => bf.readFixedBytes(4) as Uint8Array) as Array<Uint8Array>,
+        matrix: bf.readFixedArray(2, () => bf.readFixedArray(2, () => bf.readFloat32() as number) as [number, number]) as [[number, number], [number, number]],
+    }
+}
+
+export function encodeTiles(bf: Bufferfish, value: Tiles): void {
+    for (const item of value.keys) {
+        bf.writeFixedBytes(item, 2)
+    }
+    bf.writeLength(value.hashes.length)
+    for (const item of value.hashes) {
+        bf.writeFixedBytes(item, 4)
+    }
+    for (const item of value.matrix) {
+        for (const item2 of item) {
+            bf.writeFloat32(item2)
+        }
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/tiles.rs".to_string(), items)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ts_tuple_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Score {
+    pub entry: (u16, String),
+    pub unit: (),
+    pub ranks: Vec<(u8, Point)>,
+    pub pair: (Option<u8>, Vec<u8>),
+    pub last: Option<(u8, bool)>,
+}
+
+#[derive(Encode, Decode)]
+pub struct Point {
+    pub x: u16,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Score {
+    entry: [number, string]
+    unit: []
+    ranks: Array<[number, Point]>
+    pair: [number | null, Array<number>]
+    last: [number, boolean] | null
+}
+
+export function decodeScore(bf: Bufferfish): Score {
+    return {
+        entry: [bf.readUint16() as number, bf.readString() as string] as [number, string],
+        unit: [] as [],
+        ranks: bf.readArray(() => [bf.readUint8() as number, decodePoint(bf)] as [number, Point]) as Array<[number, Point]>,
+        pair: [(bf.readUint8() === 1 ? bf.readUint8() as number : null) as number | null, bf.readArray(() => bf.readUint8() as number) as Array<number>] as [number | null, Array<number>],
+        last: (bf.readUint8() === 1 ? [bf.readUint8() as number, bf.readBool() as boolean] as [number, boolean] : null) as [number, boolean] | null,
+    }
+}
+
+export function encodeScore(bf: Bufferfish, value: Score): void {
+    bf.writeUint16(value.entry[0])
+    bf.writeString(value.entry[1])
+    {}
+    bf.writeLength(value.ranks.length)
+    for (const item of value.ranks) {
+        bf.writeUint8(item[0])
+        encodePoint(bf, item[1])
+    }
+    if (value.pair[0] !== null) {
+        bf.writeUint8(1)
+        bf.writeUint8(value.pair[0])
+    } else {
+        bf.writeUint8(0)
+    }
+    bf.writeLength(value.pair[1].length)
+    for (const item of value.pair[1]) {
+        bf.writeUint8(item)
+    }
+    if (value.last !== null) {
+        bf.writeUint8(1)
+        bf.writeUint8(value.last[0])
+        bf.writeBool(value.last[1])
+    } else {
+        bf.writeUint8(0)
+    }
+}
+
+export interface Point {
+    x: number
+}
+
+export function decodePoint(bf: Bufferfish): Point {
+    return {
+        x: bf.readUint16() as number,
+    }
+}
+
+export function encodePoint(bf: Bufferfish, value: Point): void {
+    bf.writeUint16(value.x)
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/score.rs".to_string(), items)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ts_pointer_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub enum Expr {
+    Lit(i64),
+    Add(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Encode)]
+pub struct Shared<'a> {
+    pub name: Rc<String>,
+    pub label: Cow<'a, str>,
+    pub tags: &'a [u16],
+    pub root: Arc<Expr>,
+}
+    "#;
+
+        let expected_output = r#"
+export type Expr =
+    | { kind: 'Lit', value: bigint }
+    | { kind: 'Add', value: [Expr, Expr] }
+
+export function decodeExpr(bf: Bufferfish): Expr {
+    const tag = bf.readUint8()
+    switch (tag) {
+        case 0:
+            return { kind: 'Lit', value: bf.readInt64() as bigint }
+        case 1:
+            return { kind: 'Add', value: [decodeExpr(bf), decodeExpr(bf)] }
+        default:
+            throw new Error(`unknown variant ${tag} for Expr`)
+    }
+}
+
+export function encodeExpr(bf: Bufferfish, value: Expr): void {
+    switch (value.kind) {
+        case 'Lit':
+            bf.writeUint8(0)
+            bf.writeInt64(value.value)
+            break
+        case 'Add':
+            bf.writeUint8(1)
+            encodeExpr(bf, value.value[0])
+            encodeExpr(bf, value.value[1])
+            break
+    }
+}
+
+export interface Shared {
+    name: string
+    label: string
+    tags: Array<number>
+    root: Expr
+}
+
+export function decodeShared(bf: Bufferfish): Shared {
+    return {
+        name: bf.readString() as string,
+        label: bf.readString() as string,
+        tags: bf.readArray(() => bf.readUint16() as number) as Array<number>,
+        root: decodeExpr(bf),
+    }
+}
+
+export function encodeShared(bf: Bufferfish, value: Shared): void {
+    bf.writeString(value.name)
+    bf.writeString(value.label)
+    bf.writeLength(value.tags.length)
+    for (const item of value.tags) {
+        bf.writeUint16(item)
+    }
+    encodeExpr(bf, value.root)
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/expr.rs".to_string(), items)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ts_byte_buf_fields() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Upload {
+    pub data: ByteBuf,
+    pub chunks: Vec<Bytes>,
+    pub preview: Option<ByteBuf>,
+    pub raw: Vec<u8>,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Upload {
+    data: Uint8Array
+    chunks: Array<Uint8Array>
+    preview: Uint8Array | null
+    raw: Array<number>
+}
+
+export function decodeUpload(bf: Bufferfish): Upload {
+    return {
+        data: bf.readBytes() as Uint8Array,
+        chunks: bf.readArray(() => bf.readBytes() as Uint8Array) as Array<Uint8Array>,
+        preview: (bf.readUint8() === 1 ? bf.readBytes() as Uint8Array : null) as Uint8Array | null,
+        raw: bf.readArray(() => bf.readUint8() as number) as Array<number>,
+    }
+}
+
+export function encodeUpload(bf: Bufferfish, value: Upload): void {
+    bf.writeBytes(value.data)
+    bf.writeLength(value.chunks.length)
+    for (const item of value.chunks) {
+        bf.writeBytes(item)
+    }
+    if (value.preview !== null) {
+        bf.writeUint8(1)
+        bf.writeBytes(value.preview)
+    } else {
+        bf.writeUint8(0)
+    }
+    bf.writeLength(value.raw.length)
+    for (const item of value.raw) {
+        bf.writeUint8(item)
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+
+        let items = syn::parse_file(test_file).unwrap().items;
+        assert_eq!(
+            get_diagnostics(vec![("src/upload.rs".to_string(), items)]),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_ts_generic_structs() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub struct Envelope<T> {
+    pub seq: u32,
+    pub body: T,
+    pub history: Vec<T>,
+}
+
+#[derive(Encode, Decode)]
+pub struct Pair<A, B>(A, B);
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Chat)]
+pub struct ChatEnvelope {
+    pub inner: Envelope<String>,
+    pub pairs: Pair<u8, Vec<u16>>,
+}
+    "#;
+
+        let expected_output = r#"
+export interface Envelope<T> {
+    seq: number
+    body: T
+    history: Array<T>
+}
+
+export function decodeEnvelope<T>(bf: Bufferfish, decodeT: (bf: Bufferfish) => T): Envelope<T> {
+    return {
+        seq: bf.readUint32() as number,
+        body: decodeT(bf),
+        history: bf.readArray(() => decodeT(bf)) as Array<T>,
+    }
+}
+
+export function encodeEnvelope<T>(bf: Bufferfish, value: Envelope<T>, encodeT: (bf: Bufferfish, value: T) => void): void {
+    bf.writeUint32(value.seq)
+    encodeT(bf, value.body)
+    bf.writeLength(value.history.length)
+    for (const item of value.history) {
+        encodeT(bf, item)
+    }
+}
+
+export type Pair<A, B> = [A, B]
+
+export function decodePair<A, B>(bf: Bufferfish, decodeA: (bf: Bufferfish) => A, decodeB: (bf: Bufferfish) => B): Pair<A, B> {
+    return [
+        decodeA(bf),
+        decodeB(bf),
+    ]
+}
+
+export function encodePair<A, B>(bf: Bufferfish, value: Pair<A, B>, encodeA: (bf: Bufferfish, value: A) => void, encodeB: (bf: Bufferfish, value: B) => void): void {
+    encodeA(bf, value[0])
+    encodeB(bf, value[1])
+}
+
+export interface ChatEnvelope {
+    inner: Envelope<string>
+    pairs: Pair<number, Array<number>>
+}
+
+export function decodeChatEnvelope(bf: Bufferfish): ChatEnvelope {
+    return {
+        inner: decodeEnvelope(bf, (bf: Bufferfish) => bf.readString() as string),
+        pairs: decodePair(bf, (bf: Bufferfish) => bf.readUint8() as number, (bf: Bufferfish) => bf.readArray(() => bf.readUint16() as number) as Array<number>),
+    }
+}
+
+export function encodeChatEnvelope(bf: Bufferfish, value: ChatEnvelope): void {
+    encodeMessageId(bf, MessageId.Chat)
+    encodeChatEnvelopeValue(bf, value)
+}
+
+export function encodeChatEnvelopeValue(bf: Bufferfish, value: ChatEnvelope): void {
+    encodeEnvelope(bf, value.inner, (bf: Bufferfish, value: string) => bf.writeString(value))
+    encodePair(bf, value.pairs, (bf: Bufferfish, value: number) => bf.writeUint8(value), (bf: Bufferfish, value: Array<number>) => { bf.writeLength(value.length)
+    for (const item of value) {
+        bf.writeUint16(item)
+    } })
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_data_enum_generation() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+pub enum Event {
+    Move { x: i32, y: i32 },
+    Chat(String),
+    Trade(u8, Vec<u16>),
+    Quit,
+}
+
+#[derive(Encode, Decode)]
+#[repr(u16)]
+pub enum Command {
+    Spawn { entity_id: u32 } = 10,
+    Despawn(u32),
+}
+    "#;
+
+        let expected_output = r#"
+export type Event =
+    | { kind: 'Move', x: number, y: number }
+    | { kind: 'Chat', value: string }
+    | { kind: 'Trade', value: [number, Array<number>] }
+    | { kind: 'Quit' }
+
+export function decodeEvent(bf: Bufferfish): Event {
+    const tag = bf.readUint8()
+    switch (tag) {
+        case 0:
+            return { kind: 'Move', x: bf.readInt32() as number, y: bf.readInt32() as number }
+        case 1:
+            return { kind: 'Chat', value: bf.readString() as string }
+        case 2:
+            return { kind: 'Trade', value: [bf.readUint8() as number, bf.readArray(() => bf.readUint16() as number) as Array<number>] }
+        case 3:
+            return { kind: 'Quit' }
+        default:
+            throw new Error(`unknown variant ${tag} for Event`)
+    }
+}
+
+export type Command =
+    | { kind: 'Spawn', entityId: number }
+    | { kind: 'Despawn', value: number }
+
+export function decodeCommand(bf: Bufferfish): Command {
+    const tag = bf.readUint16()
+    switch (tag) {
+        case 10:
+            return { kind: 'Spawn', entityId: bf.readUint32() as number }
+        case 11:
+            return { kind: 'Despawn', value: bf.readUint32() as number }
+        default:
+            throw new Error(`unknown variant ${tag} for Command`)
+    }
+}
+
+export function encodeEvent(bf: Bufferfish, value: Event): void {
+    switch (value.kind) {
+        case 'Move':
+            bf.writeUint8(0)
+            bf.writeInt32(value.x)
+            bf.writeInt32(value.y)
+            break
+        case 'Chat':
+            bf.writeUint8(1)
+            bf.writeString(value.value)
+            break
+        case 'Trade':
+            bf.writeUint8(2)
+            bf.writeUint8(value.value[0])
+            bf.writeLength(value.value[1].length)
+            for (const item of value.value[1]) {
+                bf.writeUint16(item)
+            }
+            break
+        case 'Quit':
+            bf.writeUint8(3)
+            break
+    }
+}
+
+export function encodeCommand(bf: Bufferfish, value: Command): void {
+    switch (value.kind) {
+        case 'Spawn':
+            bf.writeUint16(10)
+            bf.writeUint32(value.entityId)
+            break
+        case 'Despawn':
+            bf.writeUint16(11)
+            bf.writeUint32(value.value)
+            break
+    }
+}"#;
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_nested_message_structs() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub name: String,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Lobby)]
+pub struct LobbyMessage {
+    pub host: JoinMessage,
+    pub guests: Vec<JoinMessage>,
+    pub pending: Option<JoinMessage>,
+    #[bufferfish(with_header)]
+    pub last: JoinMessage,
+}
+    "#;
+
+        let expected_output = r#"
+export interface JoinMessage {
+    name: string
+}
+
+export function decodeJoinMessage(bf: Bufferfish): JoinMessage {
+    return {
+        name: bf.readString() as string,
+    }
+}
+
+export function encodeJoinMessage(bf: Bufferfish, value: JoinMessage): void {
+    encodeMessageId(bf, MessageId.Join)
+    encodeJoinMessageValue(bf, value)
+}
+
+export function encodeJoinMessageValue(bf: Bufferfish, value: JoinMessage): void {
+    bf.writeString(value.name)
+}
+
+export interface LobbyMessage {
+    host: JoinMessage
+    guests: Array<JoinMessage>
+    pending: JoinMessage | null
+    last: JoinMessage
+}
+
+export function decodeLobbyMessage(bf: Bufferfish): LobbyMessage {
+    return {
+        host: decodeJoinMessage(bf),
+        guests: bf.readArray(() => decodeJoinMessage(bf)) as Array<JoinMessage>,
+        pending: (bf.readUint8() === 1 ? decodeJoinMessage(bf) : null) as JoinMessage | null,
+        last: (bf.readUint16(), decodeJoinMessage(bf)),
+    }
+}
+
+export function encodeLobbyMessage(bf: Bufferfish, value: LobbyMessage): void {
+    encodeMessageId(bf, MessageId.Lobby)
+    encodeLobbyMessageValue(bf, value)
+}
+
+export function encodeLobbyMessageValue(bf: Bufferfish, value: LobbyMessage): void {
+    encodeJoinMessageValue(bf, value.host)
+    bf.writeLength(value.guests.length)
+    for (const item of value.guests) {
+        encodeJoinMessageValue(bf, item)
+    }
+    if (value.pending !== null) {
+        bf.writeUint8(1)
+        encodeJoinMessageValue(bf, value.pending)
+    } else {
+        bf.writeUint8(0)
+    }
+    encodeJoinMessage(bf, value.last)
+}"#;
+
+        let items = syn::parse_file(test_file)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let (structs, _) = get_items_implementing_encode(items);
+        let message_id_enums = vec!["MessageId".to_string()];
+        let message_structs = get_message_structs(&structs, &[], &message_id_enums);
+        assert_eq!(message_structs, vec!["JoinMessage", "LobbyMessage"]);
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_ts_message_enums() {
+        let test_file = r#"
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Status)]
+pub enum Status {
+    Idle,
+    Busy,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Input)]
+pub enum Input {
+    Key(u8),
+    Move { x: i16, y: i16 },
+}
+
+#[derive(Encode, Decode)]
+pub struct Replay {
+    pub inputs: Vec<Input>,
+}
+    "#;
+
+        let expected_output = r#"
+export enum Status {
+    Idle = 0,
+    Busy = 1,
+}
+
+export function decodeStatus(bf: Bufferfish): Status {
+    return bf.readUint8() as Status
+}
+
+export type Input =
+    | { kind: 'Key', value: number }
+    | { kind: 'Move', x: number, y: number }
+
+export function decodeInput(bf: Bufferfish): Input {
+    const tag = bf.readUint8()
+    switch (tag) {
+        case 0:
+            return { kind: 'Key', value: bf.readUint8() as number }
+        case 1:
+            return { kind: 'Move', x: bf.readInt16() as number, y: bf.readInt16() as number }
+        default:
+            throw new Error(`unknown variant ${tag} for Input`)
+    }
+}
+
+export function encodeStatus(bf: Bufferfish, value: Status): void {
+    encodeMessageId(bf, MessageId.Status)
+    encodeStatusValue(bf, value)
+}
+
+export function encodeStatusValue(bf: Bufferfish, value: Status): void {
+    bf.writeUint8(value)
+}
+
+export function encodeInput(bf: Bufferfish, value: Input): void {
+    encodeMessageId(bf, MessageId.Input)
+    encodeInputValue(bf, value)
+}
+
+export function encodeInputValue(bf: Bufferfish, value: Input): void {
+    switch (value.kind) {
+        case 'Key':
+            bf.writeUint8(0)
+            bf.writeUint8(value.value)
+            break
+        case 'Move':
+            bf.writeUint8(1)
+            bf.writeInt16(value.x)
+            bf.writeInt16(value.y)
+            break
+    }
+}
+
+export interface Replay {
+    inputs: Array<Input>
+}
+
+export function decodeReplay(bf: Bufferfish): Replay {
+    return {
+        inputs: bf.readArray(() => decodeInput(bf)) as Array<Input>,
+    }
+}
+
+export function encodeReplay(bf: Bufferfish, value: Replay): void {
+    bf.writeLength(value.inputs.length)
+    for (const item of value.inputs) {
+        encodeInputValue(bf, item)
+    }
+}"#;
+
+        let items = syn::parse_file(test_file)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let (structs, enums) = get_items_implementing_encode(items);
+        let message_id_enums = vec!["MessageId".to_string()];
+        let message_structs = get_message_structs(&structs, &enums, &message_id_enums);
+        assert_eq!(message_structs, vec!["Status", "Input"]);
+
+        assert_ts_output(test_file, expected_output);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let test_file = r#"
+#[derive(Encode)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Encode)]
+pub struct Player<T> {
+    pub position: Position,
+    pub history: Vec<Option<Position>>,
+    pub extra: T,
+    pub pair: (u8, [u8; LEN]),
+    #[bufferfish(with = "uuid_codec")]
+    pub id: uuid::Uuid,
+    pub team: Option<Team>,
+}
+
+#[derive(Encode)]
+#[repr(C)]
+pub enum Action {
+    Idle,
+    Move(Vec<Box<dyn Shape>>),
+}
+
+#[derive(Encode)]
+#[bufferfish(1_u16)]
+pub struct Join;
+
+#[derive(MessageSet)]
+pub enum ClientMessage {
+    Join(Join),
+    Move(Position),
+    Leave(Leave),
+}
+"#;
+
+        let items = syn::parse_file(test_file)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let diagnostics = get_diagnostics(vec![("src/player.rs".to_string(), items)]);
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 13,
+                    column: 20,
+                    item: "Player".to_string(),
+                    message: "unsupported array `[u8; LEN]`; the length must be an integer literal"
+                        .to_string(),
+                },
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 16,
+                    column: 22,
+                    item: "Player".to_string(),
+                    message: "unknown type `Team`; derive `Encode` for it, or use `#[bufferfish(with = \"..\")]`".to_string(),
+                },
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 21,
+                    column: 10,
+                    item: "Action".to_string(),
+                    message: "unsupported repr `C`, expected an integer type".to_string(),
+                },
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 23,
+                    column: 18,
+                    item: "Action".to_string(),
+                    message: "unsupported type `dyn Shape`".to_string(),
+                },
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 33,
+                    column: 10,
+                    item: "ClientMessage".to_string(),
+                    message: "`Position` has no message ID; add one with `#[bufferfish(..)]`"
+                        .to_string(),
+                },
+                Diagnostic {
+                    file: "src/player.rs".to_string(),
+                    line: 34,
+                    column: 11,
+                    item: "ClientMessage".to_string(),
+                    message: "unknown type `Leave`; derive `Encode` for it, or use `#[bufferfish(with = \"..\")]`".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "src/player.rs:13:20: in `Player`: unsupported array `[u8; LEN]`; the length must be an integer literal"
+        );
     }
 }
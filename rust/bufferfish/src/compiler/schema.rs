@@ -0,0 +1,772 @@
+//! An intermediate representation of a bufferfish protocol, built by
+//! `compiler::schema()` from Rust types annotated with `#[derive(Encode)]`.
+//!
+//! The schema describes what goes over the wire: each type's fields and their
+//! wire types, enum tags and variants, and message IDs. It can be serialized
+//! to JSON for tooling that should not have to parse Rust source.
+
+use serde::{Deserialize, Serialize};
+use syn::{Fields, GenericArgument, Item, ItemEnum, ItemStruct, PathArguments, Type, TypePath};
+
+use super::{
+    format_type, get_array_type, get_enum_discriminants, get_field_options, get_generic_args,
+    get_items_implementing_encode, get_map_types, get_message_id_expr, get_message_sets,
+    get_repr_type, get_set_type, get_transparent_type, get_type_params,
+};
+
+/// A protocol definition, made up of every encodable type found in a source
+/// tree.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub structs: Vec<StructDef>,
+    pub enums: Vec<EnumDef>,
+    pub message_sets: Vec<MessageSetDef>,
+}
+
+impl Schema {
+    /// Serializes the schema as pretty-printed JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Deserializes a schema previously written with `to_json`.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Returns the struct with the given name, if any.
+    pub fn get_struct(&self, name: &str) -> Option<&StructDef> {
+        self.structs.iter().find(|s| s.name == name)
+    }
+
+    /// Returns the enum with the given name, if any.
+    pub fn get_enum(&self, name: &str) -> Option<&EnumDef> {
+        self.enums.iter().find(|e| e.name == name)
+    }
+}
+
+/// The shape of a struct or enum variant's fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldsKind {
+    Named,
+    Tuple,
+    Unit,
+}
+
+/// A struct deriving `Encode`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructDef {
+    pub name: String,
+    /// The names of the struct's type parameters.
+    pub generics: Vec<String>,
+    /// The message ID written before the struct when it is encoded at the top
+    /// level.
+    pub message_id: Option<MessageIdDef>,
+    pub kind: FieldsKind,
+    /// The fields in wire order, excluding skipped fields.
+    pub fields: Vec<FieldDef>,
+}
+
+/// A message ID attribute, eg. `#[bufferfish(MessageId::Join)]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageIdDef {
+    /// The expression as written in the attribute.
+    pub expr: String,
+    /// The numeric ID, when the expression is an integer literal or a variant
+    /// of an enum in the schema.
+    pub value: Option<u16>,
+}
+
+/// A field of a struct or enum variant.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDef {
+    /// The field name, or `None` for tuple fields.
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub ty: WireType,
+    /// The module given by `#[bufferfish(with = "..")]`, if the field uses a
+    /// custom codec. The wire format of such fields is opaque to the schema.
+    pub codec: Option<String>,
+    /// Whether the field is written with its own message ID.
+    pub with_header: bool,
+    /// Whether the field is an integer written as a varint.
+    #[serde(default)]
+    pub varint: bool,
+    /// The length prefix given by `#[bufferfish(length_prefix = "..")]`, if
+    /// the field overrides the buffer's.
+    #[serde(default)]
+    pub length_prefix: Option<String>,
+}
+
+/// How a value is written to the wire.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WireType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+    Bool,
+    String,
+    /// A length followed by each item.
+    Array {
+        item: Box<WireType>,
+    },
+    /// Exactly `len` items, with no length, from a fixed-size array.
+    FixedArray {
+        item: Box<WireType>,
+        len: usize,
+    },
+    /// A length followed by each key and its value, from a `HashMap` or
+    /// `BTreeMap`.
+    Map {
+        key: Box<WireType>,
+        value: Box<WireType>,
+    },
+    /// A length followed by each item, from a `HashSet` or `BTreeSet`.
+    Set {
+        item: Box<WireType>,
+    },
+    /// Each item in order, from a tuple. `()` has no items and writes
+    /// nothing.
+    Tuple {
+        items: Vec<WireType>,
+    },
+    /// A u8 flag (0 or 1) followed by the value when present.
+    Option {
+        item: Box<WireType>,
+    },
+    /// A struct or enum, referenced by name, with any type arguments.
+    Named {
+        name: String,
+        args: Vec<WireType>,
+    },
+    /// A type parameter of the enclosing type.
+    Param {
+        name: String,
+    },
+    /// A type the compiler does not understand, as written in the source.
+    Unsupported {
+        rust: String,
+    },
+}
+
+impl std::fmt::Display for WireType {
+    /// Formats the wire type using Rust syntax, eg. `Vec<Option<u32>>`. Maps
+    /// and sets are formatted as `Map<K, V>` and `Set<T>`, as the hashed and
+    /// ordered collections are written the same way.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WireType::U8 => write!(f, "u8"),
+            WireType::U16 => write!(f, "u16"),
+            WireType::U32 => write!(f, "u32"),
+            WireType::U64 => write!(f, "u64"),
+            WireType::U128 => write!(f, "u128"),
+            WireType::I8 => write!(f, "i8"),
+            WireType::I16 => write!(f, "i16"),
+            WireType::I32 => write!(f, "i32"),
+            WireType::I64 => write!(f, "i64"),
+            WireType::I128 => write!(f, "i128"),
+            WireType::F32 => write!(f, "f32"),
+            WireType::F64 => write!(f, "f64"),
+            WireType::Bool => write!(f, "bool"),
+            WireType::String => write!(f, "String"),
+            WireType::Array { item } => write!(f, "Vec<{item}>"),
+            WireType::FixedArray { item, len } => write!(f, "[{item}; {len}]"),
+            WireType::Map { key, value } => write!(f, "Map<{key}, {value}>"),
+            WireType::Set { item } => write!(f, "Set<{item}>"),
+            WireType::Tuple { items } if items.len() == 1 => write!(f, "({},)", items[0]),
+            WireType::Tuple { items } => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "({})", items.join(", "))
+            }
+            WireType::Option { item } => write!(f, "Option<{item}>"),
+            WireType::Named { name, args } if args.is_empty() => write!(f, "{name}"),
+            WireType::Named { name, args } => {
+                let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+                write!(f, "{name}<{}>", args.join(", "))
+            }
+            WireType::Param { name } => write!(f, "{name}"),
+            WireType::Unsupported { rust } => write!(f, "{rust}"),
+        }
+    }
+}
+
+/// An enum deriving `Encode`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnumDef {
+    pub name: String,
+    pub generics: Vec<String>,
+    /// The integer type of the variant tag, from `#[repr(..)]` (default `u8`).
+    pub tag: WireType,
+    pub variants: Vec<VariantDef>,
+}
+
+/// A variant of an enum, and the tag written before its fields.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VariantDef {
+    pub name: String,
+    pub discriminant: i128,
+    pub kind: FieldsKind,
+    pub fields: Vec<FieldDef>,
+}
+
+/// An enum deriving `MessageSet`, dispatching on the message ID of each
+/// variant's message type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageSetDef {
+    pub name: String,
+    pub variants: Vec<MessageSetVariantDef>,
+}
+
+/// A variant of a message set, wrapping a single message type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageSetVariantDef {
+    pub name: String,
+    pub message: String,
+}
+
+pub(crate) fn build_schema(items: Vec<Item>) -> Schema {
+    let message_sets = get_message_sets(&items);
+    let (structs, enums) = get_items_implementing_encode(items);
+
+    let enums = enums.iter().map(get_enum_def).collect::<Vec<_>>();
+    let structs = structs
+        .iter()
+        .map(|item| get_struct_def(item, &enums))
+        .collect();
+    let message_sets = message_sets.iter().map(get_message_set_def).collect();
+
+    Schema {
+        structs,
+        enums,
+        message_sets,
+    }
+}
+
+fn get_struct_def(item: &ItemStruct, enums: &[EnumDef]) -> StructDef {
+    let generics = get_type_params(&item.generics);
+    let (kind, fields) = get_fields_def(&item.fields, &generics);
+
+    StructDef {
+        name: item.ident.to_string(),
+        message_id: get_message_id_expr(&item.attrs).map(|expr| MessageIdDef {
+            value: resolve_message_id(&expr, enums),
+            expr,
+        }),
+        generics,
+        kind,
+        fields,
+    }
+}
+
+/// Resolve a message ID expression to its numeric value, for integer literals
+/// and variants of enums in the schema.
+fn resolve_message_id(expr: &str, enums: &[EnumDef]) -> Option<u16> {
+    if let Ok(lit_int) = syn::parse_str::<syn::LitInt>(expr) {
+        return lit_int.base10_parse().ok();
+    }
+
+    let mut segments = expr.rsplit("::");
+    let variant_name = segments.next()?;
+    let enum_name = segments.next()?;
+
+    let variant = enums
+        .iter()
+        .find(|e| e.name == enum_name)?
+        .variants
+        .iter()
+        .find(|v| v.name == variant_name)?;
+
+    u16::try_from(variant.discriminant).ok()
+}
+
+fn get_enum_def(item: &ItemEnum) -> EnumDef {
+    let generics = get_type_params(&item.generics);
+    let repr_type = get_repr_type(&item.attrs).unwrap_or("u8".to_string());
+
+    let variants = item
+        .variants
+        .iter()
+        .zip(get_enum_discriminants(item))
+        .map(|(variant, discriminant)| {
+            let (kind, fields) = get_fields_def(&variant.fields, &generics);
+            VariantDef {
+                name: variant.ident.to_string(),
+                discriminant,
+                kind,
+                fields,
+            }
+        })
+        .collect();
+
+    EnumDef {
+        name: item.ident.to_string(),
+        tag: get_primitive_wire_type(&repr_type)
+            .unwrap_or(WireType::Unsupported { rust: repr_type }),
+        generics,
+        variants,
+    }
+}
+
+fn get_message_set_def(item: &ItemEnum) -> MessageSetDef {
+    let variants = item
+        .variants
+        .iter()
+        .filter_map(|variant| {
+            let Fields::Unnamed(fields) = &variant.fields else {
+                return None;
+            };
+            let Some(Type::Path(TypePath { path, .. })) = fields.unnamed.first().map(|f| &f.ty)
+            else {
+                return None;
+            };
+
+            Some(MessageSetVariantDef {
+                name: variant.ident.to_string(),
+                message: path.segments.last()?.ident.to_string(),
+            })
+        })
+        .collect();
+
+    MessageSetDef {
+        name: item.ident.to_string(),
+        variants,
+    }
+}
+
+fn get_fields_def(fields: &Fields, type_params: &[String]) -> (FieldsKind, Vec<FieldDef>) {
+    let kind = match fields {
+        Fields::Named(_) => FieldsKind::Named,
+        Fields::Unnamed(_) => FieldsKind::Tuple,
+        Fields::Unit => FieldsKind::Unit,
+    };
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let options = get_field_options(field);
+            FieldDef {
+                name: field.ident.as_ref().map(|ident| ident.to_string()),
+                ty: get_wire_type(&field.ty, type_params),
+                codec: options.with,
+                with_header: options.with_header,
+                varint: options.varint,
+                length_prefix: options.length_prefix,
+            }
+        })
+        .collect();
+
+    (kind, fields)
+}
+
+/// Returns the wire type of a Rust type, resolving names in `type_params` to
+/// the enclosing type's parameters.
+fn get_wire_type(ty: &Type, type_params: &[String]) -> WireType {
+    if let Some(ty) = get_transparent_type(ty) {
+        return get_wire_type(&ty, type_params);
+    }
+
+    match ty {
+        Type::Tuple(tuple) => WireType::Tuple {
+            items: tuple
+                .elems
+                .iter()
+                .map(|elem| get_wire_type(elem, type_params))
+                .collect(),
+        },
+        Type::Array(_) => match get_array_type(ty) {
+            Some((item, len)) => WireType::FixedArray {
+                item: Box::new(get_wire_type(&item, type_params)),
+                len,
+            },
+            None => WireType::Unsupported {
+                rust: format_type(ty),
+            },
+        },
+        Type::Path(TypePath { path, .. }) => {
+            if let Some((key, value)) = get_map_types(path) {
+                return WireType::Map {
+                    key: Box::new(get_wire_type(&key, type_params)),
+                    value: Box::new(get_wire_type(&value, type_params)),
+                };
+            }
+
+            if let Some(item) = get_set_type(path) {
+                return WireType::Set {
+                    item: Box::new(get_wire_type(&item, type_params)),
+                };
+            }
+
+            if path.segments.len() == 1
+                && let PathArguments::AngleBracketed(args) = &path.segments[0].arguments
+                && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+            {
+                let item = Box::new(get_wire_type(inner_ty, type_params));
+                match path.segments[0].ident.to_string().as_str() {
+                    "Vec" | "VecDeque" => return WireType::Array { item },
+                    "Option" => return WireType::Option { item },
+                    _ => {}
+                }
+            }
+
+            if let Some(ident) = path.get_ident() {
+                let name = ident.to_string();
+                if type_params.contains(&name) {
+                    return WireType::Param { name };
+                }
+
+                if let Some(wire_type) = get_primitive_wire_type(&name) {
+                    return wire_type;
+                }
+            }
+
+            match path.segments.last() {
+                Some(segment) => WireType::Named {
+                    name: segment.ident.to_string(),
+                    args: get_generic_args(path)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|arg| get_wire_type(arg, type_params))
+                        .collect(),
+                },
+                None => WireType::Unsupported {
+                    rust: format_type(ty),
+                },
+            }
+        }
+        _ => WireType::Unsupported {
+            rust: format_type(ty),
+        },
+    }
+}
+
+fn get_primitive_wire_type(name: &str) -> Option<WireType> {
+    Some(match name {
+        "u8" => WireType::U8,
+        "u16" => WireType::U16,
+        "u32" => WireType::U32,
+        "u64" => WireType::U64,
+        "u128" => WireType::U128,
+        "i8" => WireType::I8,
+        "i16" => WireType::I16,
+        "i32" => WireType::I32,
+        "i64" => WireType::I64,
+        "i128" => WireType::I128,
+        "f32" => WireType::F32,
+        "f64" => WireType::F64,
+        "bool" => WireType::Bool,
+        "String" | "str" => WireType::String,
+        // Written exactly like a `Vec<u8>`, just in a single copy.
+        "ByteBuf" | "Bytes" => WireType::Array {
+            item: Box::new(WireType::U8),
+        },
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_generation() {
+        let test_file = r#"
+#[derive(Encode)]
+#[repr(u16)]
+pub enum MessageId {
+    Join = 0,
+    Move = 5,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+pub struct JoinMessage {
+    pub name: String,
+    #[bufferfish(skip)]
+    pub session: u64,
+    pub friends: Vec<Option<u32>>,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Move)]
+pub struct MoveMessage(Envelope<i16>, #[bufferfish(with = "codecs::uuid")] uuid::Uuid);
+
+#[derive(Encode, Decode)]
+pub struct Envelope<T> {
+    pub body: T,
+}
+
+#[derive(Encode, Decode)]
+pub enum Shape {
+    Point,
+    Circle { radius: f32 } = 4,
+}
+
+#[derive(MessageSet)]
+pub enum ServerMessage {
+    Join(JoinMessage),
+    Move(MoveMessage),
+}
+    "#;
+
+        let items = syn::parse_file(test_file)
+            .unwrap_or_else(|e| panic!("Failed to parse: {e}"))
+            .items;
+        let schema = build_schema(items);
+
+        let join = schema.get_struct("JoinMessage").unwrap();
+        assert_eq!(
+            join.message_id,
+            Some(MessageIdDef {
+                expr: "MessageId::Join".to_string(),
+                value: Some(0),
+            })
+        );
+        assert_eq!(join.kind, FieldsKind::Named);
+        assert_eq!(
+            join.fields,
+            vec![
+                FieldDef {
+                    name: Some("name".to_string()),
+                    ty: WireType::String,
+                    codec: None,
+                    with_header: false,
+                    varint: false,
+                    length_prefix: None,
+                },
+                FieldDef {
+                    name: Some("friends".to_string()),
+                    ty: WireType::Array {
+                        item: Box::new(WireType::Option {
+                            item: Box::new(WireType::U32),
+                        }),
+                    },
+                    codec: None,
+                    with_header: false,
+                    varint: false,
+                    length_prefix: None,
+                },
+            ]
+        );
+
+        let move_message = schema.get_struct("MoveMessage").unwrap();
+        assert_eq!(move_message.message_id.as_ref().unwrap().value, Some(5));
+        assert_eq!(move_message.kind, FieldsKind::Tuple);
+        assert_eq!(
+            move_message.fields[0].ty,
+            WireType::Named {
+                name: "Envelope".to_string(),
+                args: vec![WireType::I16],
+            }
+        );
+        assert_eq!(
+            move_message.fields[1].codec.as_deref(),
+            Some("codecs::uuid")
+        );
+
+        let envelope = schema.get_struct("Envelope").unwrap();
+        assert_eq!(envelope.generics, vec!["T"]);
+        assert_eq!(envelope.message_id, None);
+        assert_eq!(
+            envelope.fields[0].ty,
+            WireType::Param {
+                name: "T".to_string()
+            }
+        );
+
+        let message_id = schema.get_enum("MessageId").unwrap();
+        assert_eq!(message_id.tag, WireType::U16);
+
+        let shape = schema.get_enum("Shape").unwrap();
+        assert_eq!(shape.tag, WireType::U8);
+        assert_eq!(shape.variants[0].kind, FieldsKind::Unit);
+        assert_eq!(shape.variants[1].discriminant, 4);
+        assert_eq!(shape.variants[1].fields[0].ty, WireType::F32);
+
+        assert_eq!(schema.message_sets.len(), 1);
+        assert_eq!(schema.message_sets[0].variants[1].message, "MoveMessage");
+
+        let json = schema.to_json().unwrap();
+        assert!(json.contains(r#""kind": "array""#));
+        assert_eq!(Schema::from_json(&json).unwrap(), schema);
+    }
+    #[test]
+    fn test_schema_maps_and_sets() {
+        let items = syn::parse_file(
+            r#"
+#[derive(Encode, Decode)]
+pub struct Inventory<T> {
+    pub items: HashMap<u32, T, FxBuildHasher>,
+    pub tags: BTreeSet<String>,
+    pub history: VecDeque<u8>,
+}
+    "#,
+        )
+        .unwrap()
+        .items;
+        let schema = build_schema(items);
+
+        let fields = &schema.get_struct("Inventory").unwrap().fields;
+        assert_eq!(
+            fields[0].ty,
+            WireType::Map {
+                key: Box::new(WireType::U32),
+                value: Box::new(WireType::Param {
+                    name: "T".to_string()
+                }),
+            }
+        );
+        assert_eq!(fields[0].ty.to_string(), "Map<u32, T>");
+        assert_eq!(
+            fields[1].ty,
+            WireType::Set {
+                item: Box::new(WireType::String),
+            }
+        );
+        assert_eq!(
+            fields[2].ty,
+            WireType::Array {
+                item: Box::new(WireType::U8),
+            }
+        );
+
+        let json = schema.to_json().unwrap();
+        assert!(json.contains(r#""kind": "map""#));
+        assert_eq!(Schema::from_json(&json).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_schema_fixed_arrays() {
+        let items = syn::parse_file(
+            r#"
+#[derive(Encode, Decode)]
+pub struct Block {
+    pub hash: [u8; 32],
+    pub grid: [[Option<u16>; 2]; 3],
+    pub data: [u8; LEN],
+}
+    "#,
+        )
+        .unwrap()
+        .items;
+        let schema = build_schema(items);
+
+        let fields = &schema.get_struct("Block").unwrap().fields;
+        assert_eq!(
+            fields[0].ty,
+            WireType::FixedArray {
+                item: Box::new(WireType::U8),
+                len: 32,
+            }
+        );
+        assert_eq!(fields[1].ty.to_string(), "[[Option<u16>; 2]; 3]");
+        assert_eq!(
+            fields[2].ty,
+            WireType::Unsupported {
+                rust: "[u8; LEN]".to_string()
+            }
+        );
+
+        let json = schema.to_json().unwrap();
+        assert!(json.contains(r#""kind": "fixed_array""#));
+        assert_eq!(Schema::from_json(&json).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_schema_tuples() {
+        let items = syn::parse_file(
+            r#"
+#[derive(Encode, Decode)]
+pub struct Score<T> {
+    pub entry: (u16, T),
+    pub unit: (),
+    pub single: (Vec<u8>,),
+}
+    "#,
+        )
+        .unwrap()
+        .items;
+        let schema = build_schema(items);
+
+        let fields = &schema.get_struct("Score").unwrap().fields;
+        assert_eq!(
+            fields[0].ty,
+            WireType::Tuple {
+                items: vec![
+                    WireType::U16,
+                    WireType::Param {
+                        name: "T".to_string()
+                    }
+                ],
+            }
+        );
+        assert_eq!(fields[0].ty.to_string(), "(u16, T)");
+        assert_eq!(fields[1].ty, WireType::Tuple { items: vec![] });
+        assert_eq!(fields[1].ty.to_string(), "()");
+        assert_eq!(fields[2].ty.to_string(), "(Vec<u8>,)");
+
+        let json = schema.to_json().unwrap();
+        assert!(json.contains(r#""kind": "tuple""#));
+        assert_eq!(Schema::from_json(&json).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_schema_pointers() {
+        let items = syn::parse_file(
+            r#"
+#[derive(Encode)]
+pub enum Expr<'a> {
+    Lit(Cow<'a, str>),
+    Add(Box<Expr<'a>>, Rc<Expr<'a>>),
+    List(&'a [Arc<u8>]),
+}
+    "#,
+        )
+        .unwrap()
+        .items;
+        let schema = build_schema(items);
+
+        let variants = &schema.get_enum("Expr").unwrap().variants;
+        let field_types = variants
+            .iter()
+            .flat_map(|variant| &variant.fields)
+            .map(|field| field.ty.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(field_types, vec!["String", "Expr", "Expr", "Vec<u8>"]);
+    }
+
+    #[test]
+    fn test_schema_byte_buf() {
+        let items = syn::parse_file(
+            r#"
+#[derive(Encode, Decode)]
+pub struct Upload {
+    pub data: ByteBuf,
+    pub raw: Vec<u8>,
+}
+    "#,
+        )
+        .unwrap()
+        .items;
+        let schema = build_schema(items);
+
+        // Both have the same bytes on the wire.
+        let fields = &schema.get_struct("Upload").unwrap().fields;
+        assert_eq!(fields[0].ty, fields[1].ty);
+        assert_eq!(fields[0].ty.to_string(), "Vec<u8>");
+    }
+}
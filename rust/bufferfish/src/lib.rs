@@ -1,11 +1,15 @@
+#[cfg(feature = "schema")]
+pub mod compat;
 #[cfg(feature = "derive")]
 pub mod compiler;
+pub mod router;
 
 pub use bufferfish_core::{decodable::Decodable, encodable::Encodable, *};
 #[cfg(feature = "derive")]
-pub use bufferfish_derive::{Decode, Encode};
+pub use bufferfish_derive::{Decode, DecodeRef, Encode, MessageSet};
 #[cfg(feature = "derive")]
 pub use compiler::generate;
+pub use router::Router;
 
 #[cfg(feature = "derive")]
 #[cfg(test)]
@@ -342,6 +346,135 @@ mod tests {
         assert_eq!(bf.read_i128().unwrap(), i128::MIN);
     }
 
+    #[test]
+    fn test_write_f32() {
+        let mut bf = Bufferfish::new();
+        bf.write_f32(0.0).unwrap();
+        bf.write_f32(1.5).unwrap();
+        bf.write_f32(-2.25).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 0, 0, 0, 63, 192, 0, 0, 192, 16, 0, 0]);
+    }
+
+    #[test]
+    fn test_write_f64() {
+        let mut bf = Bufferfish::new();
+        bf.write_f64(0.0).unwrap();
+        bf.write_f64(1.5).unwrap();
+        bf.write_f64(-2.25).unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[
+                0, 0, 0, 0, 0, 0, 0, 0, 63, 248, 0, 0, 0, 0, 0, 0, 192, 2, 0, 0, 0, 0, 0, 0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_f32() {
+        let mut bf = Bufferfish::new();
+        bf.write_f32(0.0).unwrap();
+        bf.write_f32(1.5).unwrap();
+        bf.write_f32(f32::MAX).unwrap();
+        bf.write_f32(f32::MIN).unwrap();
+
+        assert_eq!(bf.read_f32().unwrap(), 0.0);
+        assert_eq!(bf.read_f32().unwrap(), 1.5);
+        assert_eq!(bf.read_f32().unwrap(), f32::MAX);
+        assert_eq!(bf.read_f32().unwrap(), f32::MIN);
+    }
+
+    #[test]
+    fn test_read_f64() {
+        let mut bf = Bufferfish::new();
+        bf.write_f64(0.0).unwrap();
+        bf.write_f64(1.5).unwrap();
+        bf.write_f64(f64::MAX).unwrap();
+        bf.write_f64(f64::MIN).unwrap();
+
+        assert_eq!(bf.read_f64().unwrap(), 0.0);
+        assert_eq!(bf.read_f64().unwrap(), 1.5);
+        assert_eq!(bf.read_f64().unwrap(), f64::MAX);
+        assert_eq!(bf.read_f64().unwrap(), f64::MIN);
+    }
+
+    #[test]
+    fn test_write_varint() {
+        let mut bf = Bufferfish::new();
+        bf.write_varint_u64(0).unwrap();
+        bf.write_varint_u64(127).unwrap();
+        bf.write_varint_u64(300).unwrap();
+        bf.write_varint_i64(-1).unwrap();
+        bf.write_varint_i64(64).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0x00, 0x7F, 0xAC, 0x02, 0x01, 0x80, 0x01]);
+
+        let mut bf = Bufferfish::new();
+        bf.write_varint_u64(u64::MAX).unwrap();
+
+        assert_eq!(bf.len(), 10);
+    }
+
+    #[test]
+    fn test_read_varint() {
+        let mut bf = Bufferfish::new();
+        bf.write_varint_u64(u16::MAX as u64).unwrap();
+        bf.write_varint_u64(u32::MAX as u64).unwrap();
+        bf.write_varint_u64(u64::MAX).unwrap();
+        bf.write_varint_i64(i16::MIN as i64).unwrap();
+        bf.write_varint_i64(i32::MAX as i64).unwrap();
+        bf.write_varint_i64(i64::MIN).unwrap();
+
+        assert_eq!(bf.read_varint_u16().unwrap(), u16::MAX);
+        assert_eq!(bf.read_varint_u32().unwrap(), u32::MAX);
+        assert_eq!(bf.read_varint_u64().unwrap(), u64::MAX);
+        assert_eq!(bf.read_varint_i16().unwrap(), i16::MIN);
+        assert_eq!(bf.read_varint_i32().unwrap(), i32::MAX);
+        assert_eq!(bf.read_varint_i64().unwrap(), i64::MIN);
+        assert!(bf.read_varint_u64().is_err());
+    }
+
+    #[test]
+    fn test_read_varint_overflow() {
+        use bufferfish_core::BufferfishError;
+
+        // 2^16 does not fit in a u16.
+        let mut bf = Bufferfish::from(vec![0x80, 0x80, 0x04]);
+        assert!(matches!(
+            bf.read_varint_u16(),
+            Err(BufferfishError::InvalidVarint)
+        ));
+
+        // Continues past the 5 bytes a u32 may take.
+        let mut bf = Bufferfish::from(vec![0x80; 6]);
+        assert!(matches!(
+            bf.read_varint_u32(),
+            Err(BufferfishError::InvalidVarint)
+        ));
+
+        let mut bf = Bufferfish::from(vec![0x80, 0x80]);
+        assert!(matches!(
+            bf.read_varint_u32(),
+            Err(BufferfishError::InsufficientBytes {
+                available: 2,
+                required: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn test_read_varint_past_end() {
+        use std::io::{Seek, SeekFrom};
+
+        let mut bf = Bufferfish::from(vec![1, 2]);
+        bf.read_u8().unwrap();
+        bf.seek(SeekFrom::Start(10)).unwrap();
+
+        assert!(bf.read_varint_u32().is_err());
+        assert!(bf.read_u8().is_err());
+    }
+
     #[test]
     fn test_read_reset() {
         let mut bf = Bufferfish::new();
@@ -492,6 +625,13 @@ mod tests {
             bools,
             vec![true, false, true, false, true, false, true, false]
         );
+        assert!(matches!(
+            bf.read_packed_bools(9),
+            Err(bufferfish_core::BufferfishError::InsufficientBytes {
+                available: 1,
+                required: 2
+            })
+        ));
     }
 
     #[test]
@@ -551,6 +691,178 @@ mod tests {
         assert_eq!(result, vec![Object { a: 0 }, Object { a: 1 }]);
     }
 
+    #[test]
+    fn test_encode_decode_maps_and_sets() {
+        use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+        use bufferfish_core::{Decodable, Encodable};
+
+        let map = BTreeMap::from([(2u8, "b".to_string()), (1, "a".to_string())]);
+        let mut bf = Bufferfish::new();
+        map.encode(&mut bf).unwrap();
+
+        // Written in key order, each key followed by its value.
+        assert_eq!(bf.as_ref(), &[0, 2, 1, 0, 1, b'a', 2, 0, 1, b'b']);
+        assert_eq!(BTreeMap::<u8, String>::decode(&mut bf).unwrap(), map);
+
+        let set = BTreeSet::from([3u16, 1]);
+        let mut bf = Bufferfish::new();
+        set.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 2, 0, 1, 0, 3]);
+        assert_eq!(BTreeSet::<u16>::decode(&mut bf).unwrap(), set);
+
+        // Sets and deques are written the same way as a `Vec`.
+        let mut bf = Bufferfish::new();
+        vec![3u16, 1].encode(&mut bf).unwrap();
+
+        assert_eq!(
+            VecDeque::<u16>::decode(&mut bf).unwrap(),
+            VecDeque::from([3, 1])
+        );
+    }
+
+    #[test]
+    fn test_encode_decode_fixed_arrays() {
+        use bufferfish_core::{Decodable, Encodable};
+
+        let mut bf = Bufferfish::new();
+        [1u16, 2, 3].encode(&mut bf).unwrap();
+
+        // No length prefix, just the items.
+        assert_eq!(bf.as_ref(), &[0, 1, 0, 2, 0, 3]);
+        assert_eq!(<[u16; 3]>::decode(&mut bf).unwrap(), [1, 2, 3]);
+
+        assert_eq!(<[u16; 3]>::min_bytes_required(), Some(6));
+        assert_eq!(<[u16; 3]>::max_bytes_allowed(), Some(6));
+        assert_eq!(<[u8; 0]>::max_bytes_allowed(), Some(0));
+    }
+
+    #[test]
+    fn test_encode_decode_tuples() {
+        use bufferfish_core::{Decodable, Encodable};
+
+        let value = (1u8, "a".to_string(), (), (2u16, true));
+        let mut bf = Bufferfish::new();
+        value.encode(&mut bf).unwrap();
+
+        // Each element in order, with nothing written for `()`.
+        assert_eq!(bf.as_ref(), &[1, 0, 1, b'a', 0, 2, 1]);
+        assert_eq!(
+            <(u8, String, (), (u16, bool))>::decode(&mut bf).unwrap(),
+            value
+        );
+
+        assert_eq!(<(u8, (u16, bool))>::min_bytes_required(), Some(4));
+        assert_eq!(<(u8, (u16, bool))>::max_bytes_allowed(), Some(4));
+        assert_eq!(<(u8, Option<u8>)>::min_bytes_required(), Some(2));
+        assert_eq!(
+            <(u8, String)>::max_bytes_allowed(),
+            Some(3 + u16::MAX as usize)
+        );
+        assert_eq!(<()>::max_bytes_allowed(), Some(0));
+    }
+
+    #[test]
+    fn test_encode_decode_pointers() {
+        use std::{borrow::Cow, rc::Rc};
+
+        use bufferfish_core::{Decodable, Encodable};
+
+        let mut bf = Bufferfish::new();
+        "ab".encode(&mut bf).unwrap();
+        [1u8, 2].as_slice().encode(&mut bf).unwrap();
+        Rc::new(3u16).encode(&mut bf).unwrap();
+
+        // Written the same way as the owned types they point to.
+        assert_eq!(bf.as_ref(), &[0, 2, b'a', b'b', 0, 2, 1, 2, 0, 3]);
+        assert_eq!(
+            <Cow<str>>::decode(&mut bf).unwrap(),
+            Cow::<str>::Owned("ab".to_string())
+        );
+        assert_eq!(<Cow<[u8]>>::decode(&mut bf).unwrap(), vec![1, 2]);
+        assert_eq!(<Box<u16>>::decode(&mut bf).unwrap(), Box::new(3));
+    }
+
+    #[test]
+    fn test_write_read_bytes() {
+        let mut bf = Bufferfish::new();
+        bf.write_bytes(&[1, 2, 3]).unwrap();
+        bf.write_array(&[1u8, 2, 3]).unwrap();
+
+        // The same bytes as writing a `Vec<u8>`, with a single copy.
+        assert_eq!(bf.as_ref(), &[0, 3, 1, 2, 3, 0, 3, 1, 2, 3]);
+        assert_eq!(bf.read_bytes().unwrap(), vec![1, 2, 3]);
+        assert_eq!(bf.read_bytes().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_write_array_too_long() {
+        use bufferfish_core::BufferfishError;
+
+        let mut bf = Bufferfish::new();
+
+        assert!(matches!(
+            bf.write_array(&vec![0u8; 65536]),
+            Err(BufferfishError::ExcessiveBytes {
+                available: 65536,
+                max_allowed: 65535
+            })
+        ));
+        assert!(matches!(
+            bf.write_string(&"a".repeat(65536)),
+            Err(BufferfishError::ExcessiveBytes { .. })
+        ));
+        assert!(bf.is_empty());
+    }
+
+    #[test]
+    fn test_length_prefix() {
+        use bufferfish_core::LengthPrefix;
+
+        let mut bf = Bufferfish::new();
+        bf.set_length_prefix(LengthPrefix::U32);
+        bf.write_string("hi").unwrap();
+        bf.set_length_prefix(LengthPrefix::Varint);
+        bf.write_array(&[7u8]).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 0, 0, 2, b'h', b'i', 1, 7]);
+
+        bf.set_length_prefix(LengthPrefix::U32);
+        assert_eq!(bf.read_string().unwrap(), "hi");
+        bf.set_length_prefix(LengthPrefix::Varint);
+        assert_eq!(bf.read_array::<u8>().unwrap(), vec![7]);
+
+        let mut bf = Bufferfish::new();
+        bf.set_max_capacity(0);
+        bf.set_length_prefix(LengthPrefix::Varint);
+        bf.write_array(&vec![1u16; 70000]).unwrap();
+
+        assert_eq!(bf.len(), 3 + 140000);
+        assert_eq!(bf.read_array::<u16>().unwrap().len(), 70000);
+    }
+
+    #[test]
+    fn test_with_length_prefix() {
+        use bufferfish_core::LengthPrefix;
+
+        let mut bf = Bufferfish::new();
+        bf.with_length_prefix(LengthPrefix::U32, |bf| bf.write_string("a"))
+            .unwrap();
+        bf.write_string("b").unwrap();
+
+        assert_eq!(bf.length_prefix(), LengthPrefix::U16);
+        assert_eq!(bf.as_ref(), &[0, 0, 0, 1, b'a', 0, 1, b'b']);
+
+        // The previous prefix is restored on error too.
+        let mut bf = Bufferfish::new();
+        assert!(
+            bf.with_length_prefix(LengthPrefix::Varint, |bf| bf.read_string())
+                .is_err()
+        );
+        assert_eq!(bf.length_prefix(), LengthPrefix::U16);
+    }
+
     #[test]
     fn test_to_bufferfish_struct() {
         use bufferfish_core as bufferfish;
@@ -1012,6 +1324,42 @@ mod tests {
         assert!(result_should_ok.is_ok());
     }
 
+    #[test]
+    fn test_encode_decode_floats() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Debug, Encode, Decode, PartialEq)]
+        struct Position {
+            x: f32,
+            y: f32,
+            z: f64,
+        }
+
+        let mut bf = Position {
+            x: 1.5,
+            y: -3.0,
+            z: 100.125,
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(bf.len(), 16);
+        assert_eq!(Position::min_bytes_required(), Some(16));
+        assert_eq!(Position::max_bytes_allowed(), Some(16));
+
+        let result = Position::from_bufferfish(&mut bf).unwrap();
+
+        assert_eq!(
+            result,
+            Position {
+                x: 1.5,
+                y: -3.0,
+                z: 100.125,
+            }
+        );
+    }
+
     #[test]
     fn test_reset() {
         let mut bf = Bufferfish::new();
@@ -1021,7 +1369,7 @@ mod tests {
         bf.reset();
 
         assert_eq!(bf.len(), 0);
-        assert_eq!(bf.as_ref(), &[]);
+        assert_eq!(bf.as_ref(), &[0u8; 0]);
     }
 
     #[test]
@@ -1085,6 +1433,85 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_encode_decode_explicit_discriminants() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Debug, Encode, Decode, PartialEq)]
+        enum Kind {
+            A = 10,
+            B = 20,
+            C,
+        }
+
+        let mut bf = Bufferfish::new();
+        Kind::A.encode(&mut bf).unwrap();
+        Kind::B.encode(&mut bf).unwrap();
+        Kind::C.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[10, 20, 21]);
+
+        assert_eq!(Kind::decode(&mut bf).unwrap(), Kind::A);
+        assert_eq!(Kind::decode(&mut bf).unwrap(), Kind::B);
+        assert_eq!(Kind::decode(&mut bf).unwrap(), Kind::C);
+
+        let mut bf = Bufferfish::from(vec![0]);
+        assert!(Kind::decode(&mut bf).is_err());
+    }
+
+    #[test]
+    fn test_encode_decode_repr_tag_width() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Debug, Encode, Decode, PartialEq)]
+        #[repr(u16)]
+        enum Shape {
+            Point = 300,
+            Circle(u8),
+            Rect { w: u8, h: u8 },
+        }
+
+        let mut bf = Bufferfish::new();
+        Shape::Point.encode(&mut bf).unwrap();
+        Shape::Circle(5).encode(&mut bf).unwrap();
+        Shape::Rect { w: 1, h: 2 }.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[1, 44, 1, 45, 5, 1, 46, 1, 2]);
+        assert_eq!(Shape::min_bytes_required(), Some(2));
+        assert_eq!(Shape::max_bytes_allowed(), Some(4));
+
+        assert_eq!(Shape::decode(&mut bf).unwrap(), Shape::Point);
+        assert_eq!(Shape::decode(&mut bf).unwrap(), Shape::Circle(5));
+        assert_eq!(Shape::decode(&mut bf).unwrap(), Shape::Rect { w: 1, h: 2 });
+    }
+
+    #[test]
+    fn test_encode_decode_signed_repr() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Debug, Encode, Decode, PartialEq)]
+        #[repr(i8)]
+        enum Direction {
+            Left = -1,
+            None,
+            Right,
+        }
+
+        let mut bf = Bufferfish::new();
+        Direction::Left.encode(&mut bf).unwrap();
+        Direction::None.encode(&mut bf).unwrap();
+        Direction::Right.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[255, 0, 1]);
+
+        assert_eq!(Direction::decode(&mut bf).unwrap(), Direction::Left);
+        assert_eq!(Direction::decode(&mut bf).unwrap(), Direction::None);
+        assert_eq!(Direction::decode(&mut bf).unwrap(), Direction::Right);
+    }
+
     #[test]
     fn test_manual_encode_decode_impl() {
         use bufferfish_core as bufferfish;
@@ -1216,4 +1643,400 @@ mod tests {
         assert_eq!(decoded_user_without_age.name, "Bufferfish2");
         assert_eq!(decoded_user_without_age.age, None);
     }
+
+    #[test]
+    fn test_bufferfish_ref_read_primitives() {
+        use bufferfish_core::BufferfishRef;
+
+        let mut bf = Bufferfish::new();
+        bf.write_u8(255).unwrap();
+        bf.write_u16(12345).unwrap();
+        bf.write_i32(-2147483648).unwrap();
+        bf.write_f64(1.5).unwrap();
+        bf.write_bool(true).unwrap();
+
+        let mut reader = BufferfishRef::from(&bf);
+
+        assert_eq!(reader.read_u8().unwrap(), 255);
+        assert_eq!(reader.read_u16().unwrap(), 12345);
+        assert_eq!(reader.read_i32().unwrap(), -2147483648);
+        assert_eq!(reader.read_f64().unwrap(), 1.5);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.remaining(), 0);
+        assert!(reader.read_u8().is_err());
+    }
+
+    #[test]
+    fn test_bufferfish_ref_read_varint() {
+        use bufferfish_core::BufferfishRef;
+
+        let mut bf = Bufferfish::new();
+        bf.write_varint_u64(300).unwrap();
+        bf.write_varint_i64(-300).unwrap();
+        let bytes = bf.into_vec();
+
+        let mut reader = BufferfishRef::new(&bytes);
+
+        assert_eq!(reader.read_varint_u32().unwrap(), 300);
+        assert_eq!(reader.read_varint_i16().unwrap(), -300);
+        assert_eq!(reader.remaining(), 0);
+        assert!(reader.read_varint_u64().is_err());
+    }
+
+    #[test]
+    fn test_bufferfish_ref_read_str_is_borrowed() {
+        use bufferfish_core::BufferfishRef;
+
+        let mut bf = Bufferfish::new();
+        bf.write_string("Bufferfish").unwrap();
+        let bytes = bf.into_vec();
+
+        let mut reader = BufferfishRef::new(&bytes);
+        let s = reader.read_str().unwrap();
+
+        assert_eq!(s, "Bufferfish");
+        assert_eq!(s.as_ptr(), bytes[2..].as_ptr());
+    }
+
+    #[test]
+    fn test_bufferfish_ref_read_str_past_end() {
+        use bufferfish_core::BufferfishRef;
+
+        let bytes = [0, 10, 66, 117];
+        let mut reader = BufferfishRef::new(&bytes);
+
+        assert!(reader.read_str().is_err());
+    }
+
+    #[test]
+    fn test_decode_ref_borrowed_struct() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{DecodableRef, Encodable};
+        use bufferfish_derive::DecodeRef;
+
+        #[derive(Encode)]
+        #[bufferfish(0_u16)]
+        struct ChatMessage {
+            id: u32,
+            text: String,
+            payload: Vec<u8>,
+        }
+
+        #[derive(Debug, DecodeRef, PartialEq)]
+        #[bufferfish(0_u16)]
+        struct ChatMessageRef<'a> {
+            id: u32,
+            text: &'a str,
+            payload: &'a [u8],
+        }
+
+        let bytes = ChatMessage {
+            id: 7,
+            text: "Hello".to_string(),
+            payload: vec![1, 2, 3],
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+
+        let message = ChatMessageRef::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            message,
+            ChatMessageRef {
+                id: 7,
+                text: "Hello",
+                payload: &[1, 2, 3],
+            }
+        );
+        assert_eq!(message.text.as_ptr(), bytes[8..].as_ptr());
+        assert_eq!(message.payload.as_ptr(), bytes[15..].as_ptr());
+    }
+
+    #[test]
+    fn test_decode_ref_invalid_message_id() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{BufferfishError, DecodableRef};
+        use bufferfish_derive::DecodeRef;
+
+        #[derive(Debug, DecodeRef)]
+        #[bufferfish(1_u16)]
+        struct NameRef<'a> {
+            _name: &'a str,
+        }
+
+        let bytes = [0, 0, 0, 1, 66];
+        let result = NameRef::from_bytes(&bytes);
+
+        assert!(matches!(result, Err(BufferfishError::InvalidMessageId)));
+    }
+
+    #[test]
+    fn test_decode_ref_enum_and_nested() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{BufferfishRef, DecodableRef, Encodable};
+        use bufferfish_derive::DecodeRef;
+
+        #[derive(Encode)]
+        enum Event {
+            Chat { from: String, text: String },
+            Leave(u32),
+        }
+
+        #[derive(Debug, DecodeRef, PartialEq)]
+        enum EventRef<'a> {
+            Chat { from: &'a str, text: &'a str },
+            Leave(u32),
+        }
+
+        #[derive(Debug, DecodeRef, PartialEq)]
+        struct Batch<'a> {
+            events: Vec<EventRef<'a>>,
+            note: Option<&'a str>,
+        }
+
+        let mut bf = Bufferfish::new();
+        vec![
+            Event::Chat {
+                from: "Rob".to_string(),
+                text: "Hi".to_string(),
+            },
+            Event::Leave(3),
+        ]
+        .encode(&mut bf)
+        .unwrap();
+        bf.write_option(&Some("done".to_string())).unwrap();
+
+        let mut reader = BufferfishRef::from(&bf);
+        let batch = Batch::decode_ref(&mut reader).unwrap();
+
+        assert_eq!(
+            batch,
+            Batch {
+                events: vec![
+                    EventRef::Chat {
+                        from: "Rob",
+                        text: "Hi"
+                    },
+                    EventRef::Leave(3)
+                ],
+                note: Some("done"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_skip_fields_struct() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Encode, Decode, Debug)]
+        #[bufferfish(1_u16)]
+        struct Session {
+            id: u32,
+            #[bufferfish(skip)]
+            handle: Arc<Mutex<Vec<u8>>>,
+            #[bufferfish(skip, default = 3)]
+            retries: u8,
+            name: String,
+        }
+
+        let session = Session {
+            id: 7,
+            handle: Arc::new(Mutex::new(vec![1, 2, 3])),
+            retries: 0,
+            name: "Rob".to_string(),
+        };
+
+        let mut bf = session.to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[0, 1, 0, 0, 0, 7, 0, 3, 82, 111, 98]);
+
+        let decoded = Session::decode(&mut bf).unwrap();
+        assert_eq!(decoded.id, 7);
+        assert!(decoded.handle.lock().unwrap().is_empty());
+        assert_eq!(decoded.retries, 3);
+        assert_eq!(decoded.name, "Rob");
+
+        assert_eq!(Session::min_bytes_required(), Some(2 + 4 + 2));
+        assert_eq!(
+            Session::max_bytes_allowed(),
+            String::max_bytes_allowed().map(|max| 2 + 4 + max)
+        );
+    }
+
+    #[test]
+    fn test_skip_fields_enum_and_tuple() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        enum Event {
+            Move(u8, #[bufferfish(skip)] Vec<String>, u8),
+            Chat {
+                #[bufferfish(skip, default = u32::MAX)]
+                seen: u32,
+                text: String,
+            },
+        }
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        struct Position(u16, #[bufferfish(skip)] bool, u16);
+
+        let mut bf = Bufferfish::new();
+        Event::Move(1, vec!["ignored".to_string()], 2)
+            .encode(&mut bf)
+            .unwrap();
+        Event::Chat {
+            seen: 1,
+            text: "Hi".to_string(),
+        }
+        .encode(&mut bf)
+        .unwrap();
+        Position(4, true, 5).encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 1, 2, 1, 0, 2, 72, 105, 0, 4, 0, 5]);
+
+        assert_eq!(Event::decode(&mut bf).unwrap(), Event::Move(1, vec![], 2));
+        assert_eq!(
+            Event::decode(&mut bf).unwrap(),
+            Event::Chat {
+                seen: u32::MAX,
+                text: "Hi".to_string()
+            }
+        );
+        assert_eq!(Position::decode(&mut bf).unwrap(), Position(4, false, 5));
+        assert_eq!(Position::max_bytes_allowed(), Some(4));
+    }
+
+    #[test]
+    fn test_with_field_codec() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+        use std::net::Ipv4Addr;
+
+        mod ipv4 {
+            use bufferfish_core::{Bufferfish, BufferfishError};
+            use std::net::Ipv4Addr;
+
+            pub fn encode(addr: &Ipv4Addr, bf: &mut Bufferfish) -> Result<(), BufferfishError> {
+                bf.write_u32(u32::from(*addr))
+            }
+
+            pub fn decode(bf: &mut Bufferfish) -> Result<Ipv4Addr, BufferfishError> {
+                Ok(Ipv4Addr::from(bf.read_u32()?))
+            }
+        }
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        #[bufferfish(2_u16)]
+        struct Peer {
+            id: u8,
+            #[bufferfish(with = "ipv4", ts_type = "Ipv4")]
+            addr: Ipv4Addr,
+        }
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        enum Route {
+            Direct(#[bufferfish(with = "ipv4")] Ipv4Addr),
+        }
+
+        let peer = Peer {
+            id: 1,
+            addr: Ipv4Addr::new(127, 0, 0, 1),
+        };
+
+        let mut bf = peer.to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[0, 2, 1, 127, 0, 0, 1]);
+        assert_eq!(Peer::decode(&mut bf).unwrap(), peer);
+
+        let mut bf = Bufferfish::new();
+        Route::Direct(Ipv4Addr::new(10, 0, 0, 2))
+            .encode(&mut bf)
+            .unwrap();
+        assert_eq!(bf.as_ref(), &[0, 10, 0, 0, 2]);
+        assert_eq!(
+            Route::decode(&mut bf).unwrap(),
+            Route::Direct(Ipv4Addr::new(10, 0, 0, 2))
+        );
+
+        // The size of a field using a codec is unknown.
+        assert_eq!(Peer::min_bytes_required(), Some(3));
+        assert_eq!(Peer::max_bytes_allowed(), None);
+    }
+
+    #[test]
+    fn test_generic_struct_and_enum() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        #[bufferfish(3_u16)]
+        struct Envelope<T> {
+            seq: u32,
+            body: T,
+        }
+
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        enum Either<L, R>
+        where
+            L: Clone,
+        {
+            Left(L),
+            Right { value: Vec<R> },
+        }
+
+        let envelope = Envelope {
+            seq: 9,
+            body: "Hi".to_string(),
+        };
+        let mut bf = envelope.to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[0, 3, 0, 0, 0, 9, 0, 2, 72, 105]);
+        assert_eq!(Envelope::<String>::decode(&mut bf).unwrap(), envelope);
+        assert_eq!(Envelope::<u8>::min_bytes_required(), Some(7));
+        assert_eq!(Envelope::<u8>::max_bytes_allowed(), Some(7));
+
+        let mut bf = Bufferfish::new();
+        Either::<u8, u16>::Left(1).encode(&mut bf).unwrap();
+        Either::<u8, u16>::Right { value: vec![2] }
+            .encode(&mut bf)
+            .unwrap();
+        assert_eq!(bf.as_ref(), &[0, 1, 1, 0, 1, 0, 2]);
+        assert_eq!(Either::<u8, u16>::decode(&mut bf).unwrap(), Either::Left(1));
+        assert_eq!(
+            Either::<u8, u16>::decode(&mut bf).unwrap(),
+            Either::Right { value: vec![2] }
+        );
+    }
+
+    #[test]
+    fn test_generic_custom_bound() {
+        use bufferfish_core as bufferfish;
+        use bufferfish_core::{Decodable, Encodable};
+        use std::marker::PhantomData;
+
+        trait Kind {}
+
+        #[derive(Debug, PartialEq)]
+        struct Chat;
+        impl Kind for Chat {}
+
+        // `K` is only a marker, so requiring it to be encodable would be
+        // needlessly restrictive.
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        #[bufferfish(bound = "K: Kind")]
+        struct Tagged<K> {
+            id: u8,
+            #[bufferfish(skip)]
+            kind: PhantomData<K>,
+        }
+
+        let tagged = Tagged::<Chat> {
+            id: 4,
+            kind: PhantomData,
+        };
+        let mut bf = tagged.to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[4]);
+        assert_eq!(Tagged::<Chat>::decode(&mut bf).unwrap(), tagged);
+    }
 }
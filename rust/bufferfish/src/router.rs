@@ -0,0 +1,96 @@
+//! Dispatching of incoming messages to handlers based on their message ID.
+
+use std::collections::HashMap;
+
+use bufferfish_core::{Bufferfish, BufferfishError, Decodable, Message};
+
+type Handler<R> = Box<dyn FnMut(&mut Bufferfish) -> Result<R, BufferfishError> + Send>;
+
+/// Routes a `Bufferfish` to a handler registered for the message ID at the
+/// start of the buffer.
+///
+/// Handlers are registered per message type; the message ID is read from the
+/// type's `Message` impl, which `#[derive(Decode)]` provides for any type with
+/// a `#[bufferfish(..)]` attribute. Every handler returns the same type `R`,
+/// which is handed back from `dispatch`.
+///
+/// ```ignore
+/// let mut router = Router::new();
+/// router
+///     .register(|msg: JoinMessage| println!("{} joined", msg.username))
+///     .register(|_: LeaveMessage| println!("someone left"));
+///
+/// router.dispatch(&mut bf)?;
+/// ```
+pub struct Router<R = ()> {
+    handlers: HashMap<u16, Handler<R>>,
+}
+
+impl<R> Router<R> {
+    /// Creates a new `Router` with no handlers.
+    pub fn new() -> Self {
+        Self {
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Registers a handler for the message type `T`.
+    ///
+    /// # Panics
+    /// Panics if a handler is already registered for `T::MESSAGE_ID`.
+    pub fn register<T, F>(&mut self, mut handler: F) -> &mut Self
+    where
+        T: Decodable + Message,
+        F: FnMut(T) -> R + Send + 'static,
+    {
+        let previous = self.handlers.insert(
+            T::MESSAGE_ID,
+            Box::new(move |bf| Ok(handler(T::decode(bf)?))),
+        );
+
+        assert!(
+            previous.is_none(),
+            "a handler is already registered for message id {}",
+            T::MESSAGE_ID
+        );
+
+        self
+    }
+
+    /// Returns true if a handler is registered for the given message ID.
+    pub fn contains(&self, message_id: u16) -> bool {
+        self.handlers.contains_key(&message_id)
+    }
+
+    /// Peeks the message ID at the cursor, decodes the matching message type
+    /// and passes it to its handler, returning the handler's result.
+    ///
+    /// Returns `BufferfishError::InvalidMessageId` if no handler is registered
+    /// for the message ID.
+    pub fn dispatch(&mut self, bf: &mut Bufferfish) -> Result<R, BufferfishError> {
+        let message_id = bf.peek_u16()?;
+
+        let Some(handler) = self.handlers.get_mut(&message_id) else {
+            return Err(BufferfishError::InvalidMessageId);
+        };
+
+        handler(bf)
+    }
+}
+
+impl<R> Default for Router<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> std::fmt::Debug for Router<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut message_ids: Vec<_> = self.handlers.keys().collect();
+        message_ids.sort();
+
+        f.debug_struct("Router")
+            .field("message_ids", &message_ids)
+            .finish()
+    }
+}
@@ -0,0 +1,181 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{Bufferfish, Encodable, MessageSet};
+    use bufferfish_derive::{Decode, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[repr(u16)]
+    enum MessageId {
+        Join = 3,
+        Ping = 4,
+    }
+
+    impl From<MessageId> for u16 {
+        fn from(id: MessageId) -> u16 {
+            id as u16
+        }
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Join)]
+    struct Join {
+        id: u32,
+        name: String,
+        position: Position,
+        tags: Vec<u8>,
+        team: Option<Team>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Position(u8, u8);
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Team {
+        Red,
+        Blue { score: u16 },
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(7_u16)]
+    struct Empty;
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Ping)]
+    struct Ping {
+        seq: u8,
+    }
+
+    #[derive(MessageSet, Debug, PartialEq)]
+    enum Message {
+        Join(Join),
+        Ping(Ping),
+    }
+
+    fn join() -> Join {
+        Join {
+            id: 1,
+            name: "bob".to_string(),
+            position: Position(4, 5),
+            tags: vec![6, 7],
+            team: Some(Team::Blue { score: 8 }),
+        }
+    }
+
+    #[test]
+    fn annotates_each_field() {
+        let bf = join().to_bufferfish().unwrap();
+        let annotation = bf.annotate::<Join>();
+
+        assert!(annotation.error().is_none());
+        assert_eq!(
+            annotation.to_string(),
+            "\
+[0..2] message_id = 3 (MessageId::Join)
+[2..6] id: u32 = 1
+[6..11] name: String = \"bob\"
+[11..13] position: Position
+  [11..12] 0: u8 = 4
+  [12..13] 1: u8 = 5
+[13..17] tags: Vec<u8>
+  [13..15] len: u16 = 2
+  [15..16] [0]: u8 = 6
+  [16..17] [1]: u8 = 7
+[17..21] team: Option<Team>
+  [17..18] flag: u8 = 1
+  [18..21] value: Team
+    [18..19] variant: u8 = 1 (Blue)
+    [19..21] Blue.score: u16 = 8
+"
+        );
+    }
+
+    #[test]
+    fn annotates_literal_message_id() {
+        let bf = Empty.to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Empty>().to_string(),
+            "[0..2] message_id = 7\n"
+        );
+    }
+
+    #[test]
+    fn annotates_message_sets() {
+        let bf = Ping { seq: 9 }.to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Message>().to_string(),
+            "[0..2] message_id = 4 (MessageId::Ping)\n[2..3] seq: u8 = 9\n"
+        );
+    }
+
+    #[test]
+    fn reports_where_decoding_stopped() {
+        let mut bytes = join().to_bufferfish().unwrap().into_vec();
+        bytes.truncate(16);
+
+        let annotation = Bufferfish::from(bytes).annotate::<Join>();
+        let error = annotation.error().unwrap();
+
+        assert_eq!(error.offset, 16);
+        assert_eq!(error.path, "tags[1]");
+        assert!(
+            annotation
+                .to_string()
+                .ends_with("[16..] [1]: u8\ndecoding stopped at byte 16 in `tags[1]`: insufficient bytes in buffer: available 0, required 1\n"),
+            "{annotation}"
+        );
+    }
+
+    #[test]
+    fn reports_invalid_variants() {
+        let mut bytes = join().to_bufferfish().unwrap().into_vec();
+        bytes[18] = 9;
+
+        let annotation = Bufferfish::from(bytes).annotate::<Join>();
+        let error = annotation.error().unwrap();
+
+        assert_eq!(error.offset, 18);
+        assert_eq!(error.path, "team.value");
+        assert!(
+            annotation
+                .to_string()
+                .contains("[18..19] variant: u8 = 9 (unknown)\n")
+        );
+    }
+
+    #[test]
+    fn reports_wrong_message_id() {
+        let bf = Ping { seq: 9 }.to_bufferfish().unwrap();
+        let annotation = bf.annotate::<Join>();
+
+        assert_eq!(annotation.error().unwrap().path, "message_id");
+        assert!(
+            annotation
+                .to_string()
+                .starts_with("[0..] message_id = 4 (expected MessageId::Join)\n")
+        );
+    }
+
+    #[test]
+    fn reports_trailing_bytes() {
+        let mut bf = Ping { seq: 9 }.to_bufferfish().unwrap();
+        bf.write_u16(0).unwrap();
+        let annotation = bf.annotate::<Ping>();
+
+        assert_eq!(annotation.trailing_bytes(), 2);
+        assert!(
+            annotation
+                .to_string()
+                .ends_with("[3..5] 2 trailing byte(s) not read\n")
+        );
+    }
+
+    #[test]
+    fn does_not_move_cursor() {
+        let mut bf = join().to_bufferfish().unwrap();
+        bf.annotate::<Join>();
+
+        assert_eq!(bf.peek_u16().unwrap(), 3);
+    }
+}
@@ -0,0 +1,167 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Block {
+        hash: [u8; 4],
+        position: [i16; 3],
+        names: [String; 2],
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Shape {
+        Point([u8; 2]),
+        Line { points: [[u8; 2]; 2] },
+    }
+
+    fn block() -> Block {
+        Block {
+            hash: [0xDE, 0xAD, 0xBE, 0xEF],
+            position: [1, -1, 2],
+            names: ["a".to_string(), "bc".to_string()],
+        }
+    }
+
+    #[test]
+    fn writes_items_without_a_length() {
+        let mut bf = block().to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[
+                0, 1, 0xDE, 0xAD, 0xBE, 0xEF, 0, 1, 0xFF, 0xFF, 0, 2, 0, 1, b'a', 0, 2, b'b', b'c'
+            ]
+        );
+        assert_eq!(Block::decode(&mut bf).unwrap(), block());
+    }
+
+    #[test]
+    fn enum_fields() {
+        for shape in [
+            Shape::Point([1, 2]),
+            Shape::Line {
+                points: [[1, 2], [3, 4]],
+            },
+        ] {
+            let mut bf = shape.to_bufferfish().unwrap();
+            assert_eq!(Shape::decode(&mut bf).unwrap(), shape);
+        }
+
+        let bf = Shape::Line {
+            points: [[1, 2], [3, 4]],
+        }
+        .to_bufferfish()
+        .unwrap();
+        assert_eq!(bf.as_ref(), &[1, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(Shape::min_bytes_required(), Some(1 + 2));
+        assert_eq!(Shape::max_bytes_allowed(), Some(1 + 4));
+
+        assert_eq!(Block::min_bytes_required(), Some(2 + 4 + 6 + 2 * 2));
+        assert_eq!(
+            Block::max_bytes_allowed(),
+            Some(2 + 4 + 6 + 2 * (2 + u16::MAX as usize))
+        );
+
+        // Collections only require their length prefix.
+        assert_eq!(<[Vec<Vec<u8>>; 4]>::min_bytes_required(), Some(4 * 2));
+        assert_eq!(
+            <[[u128; 1 << 10]; 1 << 10]>::min_bytes_required(),
+            Some(16 << 20)
+        );
+
+        // A maximum too large for a `usize` gives no maximum.
+        assert_eq!(
+            <[BTreeSet<BTreeSet<BTreeSet<u64>>>; 1 << 16]>::max_bytes_allowed(),
+            None
+        );
+    }
+
+    #[test]
+    fn derived_size_bounds_overflow() {
+        type Sets = [BTreeSet<BTreeSet<BTreeSet<u64>>>; 1 << 12];
+        type Blocks = [[u128; 1 << 10]; 1 << 10];
+
+        #[derive(Decode)]
+        #[allow(dead_code)]
+        struct Wide {
+            a: Sets,
+            b: Sets,
+            c: Sets,
+            d: Blocks,
+        }
+
+        #[derive(Decode)]
+        #[allow(dead_code, clippy::large_enum_variant)]
+        enum Choice {
+            Deep { a: Sets, b: Sets, c: Sets },
+            Deeper(Blocks, Vec<Blocks>, u8),
+        }
+
+        assert_eq!(Wide::max_bytes_allowed(), None);
+        assert_eq!(Wide::min_bytes_required(), Some(3 * (2 << 12) + (16 << 20)));
+        assert_eq!(Choice::max_bytes_allowed(), None);
+        assert_eq!(Choice::min_bytes_required(), Some(1 + 3 * (2 << 12)));
+    }
+
+    #[test]
+    fn reports_item_index() {
+        let mut bytes = block().to_bufferfish().unwrap().into_vec();
+        // Cut the buffer inside the second name.
+        bytes.truncate(bytes.len() - 1);
+
+        let Err(BufferfishError::Decode(e)) = Block::decode(&mut Bufferfish::from(bytes)) else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(e.path(), "Block.names[1]");
+        assert_eq!(e.offset(), 15);
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct BlockRef<'a> {
+            hash: [u8; 4],
+            position: [i16; 3],
+            names: [&'a str; 2],
+        }
+
+        let bytes = block().to_bufferfish().unwrap().into_vec();
+
+        assert_eq!(
+            BlockRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            BlockRef {
+                hash: [0xDE, 0xAD, 0xBE, 0xEF],
+                position: [1, -1, 2],
+                names: ["a", "bc"],
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_items() {
+        let bf = Shape::Point([7, 8]).to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Shape>().to_string(),
+            "\
+[0..1] variant: u8 = 0 (Point)
+[1..3] Point.0: [u8; 2]
+  [1..2] [0]: u8 = 7
+  [2..3] [1]: u8 = 8
+"
+        );
+    }
+}
@@ -0,0 +1,119 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{
+        BufferfishError, BufferfishRef, ByteBuf, Decodable, DecodableRef, DecodeLimits, Encodable,
+        Limit,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Upload {
+        name: String,
+        data: ByteBuf,
+    }
+
+    fn upload() -> Upload {
+        Upload {
+            name: "a".to_string(),
+            data: ByteBuf::from(vec![1, 2, 3]),
+        }
+    }
+
+    #[test]
+    fn encodes_like_a_vec() {
+        #[derive(Encode)]
+        #[bufferfish(1_u16)]
+        struct UploadVec {
+            name: String,
+            data: Vec<u8>,
+        }
+
+        let mut bf = upload().to_bufferfish().unwrap();
+        let vec_bf = UploadVec {
+            name: "a".to_string(),
+            data: vec![1, 2, 3],
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(bf, vec_bf);
+        assert_eq!(bf.as_ref(), &[0, 1, 0, 1, b'a', 0, 3, 1, 2, 3]);
+        assert_eq!(Upload::decode(&mut bf).unwrap(), upload());
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(Upload::min_bytes_required(), Some(2 + 2 + 2));
+        assert_eq!(ByteBuf::max_bytes_allowed(), Some(2 + u16::MAX as usize));
+    }
+
+    #[test]
+    fn limits_apply() {
+        let mut bf = upload().to_bufferfish().unwrap();
+        bf.set_decode_limits(DecodeLimits {
+            max_collection_len: 2,
+            ..Default::default()
+        });
+
+        let Err(BufferfishError::Decode(e)) = Upload::decode(&mut bf) else {
+            panic!("expected a limit error");
+        };
+
+        assert_eq!(e.path(), "Upload.data");
+        assert!(matches!(
+            e.kind(),
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct UploadRef<'a> {
+            name: &'a str,
+            data: ByteBuf,
+        }
+
+        let bytes = upload().to_bufferfish().unwrap().into_vec();
+
+        assert_eq!(
+            UploadRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            UploadRef {
+                name: "a",
+                data: ByteBuf::from(vec![1, 2, 3]),
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_bytes() {
+        let bf = upload().to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Upload>().to_string(),
+            "\
+[0..2] message_id = 1
+[2..5] name: String = \"a\"
+[5..10] data: ByteBuf = [1, 2, 3]
+"
+        );
+    }
+
+    #[cfg(feature = "with-bytes")]
+    #[test]
+    fn bytes_type() {
+        let mut bf = bufferfish::Bufferfish::new();
+        bytes::Bytes::from_static(&[4, 5]).encode(&mut bf).unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 2, 4, 5]);
+        assert_eq!(
+            bytes::Bytes::decode(&mut bf).unwrap(),
+            bytes::Bytes::from_static(&[4, 5])
+        );
+    }
+}
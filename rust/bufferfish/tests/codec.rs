@@ -0,0 +1,112 @@
+#[cfg(all(feature = "derive", feature = "tokio-codec"))]
+mod tests {
+    use bufferfish::{BufferfishCodec, BufferfishError, LengthPrefix};
+    use bufferfish_derive::{Decode, Encode};
+    use bytes::BytesMut;
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::AsyncWriteExt;
+    use tokio_util::codec::{Decoder, Encoder, FramedRead, FramedWrite};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct ChatMessage {
+        id: u32,
+        text: String,
+    }
+
+    fn messages() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage {
+                id: 1,
+                text: "hello".to_string(),
+            },
+            ChatMessage {
+                id: 2,
+                text: String::new(),
+            },
+            ChatMessage {
+                id: 3,
+                text: "x".repeat(300),
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_codec_roundtrip_over_duplex() {
+        for prefix in [LengthPrefix::U16, LengthPrefix::U32, LengthPrefix::Varint] {
+            // A tiny duplex buffer forces frames to be split across reads.
+            let (client, server) = tokio::io::duplex(7);
+
+            let writer = tokio::spawn(async move {
+                let mut sink = FramedWrite::new(client, BufferfishCodec::new(prefix));
+                for message in messages() {
+                    sink.send(message).await.unwrap();
+                }
+            });
+
+            let stream = FramedRead::new(server, BufferfishCodec::<ChatMessage>::new(prefix));
+            let received = stream.map(Result::unwrap).collect::<Vec<_>>().await;
+
+            writer.await.unwrap();
+            assert_eq!(received, messages());
+        }
+    }
+
+    #[test]
+    fn test_codec_inner_length_prefix() {
+        let mut codec = BufferfishCodec::<ChatMessage>::new(LengthPrefix::U16);
+        codec.set_inner_length_prefix(LengthPrefix::Varint);
+
+        let mut buf = BytesMut::new();
+        codec.encode(messages().remove(0), &mut buf).unwrap();
+
+        // The frame length, message ID and id, then the text's varint length.
+        assert_eq!(buf.len(), 2 + 2 + 4 + 1 + 5);
+        assert_eq!(buf[8], 5);
+
+        assert_eq!(
+            codec.decode(&mut buf).unwrap(),
+            messages().into_iter().next()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_codec_rejects_oversized_frame() {
+        let (mut client, server) = tokio::io::duplex(64);
+        client.write_all(&[0x04, 0x01]).await.unwrap();
+
+        let mut stream = FramedRead::new(
+            server,
+            BufferfishCodec::<ChatMessage>::new(LengthPrefix::U16),
+        );
+
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(BufferfishError::ExcessiveBytes {
+                available: 1025,
+                max_allowed: 1024
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_codec_encode_oversized_message() {
+        let (client, _server) = tokio::io::duplex(64);
+
+        let mut codec = BufferfishCodec::new(LengthPrefix::U16);
+        codec.set_max_frame_size(8);
+        let mut sink = FramedWrite::new(client, codec);
+
+        let result = sink
+            .send(ChatMessage {
+                id: 1,
+                text: "too long".to_string(),
+            })
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(BufferfishError::ExcessiveBytes { max_allowed: 8, .. })
+        ));
+    }
+}
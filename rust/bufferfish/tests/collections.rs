@@ -0,0 +1,232 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, DecodeLimits,
+        Encodable, Limit,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Inventory {
+        items: HashMap<u32, Item>,
+        tags: HashSet<String>,
+        slots: BTreeMap<u8, Option<u32>>,
+        seen: BTreeSet<u16>,
+        history: VecDeque<u8>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq, Clone)]
+    struct Item {
+        name: String,
+        count: u16,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Event {
+        Scores(BTreeMap<String, u32>),
+    }
+
+    fn inventory() -> Inventory {
+        Inventory {
+            items: HashMap::from([
+                (
+                    1,
+                    Item {
+                        name: "sword".to_string(),
+                        count: 1,
+                    },
+                ),
+                (
+                    7,
+                    Item {
+                        name: "arrow".to_string(),
+                        count: 20,
+                    },
+                ),
+            ]),
+            tags: HashSet::from(["rare".to_string()]),
+            slots: BTreeMap::from([(0, Some(1)), (1, None)]),
+            seen: BTreeSet::from([3, 9]),
+            history: VecDeque::from([4, 2]),
+        }
+    }
+
+    #[test]
+    fn roundtrips() {
+        let mut bf = inventory().to_bufferfish().unwrap();
+
+        assert_eq!(Inventory::decode(&mut bf).unwrap(), inventory());
+    }
+
+    #[test]
+    fn enum_fields() {
+        let event = Event::Scores(BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)]));
+        let mut bf = event.to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[0, 0, 2, 0, 1, b'a', 0, 0, 0, 1, 0, 1, b'b', 0, 0, 0, 2]
+        );
+        assert_eq!(Event::decode(&mut bf).unwrap(), event);
+    }
+
+    #[test]
+    fn duplicate_keys_keep_the_last_value() {
+        // Two entries for key 1.
+        let mut bf = Bufferfish::from(vec![0, 2, 1, 10, 1, 20]);
+
+        assert_eq!(
+            HashMap::<u8, u8>::decode(&mut bf).unwrap(),
+            HashMap::from([(1, 20)])
+        );
+    }
+
+    #[test]
+    fn reports_entry_index() {
+        let mut bytes = inventory().to_bufferfish().unwrap().into_vec();
+        // Cut the buffer inside the second item.
+        bytes.truncate(2 + 2 + 4 + 2 + 5 + 2 + 4 + 1);
+
+        let Err(BufferfishError::Decode(e)) = Inventory::decode(&mut Bufferfish::from(bytes))
+        else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(e.path(), "Inventory.items[1].name");
+    }
+
+    #[test]
+    fn limits_apply_to_maps() {
+        let mut bf = inventory().to_bufferfish().unwrap();
+        bf.set_decode_limits(DecodeLimits {
+            max_collection_len: 1,
+            ..Default::default()
+        });
+
+        let Err(BufferfishError::Decode(e)) = Inventory::decode(&mut bf) else {
+            panic!("expected a limit error");
+        };
+
+        assert_eq!(e.path(), "Inventory.items");
+        assert!(matches!(
+            e.kind(),
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn limits_apply_to_annotated_collections() {
+        let limits = DecodeLimits {
+            max_collection_len: 1,
+            ..Default::default()
+        };
+        let exceeded = |requested| {
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                requested,
+                max: 1,
+            }
+            .to_string()
+        };
+
+        let mut bf = inventory().to_bufferfish().unwrap();
+        bf.set_decode_limits(limits);
+
+        let annotation = bf.annotate::<Inventory>();
+        let error = annotation.error().unwrap();
+
+        assert_eq!(error.path, "items");
+        assert_eq!(error.message, exceeded(2));
+
+        // Claims u16::MAX items in 2 bytes.
+        let mut bf = Bufferfish::from(vec![0xFF, 0xFF]);
+        bf.set_decode_limits(limits);
+
+        let max = u16::MAX as usize;
+        assert_eq!(
+            bf.annotate::<HashSet<u64>>().error().unwrap().message,
+            exceeded(max)
+        );
+        assert_eq!(
+            bf.annotate::<BTreeMap<u8, u64>>().error().unwrap().message,
+            exceeded(max)
+        );
+        assert_eq!(
+            bf.annotate::<VecDeque<u64>>().error().unwrap().message,
+            exceeded(max)
+        );
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(
+            <BTreeMap<u8, u32>>::max_bytes_allowed(),
+            Some(2 + u16::MAX as usize * 5)
+        );
+        assert_eq!(<HashSet<String>>::min_bytes_required(), Some(2));
+        assert_eq!(
+            <BTreeMap<u8, String>>::max_bytes_allowed(),
+            Some(2 + u16::MAX as usize * (1 + 2 + u16::MAX as usize))
+        );
+        assert_eq!(
+            <BTreeSet<BTreeSet<BTreeSet<BTreeSet<u64>>>>>::max_bytes_allowed(),
+            None
+        );
+
+        assert_eq!(<Vec<u64>>::min_bytes_required(), Some(2));
+        assert_eq!(
+            <Vec<u64>>::max_bytes_allowed(),
+            <VecDeque<u64>>::max_bytes_allowed()
+        );
+        assert_eq!(<Vec<Vec<Vec<Vec<u64>>>>>::max_bytes_allowed(), None);
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        struct ScoresRef<'a> {
+            scores: BTreeMap<&'a str, u32>,
+            seen: HashSet<u16>,
+        }
+
+        let mut bf = Bufferfish::new();
+        BTreeMap::from([("a".to_string(), 1u32)])
+            .encode(&mut bf)
+            .unwrap();
+        HashSet::from([5u16]).encode(&mut bf).unwrap();
+        let bytes = bf.into_vec();
+
+        assert_eq!(
+            ScoresRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            ScoresRef {
+                scores: BTreeMap::from([("a", 1)]),
+                seen: HashSet::from([5]),
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_entries() {
+        let bf = Event::Scores(BTreeMap::from([("a".to_string(), 1)]))
+            .to_bufferfish()
+            .unwrap();
+
+        assert_eq!(
+            bf.annotate::<Event>().to_string(),
+            "\
+[0..1] variant: u8 = 0 (Scores)
+[1..10] Scores.0: BTreeMap<String, u32>
+  [1..3] len: u16 = 1
+  [3..10] [0]: (String, u32)
+    [3..6] key: String = \"a\"
+    [6..10] value: u32 = 1
+"
+        );
+    }
+}
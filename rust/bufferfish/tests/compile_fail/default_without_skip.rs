@@ -0,0 +1,9 @@
+use bufferfish::Encode;
+
+#[derive(Encode)]
+struct Foo {
+    #[bufferfish(default = 5)]
+    bar: u8,
+}
+
+fn main() {}
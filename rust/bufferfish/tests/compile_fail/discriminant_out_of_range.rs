@@ -0,0 +1,9 @@
+use bufferfish::Encode;
+
+#[derive(Encode)]
+enum Kind {
+    A = 10,
+    B = 300,
+}
+
+fn main() {}
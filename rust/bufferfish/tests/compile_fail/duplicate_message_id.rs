@@ -0,0 +1,21 @@
+use bufferfish::{Decode, Encode, MessageSet};
+
+#[derive(Encode, Decode)]
+#[bufferfish(1_u16)]
+struct Join {
+    id: u8,
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(1_u16)]
+struct Leave {
+    id: u8,
+}
+
+#[derive(MessageSet)]
+enum ClientMessage {
+    Join(Join),
+    Leave(Leave),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bufferfish::Encode;
+
+#[derive(Encode)]
+struct Snapshot {
+    #[bufferfish(length_prefix = "u64")]
+    tiles: Vec<u8>,
+}
+
+fn main() {}
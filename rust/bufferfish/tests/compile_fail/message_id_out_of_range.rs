@@ -0,0 +1,20 @@
+use bufferfish::{Decode, Encode};
+
+#[repr(u32)]
+enum MessageId {
+    Join = 70_000,
+}
+
+impl From<MessageId> for u16 {
+    fn from(id: MessageId) -> u16 {
+        id as u16
+    }
+}
+
+#[derive(Encode, Decode)]
+#[bufferfish(MessageId::Join)]
+struct Join {
+    id: u8,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use bufferfish::Encode;
+
+#[derive(Encode)]
+struct Move {
+    #[bufferfish(varint)]
+    speed: f32,
+}
+
+fn main() {}
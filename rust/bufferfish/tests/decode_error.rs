@@ -0,0 +1,163 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{Bufferfish, BufferfishError, Decodable, DecodableRef, Encodable};
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct JoinMessage {
+        id: u8,
+        user: User,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct User {
+        level: u16,
+        name: String,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Lobby {
+        players: Vec<User>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Event {
+        Quit,
+        Renamed { name: String },
+    }
+
+    fn decode_error<T: Decodable>(bytes: Vec<u8>) -> bufferfish::DecodeError {
+        match T::decode(&mut Bufferfish::from(bytes)) {
+            Err(BufferfishError::Decode(e)) => e,
+            Err(e) => panic!("expected a decode error, got {e:?}"),
+            Ok(_) => panic!("expected a decode error"),
+        }
+    }
+
+    #[test]
+    fn reports_nested_field_path() {
+        // message ID, id, level, then a name of length 5 with only 2 bytes.
+        let e = decode_error::<JoinMessage>(vec![0, 1, 7, 0, 3, 0, 5, b'h', b'i']);
+
+        assert_eq!(e.path(), "JoinMessage.user.name");
+        assert_eq!(e.type_name(), "JoinMessage");
+        assert_eq!(e.fields(), ["user", "name"]);
+        assert_eq!(e.offset(), 5);
+        assert!(matches!(
+            e.kind(),
+            BufferfishError::InsufficientBytes {
+                available: 2,
+                required: 5
+            }
+        ));
+        assert_eq!(
+            e.to_string(),
+            "failed to decode `JoinMessage.user.name` at byte 5: insufficient bytes in buffer: available 2, required 5"
+        );
+    }
+
+    #[test]
+    fn reports_array_index() {
+        let mut bytes = Lobby {
+            players: vec![
+                User {
+                    level: 1,
+                    name: "a".to_string(),
+                },
+                User {
+                    level: 2,
+                    name: "b".to_string(),
+                },
+            ],
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+        bytes.truncate(bytes.len() - 2);
+
+        let e = decode_error::<Lobby>(bytes);
+
+        assert_eq!(e.path(), "Lobby.players[1].name");
+        assert_eq!(e.offset(), 9);
+    }
+
+    #[test]
+    fn reports_enum_variant_fields() {
+        let e = decode_error::<Event>(vec![1, 0, 2, 0xC3, 0x28]);
+
+        assert_eq!(e.path(), "Event.Renamed.name");
+        assert_eq!(e.offset(), 1);
+        assert!(matches!(e.kind(), BufferfishError::InvalidUtf8(_)));
+    }
+
+    #[test]
+    fn reports_invalid_variant_in_field() {
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        struct Log {
+            event: Event,
+        }
+
+        let e = decode_error::<Log>(vec![9]);
+
+        assert_eq!(e.path(), "Log.event");
+        assert!(matches!(e.kind(), BufferfishError::InvalidEnumVariant));
+    }
+
+    #[test]
+    fn header_errors_are_not_wrapped() {
+        let result = JoinMessage::decode(&mut Bufferfish::from(vec![0, 2, 7]));
+
+        assert!(matches!(result, Err(BufferfishError::InvalidMessageId)));
+    }
+
+    #[test]
+    fn primitive_reads_are_not_write_errors() {
+        let mut bf = Bufferfish::from(vec![0, 1]);
+
+        assert!(matches!(
+            bf.read_u32(),
+            Err(BufferfishError::InsufficientBytes {
+                available: 2,
+                required: 4
+            })
+        ));
+        assert!(matches!(
+            Bufferfish::from(vec![0, 1, 0xFF]).read_string(),
+            Err(BufferfishError::InvalidUtf8(_))
+        ));
+    }
+
+    #[test]
+    fn source_is_the_underlying_error() {
+        let e = JoinMessage::decode(&mut Bufferfish::from(vec![0, 1, 7])).unwrap_err();
+
+        let source = std::error::Error::source(&e).unwrap();
+        assert_eq!(
+            source.to_string(),
+            "insufficient bytes in buffer: available 0, required 2"
+        );
+    }
+
+    #[test]
+    fn reports_borrowed_field_path() {
+        #[derive(DecodeRef, Debug)]
+        #[bufferfish(1_u16)]
+        struct JoinRef<'a> {
+            #[allow(dead_code)]
+            id: u8,
+            #[allow(dead_code)]
+            name: &'a str,
+        }
+
+        let bytes = [0, 1, 7, 0, 4, b'b', b'o'];
+        let mut bf = bufferfish::BufferfishRef::new(&bytes);
+
+        let Err(BufferfishError::Decode(e)) = JoinRef::decode_ref(&mut bf) else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(e.path(), "JoinRef.name");
+        assert_eq!(e.offset(), 3);
+    }
+}
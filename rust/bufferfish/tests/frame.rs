@@ -0,0 +1,156 @@
+mod tests {
+    use bufferfish::{Bufferfish, BufferfishError, FrameDecoder, FrameEncoder, LengthPrefix};
+
+    const PREFIXES: [LengthPrefix; 3] =
+        [LengthPrefix::U16, LengthPrefix::U32, LengthPrefix::Varint];
+
+    fn frames() -> Vec<Vec<u8>> {
+        vec![vec![0, 1, 2, 3], vec![], vec![42; 200], vec![7]]
+    }
+
+    fn encode_all(prefix: LengthPrefix) -> Vec<u8> {
+        let encoder = FrameEncoder::new(prefix);
+        let mut stream = Vec::new();
+
+        for frame in frames() {
+            encoder.encode(&frame, &mut stream).unwrap();
+        }
+
+        stream
+    }
+
+    #[test]
+    fn test_frame_roundtrip_single_chunk() {
+        for prefix in PREFIXES {
+            let mut decoder = FrameDecoder::new(prefix);
+            decoder.extend(&encode_all(prefix));
+
+            let decoded = decoder.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+            let expected = frames()
+                .into_iter()
+                .map(Bufferfish::from)
+                .collect::<Vec<_>>();
+
+            assert_eq!(decoded, expected);
+            assert_eq!(decoder.buffered(), 0);
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip_split_at_every_point() {
+        for prefix in PREFIXES {
+            let stream = encode_all(prefix);
+
+            for split in 0..=stream.len() {
+                let mut decoder = FrameDecoder::new(prefix);
+                let mut decoded = Vec::new();
+
+                for chunk in [&stream[..split], &stream[split..]] {
+                    decoder.extend(chunk);
+                    while let Some(frame) = decoder.decode().unwrap() {
+                        decoded.push(frame.into_vec());
+                    }
+                }
+
+                assert_eq!(decoded, frames(), "{prefix:?} split at {split}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip_byte_at_a_time() {
+        for prefix in PREFIXES {
+            let mut decoder = FrameDecoder::new(prefix);
+            let mut decoded = Vec::new();
+
+            for byte in encode_all(prefix) {
+                decoder.extend(&[byte]);
+                decoded.extend(decoder.by_ref().map(|frame| frame.unwrap().into_vec()));
+            }
+
+            assert_eq!(decoded, frames());
+        }
+    }
+
+    #[test]
+    fn test_frame_varint_prefix() {
+        let encoder = FrameEncoder::new(LengthPrefix::Varint);
+        let mut stream = Vec::new();
+        encoder.encode([1; 300], &mut stream).unwrap();
+
+        assert_eq!(&stream[..2], &[0xAC, 0x02]);
+        assert_eq!(stream.len(), 302);
+    }
+
+    #[test]
+    fn test_frame_decoder_max_frame_size() {
+        let mut decoder = FrameDecoder::new(LengthPrefix::U32);
+        decoder.set_max_frame_size(16);
+        decoder.extend(&[0, 0, 0, 17]);
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(BufferfishError::ExcessiveBytes {
+                available: 17,
+                max_allowed: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn test_frame_encoder_max_frame_size() {
+        let encoder = FrameEncoder::new(LengthPrefix::U16);
+        let mut stream = Vec::new();
+
+        assert!(matches!(
+            encoder.encode([0; 1025], &mut stream),
+            Err(BufferfishError::ExcessiveBytes {
+                available: 1025,
+                max_allowed: 1024
+            })
+        ));
+        assert!(stream.is_empty());
+
+        let mut encoder = FrameEncoder::new(LengthPrefix::U16);
+        encoder.set_max_frame_size(0);
+
+        assert!(matches!(
+            encoder.encode(vec![0; u16::MAX as usize + 1], &mut stream),
+            Err(BufferfishError::ExcessiveBytes {
+                max_allowed: 65535,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_frame_inner_length_prefix() {
+        let mut bf = Bufferfish::new();
+        bf.set_length_prefix(LengthPrefix::U32);
+        bf.write_string("hello").unwrap();
+
+        let mut stream = Vec::new();
+        FrameEncoder::default().encode(&bf, &mut stream).unwrap();
+
+        let mut decoder = FrameDecoder::default();
+        decoder.set_inner_length_prefix(LengthPrefix::U32);
+        decoder.extend(&stream);
+
+        let mut frame = decoder.decode().unwrap().unwrap();
+
+        assert_eq!(frame.length_prefix(), LengthPrefix::U32);
+        assert_eq!(frame.read_string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_frame_varint_overflow() {
+        let mut decoder = FrameDecoder::new(LengthPrefix::Varint);
+        decoder.set_max_frame_size(0);
+        decoder.extend(&[0xFF, 0xFF, 0xFF, 0xFF, 0x1F]);
+
+        assert!(matches!(
+            decoder.decode(),
+            Err(BufferfishError::InvalidVarint)
+        ));
+    }
+}
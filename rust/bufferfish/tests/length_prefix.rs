@@ -0,0 +1,132 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+        LengthPrefix,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Snapshot {
+        name: String,
+        #[bufferfish(length_prefix = "u32")]
+        tiles: Vec<u8>,
+        #[bufferfish(length_prefix = "varint")]
+        log: String,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Chunk {
+        Empty,
+        Data(#[bufferfish(length_prefix = "varint")] Vec<u16>),
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Lobby {
+        players: Vec<String>,
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            name: "a".to_string(),
+            tiles: vec![1, 2],
+            log: "ok".to_string(),
+        }
+    }
+
+    #[test]
+    fn field_prefixes() {
+        let mut bf = snapshot().to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[0, 1, 0, 1, b'a', 0, 0, 0, 2, 1, 2, 2, b'o', b'k']
+        );
+        assert_eq!(Snapshot::decode(&mut bf).unwrap(), snapshot());
+        assert_eq!(bf.length_prefix(), LengthPrefix::U16);
+    }
+
+    #[test]
+    fn field_prefix_applies_to_nested_values() {
+        let chunk = Chunk::Data(vec![7; 70000]);
+
+        let mut bf = Bufferfish::new();
+        bf.set_max_capacity(0);
+        chunk.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.len(), 1 + 3 + 140000);
+        assert_eq!(Chunk::decode(&mut bf).unwrap(), chunk);
+    }
+
+    #[test]
+    fn buffer_prefix() {
+        let lobby = Lobby {
+            players: vec!["x".repeat(70000)],
+        };
+
+        let mut bf = Bufferfish::new();
+        bf.set_max_capacity(0);
+        assert!(matches!(
+            lobby.encode(&mut bf),
+            Err(BufferfishError::ExcessiveBytes {
+                available: 70000,
+                max_allowed: 65535
+            })
+        ));
+
+        let mut bf = Bufferfish::new();
+        bf.set_max_capacity(0);
+        bf.set_length_prefix(LengthPrefix::U32);
+        lobby.encode(&mut bf).unwrap();
+
+        assert_eq!(bf.len(), 4 + 4 + 70000);
+        assert_eq!(Lobby::from_bufferfish(&mut bf).unwrap(), lobby);
+    }
+
+    #[test]
+    fn size_bounds() {
+        // `name` is the only field with a known size.
+        assert_eq!(Snapshot::min_bytes_required(), Some(2 + 2));
+        assert_eq!(Snapshot::max_bytes_allowed(), None);
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct SnapshotRef<'a> {
+            name: &'a str,
+            #[bufferfish(length_prefix = "u32")]
+            tiles: &'a [u8],
+            #[bufferfish(length_prefix = "varint")]
+            log: &'a str,
+        }
+
+        let bytes = snapshot().to_bufferfish().unwrap().into_vec();
+
+        assert_eq!(
+            SnapshotRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            SnapshotRef {
+                name: "a",
+                tiles: &[1, 2],
+                log: "ok",
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_prefix_type() {
+        let bf = Chunk::Data(vec![3]).to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Chunk>().to_string(),
+            "\
+[0..1] variant: u8 = 1 (Data)
+[1..4] Data.0: Vec<u16>
+  [1..2] len: varint = 1
+  [2..4] [0]: u16 = 3
+"
+        );
+    }
+}
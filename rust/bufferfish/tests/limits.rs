@@ -0,0 +1,318 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, DecodeLimits,
+        Encodable, FrameDecoder, LengthPrefix, Limit,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Chat {
+        text: String,
+        tags: Vec<u8>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Grid {
+        rows: Vec<Vec<u8>>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Outer {
+        middle: Middle,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Middle {
+        inner: Inner,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Inner {
+        value: u8,
+    }
+
+    fn limited(bytes: Vec<u8>, limits: DecodeLimits) -> Bufferfish {
+        let mut bf = Bufferfish::from(bytes);
+        bf.set_decode_limits(limits);
+
+        bf
+    }
+
+    fn exceeded<T: Decodable + std::fmt::Debug>(
+        bf: &mut Bufferfish,
+    ) -> (String, Limit, usize, usize) {
+        match T::decode(bf) {
+            Err(BufferfishError::Decode(e)) => match e.kind() {
+                BufferfishError::LimitExceeded {
+                    limit,
+                    requested,
+                    max,
+                } => (e.path(), *limit, *requested, *max),
+                kind => panic!("expected a limit error, got {kind:?}"),
+            },
+            result => panic!("expected a limit error, got {result:?}"),
+        }
+    }
+
+    fn chat() -> Vec<u8> {
+        Chat {
+            text: "hello".to_string(),
+            tags: vec![1, 2, 3],
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec()
+    }
+
+    #[test]
+    fn unlimited_by_default() {
+        let mut bf = Bufferfish::from(chat());
+
+        assert_eq!(bf.decode_limits(), DecodeLimits::UNLIMITED);
+        assert!(Chat::decode(&mut bf).is_ok());
+    }
+
+    #[test]
+    fn max_string_len() {
+        let limits = DecodeLimits {
+            max_string_len: 4,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            exceeded::<Chat>(&mut limited(chat(), limits)),
+            ("Chat.text".to_string(), Limit::StringLen, 5, 4)
+        );
+    }
+
+    #[test]
+    fn max_collection_len() {
+        let limits = DecodeLimits {
+            max_collection_len: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            exceeded::<Chat>(&mut limited(chat(), limits)),
+            ("Chat.tags".to_string(), Limit::CollectionLen, 3, 2)
+        );
+    }
+
+    #[test]
+    fn max_allocation_is_counted_across_nested_values() {
+        let bytes = Grid {
+            rows: vec![vec![0; 4], vec![0; 4], vec![0; 4]],
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+
+        // The outer Vec takes 3 * size_of::<Vec<u8>>() bytes, leaving room
+        // for the first two rows only.
+        let limits = DecodeLimits {
+            max_allocation: 3 * size_of::<Vec<u8>>() + 8,
+            ..Default::default()
+        };
+        let mut bf = limited(bytes.clone(), limits);
+
+        assert_eq!(
+            exceeded::<Grid>(&mut bf),
+            (
+                "Grid.rows[2]".to_string(),
+                Limit::Allocation,
+                limits.max_allocation + 4,
+                limits.max_allocation
+            )
+        );
+
+        // Each decode is counted separately.
+        let limits = DecodeLimits {
+            max_allocation: 3 * size_of::<Vec<u8>>() + 12,
+            ..Default::default()
+        };
+        let mut bf = limited([bytes.clone(), bytes].concat(), limits);
+
+        assert!(Grid::decode(&mut bf).is_ok());
+        assert!(Grid::decode(&mut bf).is_ok());
+    }
+
+    #[test]
+    fn max_depth() {
+        let bytes = Outer {
+            middle: Middle {
+                inner: Inner { value: 1 },
+            },
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+
+        let limits = DecodeLimits {
+            max_depth: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            exceeded::<Outer>(&mut limited(bytes.clone(), limits)),
+            ("Outer.middle.inner".to_string(), Limit::Depth, 3, 2)
+        );
+
+        let limits = DecodeLimits {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let mut bf = limited(bytes, limits);
+
+        assert!(Outer::decode(&mut bf).is_ok());
+    }
+
+    #[test]
+    fn core_read_paths() {
+        let limits = DecodeLimits {
+            max_string_len: 4,
+            max_collection_len: 2,
+            ..Default::default()
+        };
+
+        let mut bf = Bufferfish::new();
+        bf.write_string("hello").unwrap();
+        bf.set_decode_limits(limits);
+
+        assert!(matches!(
+            bf.read_string(),
+            Err(BufferfishError::LimitExceeded {
+                limit: Limit::StringLen,
+                ..
+            })
+        ));
+
+        let mut bf = Bufferfish::new();
+        bf.write_array(&[1u8, 2, 3]).unwrap();
+        bf.set_decode_limits(limits);
+
+        assert!(matches!(
+            bf.read_array::<u8>(),
+            Err(BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn hostile_length_prefix() {
+        // Claims u16::MAX rows, each claiming u16::MAX items, in 6 bytes.
+        let bytes = vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let mut bf = Bufferfish::from(bytes);
+
+        assert!(Grid::decode(&mut bf).is_err());
+    }
+
+    #[test]
+    fn annotated_reads() {
+        let limits = DecodeLimits {
+            max_collection_len: 2,
+            ..Default::default()
+        };
+
+        let mut bf = Bufferfish::from(chat());
+        bf.set_decode_limits(limits);
+
+        let annotation = bf.annotate::<Chat>();
+        let error = annotation.error().unwrap();
+
+        assert_eq!(error.path, "tags");
+        assert_eq!(
+            error.message,
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                requested: 3,
+                max: 2,
+            }
+            .to_string()
+        );
+
+        // A hostile length prefix is rejected before anything is allocated.
+        let mut bf = Bufferfish::from(vec![0xFF, 0xFF]);
+        bf.set_decode_limits(limits);
+
+        assert_eq!(
+            bf.annotate::<Vec<u64>>().error().unwrap().message,
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                requested: u16::MAX as usize,
+                max: 2,
+            }
+            .to_string()
+        );
+
+        let bytes = Grid {
+            rows: vec![vec![1]],
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+        let mut bf = Bufferfish::from(bytes);
+        bf.set_decode_limits(DecodeLimits {
+            max_depth: 2,
+            ..Default::default()
+        });
+
+        let error = bf.annotate::<Grid>().error().cloned().unwrap();
+
+        assert_eq!(error.path, "rows[0]");
+        assert!(error.message.starts_with("nesting depth limit exceeded"));
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug)]
+        struct ChatRef<'a> {
+            #[allow(dead_code)]
+            text: &'a str,
+            #[allow(dead_code)]
+            tags: Vec<u8>,
+        }
+
+        let bytes = chat();
+        let mut bf = BufferfishRef::new(&bytes);
+        bf.set_decode_limits(DecodeLimits {
+            max_collection_len: 2,
+            ..Default::default()
+        });
+
+        let Err(BufferfishError::Decode(e)) = ChatRef::decode_ref(&mut bf) else {
+            panic!("expected a limit error");
+        };
+
+        assert_eq!(e.path(), "ChatRef.tags");
+        assert!(matches!(
+            e.kind(),
+            BufferfishError::LimitExceeded {
+                limit: Limit::CollectionLen,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn frames_inherit_limits() {
+        let limits = DecodeLimits {
+            max_string_len: 4,
+            ..Default::default()
+        };
+
+        let mut decoder = FrameDecoder::new(LengthPrefix::U16);
+        decoder.set_decode_limits(limits);
+
+        let bytes = chat();
+        decoder.extend(&(bytes.len() as u16).to_be_bytes());
+        decoder.extend(&bytes);
+
+        let mut frame = decoder.decode().unwrap().unwrap();
+
+        assert_eq!(frame.decode_limits(), limits);
+        assert_eq!(exceeded::<Chat>(&mut frame).1, Limit::StringLen);
+    }
+}
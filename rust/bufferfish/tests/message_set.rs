@@ -0,0 +1,133 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{Bufferfish, BufferfishError, Decodable, Encodable};
+    use bufferfish_derive::{Decode, Encode, MessageSet};
+
+    #[derive(Clone, Copy)]
+    #[repr(u16)]
+    enum MessageId {
+        Join = 1,
+        Leave = 2,
+        Ping = 3,
+    }
+
+    impl From<MessageId> for u16 {
+        fn from(message_id: MessageId) -> u16 {
+            message_id as u16
+        }
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Join)]
+    struct JoinMessage {
+        id: u32,
+        username: String,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Leave)]
+    struct LeaveMessage;
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Ping)]
+    struct PingMessage {
+        timestamp: u64,
+    }
+
+    #[derive(MessageSet, Debug, PartialEq)]
+    enum ClientMessage {
+        Join(JoinMessage),
+        Leave(LeaveMessage),
+    }
+
+    #[test]
+    fn encode_delegates_to_message() {
+        let join = JoinMessage {
+            id: 1,
+            username: "Rob".to_string(),
+        };
+        let expected = join.to_bufferfish().unwrap();
+
+        let bf = ClientMessage::Join(join).to_bufferfish().unwrap();
+
+        assert_eq!(bf.as_ref(), expected.as_ref());
+    }
+
+    #[test]
+    fn decode_any_message() {
+        let mut bf = Bufferfish::new();
+        JoinMessage {
+            id: 1,
+            username: "Rob".to_string(),
+        }
+        .encode(&mut bf)
+        .unwrap();
+        LeaveMessage.encode(&mut bf).unwrap();
+
+        assert_eq!(
+            ClientMessage::decode(&mut bf).unwrap(),
+            ClientMessage::Join(JoinMessage {
+                id: 1,
+                username: "Rob".to_string(),
+            })
+        );
+        assert_eq!(
+            ClientMessage::decode(&mut bf).unwrap(),
+            ClientMessage::Leave(LeaveMessage)
+        );
+    }
+
+    #[test]
+    fn decode_unknown_message() {
+        let mut bf = PingMessage { timestamp: 0 }.to_bufferfish().unwrap();
+
+        assert!(matches!(
+            ClientMessage::decode(&mut bf),
+            Err(BufferfishError::InvalidMessageId)
+        ));
+    }
+
+    #[test]
+    fn round_trip_array_of_messages() {
+        let messages = vec![
+            ClientMessage::Leave(LeaveMessage),
+            ClientMessage::Join(JoinMessage {
+                id: 2,
+                username: "Bufferfish".to_string(),
+            }),
+        ];
+
+        let mut bf = messages.to_bufferfish().unwrap();
+
+        assert_eq!(Vec::<ClientMessage>::decode(&mut bf).unwrap(), messages);
+    }
+
+    #[test]
+    fn generic_message_set() {
+        #[derive(Encode, Decode, Debug, PartialEq)]
+        #[bufferfish(MessageId::Ping)]
+        struct Reply<T> {
+            body: T,
+        }
+
+        #[derive(MessageSet, Debug, PartialEq)]
+        enum ServerMessage<T: Encodable + Decodable> {
+            Leave(LeaveMessage),
+            Reply(Reply<T>),
+        }
+
+        let message = ServerMessage::Reply(Reply { body: 7_u8 });
+        let mut bf = message.to_bufferfish().unwrap();
+
+        assert_eq!(ServerMessage::<u8>::decode(&mut bf).unwrap(), message);
+    }
+
+    #[test]
+    fn size_hints() {
+        assert_eq!(ClientMessage::min_bytes_required(), Some(2));
+        assert_eq!(
+            ClientMessage::max_bytes_allowed(),
+            JoinMessage::max_bytes_allowed()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{Bufferfish, Decodable, DecodableRef, Encodable};
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Ping {
+        seq: u32,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Untagged {
+        ping: Ping,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(2_u16)]
+    struct Tagged {
+        ping: Ping,
+        point: Point,
+        flag: bool,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(3_u16)]
+    struct Collections {
+        pings: Vec<Ping>,
+        last: Option<Ping>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(4_u16)]
+    struct WithHeader {
+        #[bufferfish(with_header)]
+        ping: Ping,
+        other: Ping,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Event {
+        Ping(Ping),
+        Moved { ping: Ping, to: Point },
+    }
+
+    fn round_trip<T: Encodable + Decodable>(value: &T) -> (Vec<u8>, T) {
+        let bf = value.to_bufferfish().unwrap();
+        let bytes = bf.as_bytes().to_vec();
+        let decoded = T::decode(&mut Bufferfish::from(bytes.clone())).unwrap();
+
+        (bytes, decoded)
+    }
+
+    #[test]
+    fn tagged_in_untagged() {
+        let value = Untagged {
+            ping: Ping { seq: 7 },
+        };
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [0, 0, 0, 7]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn tagged_in_tagged() {
+        let value = Tagged {
+            ping: Ping { seq: 7 },
+            point: Point { x: 1, y: 2 },
+            flag: true,
+        };
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [0, 2, 0, 0, 0, 7, 1, 2, 1]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn nested_sizes_exclude_headers() {
+        assert_eq!(Ping::header_size(), 2);
+        assert_eq!(Point::header_size(), 0);
+        assert_eq!(Untagged::min_bytes_required(), Some(4));
+        assert_eq!(Untagged::max_bytes_allowed(), Some(4));
+        assert_eq!(Tagged::min_bytes_required(), Some(9));
+        assert_eq!(Tagged::max_bytes_allowed(), Some(9));
+        assert_eq!(WithHeader::min_bytes_required(), Some(12));
+
+        let mut bf = Tagged {
+            ping: Ping { seq: 7 },
+            point: Point { x: 1, y: 2 },
+            flag: true,
+        }
+        .to_bufferfish()
+        .unwrap();
+        assert!(Tagged::from_bufferfish(&mut bf).is_ok());
+    }
+
+    #[test]
+    fn tagged_in_collections() {
+        let value = Collections {
+            pings: vec![Ping { seq: 1 }, Ping { seq: 2 }],
+            last: Some(Ping { seq: 3 }),
+        };
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [0, 3, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 1, 0, 0, 0, 3]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn tagged_in_enum() {
+        let value = Event::Moved {
+            ping: Ping { seq: 9 },
+            to: Point { x: 3, y: 4 },
+        };
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [1, 0, 0, 0, 9, 3, 4]);
+        assert_eq!(decoded, value);
+
+        let value = Event::Ping(Ping { seq: 5 });
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [0, 0, 0, 0, 5]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn with_header_opt_in() {
+        let value = WithHeader {
+            ping: Ping { seq: 1 },
+            other: Ping { seq: 2 },
+        };
+        let (bytes, decoded) = round_trip(&value);
+
+        assert_eq!(bytes, [0, 4, 0, 1, 0, 0, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn with_header_rejects_wrong_message_id() {
+        let mut bf = Bufferfish::from(vec![0, 4, 0, 9, 0, 0, 0, 1, 0, 0, 0, 2]);
+
+        assert!(WithHeader::decode(&mut bf).is_err());
+    }
+
+    #[test]
+    fn array_and_option_of_tagged() {
+        let pings = vec![Ping { seq: 1 }, Ping { seq: 2 }];
+
+        let mut bf = Bufferfish::new();
+        bf.write_array(&pings).unwrap();
+        bf.write_option(&Some(Ping { seq: 3 })).unwrap();
+
+        assert_eq!(bf.read_array::<Ping>().unwrap(), pings);
+        assert_eq!(
+            bufferfish::read_option::<Ping>(&mut bf).unwrap(),
+            Some(Ping { seq: 3 })
+        );
+    }
+
+    #[test]
+    fn tagged_in_borrowed() {
+        #[derive(Encode)]
+        #[bufferfish(5_u16)]
+        struct Chat {
+            ping: Ping,
+            text: String,
+        }
+
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct PingRef {
+            seq: u32,
+        }
+
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(5_u16)]
+        struct ChatRef<'a> {
+            ping: PingRef,
+            text: &'a str,
+        }
+
+        let bf = Chat {
+            ping: Ping { seq: 1 },
+            text: "hi".to_string(),
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(
+            ChatRef::from_bytes(bf.as_bytes()).unwrap(),
+            ChatRef {
+                ping: PingRef { seq: 1 },
+                text: "hi",
+            }
+        );
+    }
+}
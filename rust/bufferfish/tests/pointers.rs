@@ -0,0 +1,171 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use std::{borrow::Cow, rc::Rc, sync::Arc};
+
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Expr {
+        Lit(i64),
+        Add(Box<Expr>, Box<Expr>),
+        Neg { inner: Box<Expr> },
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Shared {
+        name: Rc<String>,
+        scores: Arc<Vec<u16>>,
+        label: Cow<'static, str>,
+        tags: Cow<'static, [u8]>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(2_u16)]
+    struct Inner {
+        value: u8,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    struct Outer {
+        #[bufferfish(with_header)]
+        inner: Box<Inner>,
+    }
+
+    fn expr() -> Expr {
+        Expr::Add(
+            Box::new(Expr::Lit(1)),
+            Box::new(Expr::Neg {
+                inner: Box::new(Expr::Lit(2)),
+            }),
+        )
+    }
+
+    fn shared() -> Shared {
+        Shared {
+            name: Rc::new("a".to_string()),
+            scores: Arc::new(vec![3]),
+            label: Cow::Borrowed("b"),
+            tags: Cow::Owned(vec![4]),
+        }
+    }
+
+    #[test]
+    fn recursive_types() {
+        let mut bf = expr().to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+        assert_eq!(Expr::decode(&mut bf).unwrap(), expr());
+    }
+
+    #[test]
+    fn pointers_are_transparent() {
+        let mut bf = shared().to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.as_ref(),
+            &[0, 1, 0, 1, b'a', 0, 1, 0, 3, 0, 1, b'b', 0, 1, 4]
+        );
+        assert_eq!(Shared::decode(&mut bf).unwrap(), shared());
+    }
+
+    #[test]
+    fn pointers_keep_headers() {
+        let value = Outer {
+            inner: Box::new(Inner { value: 9 }),
+        };
+        let mut bf = value.to_bufferfish().unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 2, 9]);
+        assert_eq!(Outer::decode(&mut bf).unwrap(), value);
+    }
+
+    #[test]
+    fn size_bounds() {
+        // The bounds of a boxed value are not followed, as it may be
+        // recursive.
+        assert_eq!(<Box<u8>>::min_bytes_required(), None);
+        assert_eq!(Expr::max_bytes_allowed(), None);
+        assert_eq!(<Cow<str>>::max_bytes_allowed(), Some(2 + u16::MAX as usize));
+    }
+
+    #[test]
+    fn decode_depth_is_limited() {
+        // 200 nested negations.
+        let mut bytes = vec![2; 200];
+        bytes.extend([0; 9]);
+
+        let mut bf = Bufferfish::from(bytes);
+        bf.set_decode_limits(bufferfish::DecodeLimits {
+            max_depth: 100,
+            ..Default::default()
+        });
+
+        let Err(BufferfishError::Decode(e)) = Expr::decode(&mut bf) else {
+            panic!("expected a limit error");
+        };
+
+        assert!(matches!(
+            e.kind(),
+            BufferfishError::LimitExceeded {
+                limit: bufferfish::Limit::Depth,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn borrowed_fields_encode() {
+        #[derive(Encode)]
+        #[bufferfish(1_u16)]
+        struct SharedRef<'a> {
+            name: &'a str,
+            scores: &'a [u16],
+            label: Cow<'a, str>,
+            tags: &'a [u8],
+        }
+
+        let bf = SharedRef {
+            name: "a",
+            scores: &[3],
+            label: Cow::Borrowed("b"),
+            tags: &[4],
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(bf, shared().to_bufferfish().unwrap());
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct SharedRef<'a> {
+            name: Rc<String>,
+            scores: Arc<Vec<u16>>,
+            label: Cow<'a, str>,
+            tags: Cow<'a, [u8]>,
+        }
+
+        let bytes = shared().to_bufferfish().unwrap().into_vec();
+        let value = SharedRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap();
+
+        assert!(matches!(value.label, Cow::Borrowed("b")));
+        assert_eq!(
+            value,
+            SharedRef {
+                name: Rc::new("a".to_string()),
+                scores: Arc::new(vec![3]),
+                label: Cow::Borrowed("b"),
+                tags: Cow::Owned(vec![4]),
+            }
+        );
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{Bufferfish, BufferfishError, Encodable, Message, Router};
+    use bufferfish_derive::{Decode, Encode};
+
+    #[derive(Clone, Copy)]
+    #[repr(u16)]
+    enum MessageId {
+        Join = 1,
+        Leave = 2,
+        Ping = 3,
+    }
+
+    impl From<MessageId> for u16 {
+        fn from(message_id: MessageId) -> u16 {
+            message_id as u16
+        }
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Join)]
+    struct JoinMessage {
+        id: u32,
+        username: String,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Leave)]
+    struct LeaveMessage {
+        id: u32,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(MessageId::Ping)]
+    struct PingMessage;
+
+    #[derive(Debug, PartialEq)]
+    enum Event {
+        Joined(String),
+        Left(u32),
+    }
+
+    fn router() -> Router<Event> {
+        let mut router = Router::new();
+        router
+            .register(|msg: JoinMessage| Event::Joined(msg.username))
+            .register(|msg: LeaveMessage| Event::Left(msg.id));
+
+        router
+    }
+
+    #[test]
+    fn message_id_const() {
+        assert_eq!(JoinMessage::MESSAGE_ID, 1);
+        assert_eq!(LeaveMessage::MESSAGE_ID, 2);
+        assert_eq!(PingMessage::MESSAGE_ID, 3);
+    }
+
+    #[test]
+    fn dispatch_to_handler() {
+        let mut router = router();
+
+        let mut bf = JoinMessage {
+            id: 7,
+            username: "Rob".to_string(),
+        }
+        .to_bufferfish()
+        .unwrap();
+        assert_eq!(
+            router.dispatch(&mut bf).unwrap(),
+            Event::Joined("Rob".to_string())
+        );
+
+        let mut bf = LeaveMessage { id: 7 }.to_bufferfish().unwrap();
+        assert_eq!(router.dispatch(&mut bf).unwrap(), Event::Left(7));
+    }
+
+    #[test]
+    fn dispatch_consecutive_messages() {
+        let mut router = router();
+
+        let mut bf = Bufferfish::new();
+        LeaveMessage { id: 1 }.encode(&mut bf).unwrap();
+        LeaveMessage { id: 2 }.encode(&mut bf).unwrap();
+
+        assert_eq!(router.dispatch(&mut bf).unwrap(), Event::Left(1));
+        assert_eq!(router.dispatch(&mut bf).unwrap(), Event::Left(2));
+    }
+
+    #[test]
+    fn dispatch_unknown_message_id() {
+        let mut router = router();
+        let mut bf = PingMessage.to_bufferfish().unwrap();
+
+        assert!(!router.contains(PingMessage::MESSAGE_ID));
+        assert!(matches!(
+            router.dispatch(&mut bf),
+            Err(BufferfishError::InvalidMessageId)
+        ));
+    }
+
+    #[test]
+    fn dispatch_empty_buffer() {
+        let mut router = router();
+        let mut bf = Bufferfish::new();
+
+        assert!(router.dispatch(&mut bf).is_err());
+    }
+
+    #[test]
+    fn handlers_can_mutate_state() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        };
+
+        let pings = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&pings);
+
+        let mut router = Router::new();
+        router.register(move |_: PingMessage| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        for _ in 0..3 {
+            let mut bf = PingMessage.to_bufferfish().unwrap();
+            router.dispatch(&mut bf).unwrap();
+        }
+
+        assert_eq!(pings.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "a handler is already registered for message id 1")]
+    fn register_duplicate_message_id() {
+        let mut router: Router = Router::new();
+        router
+            .register(|_: JoinMessage| {})
+            .register(|_: JoinMessage| {});
+    }
+}
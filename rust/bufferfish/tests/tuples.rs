@@ -0,0 +1,120 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Score {
+        entry: (u16, String),
+        unit: (),
+        ranks: Vec<(u8, i8)>,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Event {
+        Ping(()),
+        Moved { to: (i16, i16, i16) },
+    }
+
+    fn score() -> Score {
+        Score {
+            entry: (7, "ab".to_string()),
+            unit: (),
+            ranks: vec![(1, -1)],
+        }
+    }
+
+    #[test]
+    fn writes_elements_in_order() {
+        let mut bf = score().to_bufferfish().unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 1, 0, 7, 0, 2, b'a', b'b', 0, 1, 1, 0xFF]);
+        assert_eq!(Score::decode(&mut bf).unwrap(), score());
+    }
+
+    #[test]
+    fn enum_fields() {
+        for event in [Event::Ping(()), Event::Moved { to: (1, -2, 3) }] {
+            let mut bf = event.to_bufferfish().unwrap();
+            assert_eq!(Event::decode(&mut bf).unwrap(), event);
+        }
+
+        let bf = Event::Ping(()).to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[0]);
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(Event::min_bytes_required(), Some(1));
+        assert_eq!(Event::max_bytes_allowed(), Some(1 + 6));
+        assert_eq!(<(u16, String)>::min_bytes_required(), Some(2 + 2));
+
+        // Collections only require their length prefix.
+        assert_eq!(<(Vec<Vec<u8>>, u8)>::min_bytes_required(), Some(2 + 1));
+        assert_eq!(
+            <([u128; 1 << 16], [u128; 1 << 16])>::min_bytes_required(),
+            Some(2 * (16 << 16))
+        );
+
+        // A maximum too large for a `usize` gives no maximum.
+        type Sets = [BTreeSet<BTreeSet<BTreeSet<u64>>>; 1 << 12];
+        assert_eq!(<(Sets, Sets, Sets)>::max_bytes_allowed(), None);
+    }
+
+    #[test]
+    fn reports_element_index() {
+        let mut bytes = score().to_bufferfish().unwrap().into_vec();
+        // Cut the buffer inside the entry's string.
+        bytes.truncate(7);
+
+        let Err(BufferfishError::Decode(e)) = Score::decode(&mut Bufferfish::from(bytes)) else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(e.path(), "Score.entry.1");
+        assert_eq!(e.offset(), 4);
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct ScoreRef<'a> {
+            entry: (u16, &'a str),
+            unit: (),
+            ranks: Vec<(u8, i8)>,
+        }
+
+        let bytes = score().to_bufferfish().unwrap().into_vec();
+
+        assert_eq!(
+            ScoreRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            ScoreRef {
+                entry: (7, "ab"),
+                unit: (),
+                ranks: vec![(1, -1)],
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_elements() {
+        let bf = Event::Moved { to: (1, -2, 3) }.to_bufferfish().unwrap();
+
+        assert_eq!(
+            bf.annotate::<Event>().to_string(),
+            "\
+[0..1] variant: u8 = 1 (Moved)
+[1..7] Moved.to: (i16, i16, i16)
+  [1..3] 0: i16 = 1
+  [3..5] 1: i16 = -2
+  [5..7] 2: i16 = 3
+"
+        );
+    }
+}
@@ -0,0 +1,145 @@
+#[cfg(feature = "derive")]
+mod tests {
+    use bufferfish::{
+        Bufferfish, BufferfishError, BufferfishRef, Decodable, DecodableRef, Encodable,
+    };
+    use bufferfish_derive::{Decode, DecodeRef, Encode};
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    #[bufferfish(1_u16)]
+    struct Move {
+        #[bufferfish(varint)]
+        entity: u32,
+        #[bufferfish(varint)]
+        dx: i16,
+        #[bufferfish(varint)]
+        tick: u64,
+        speed: u8,
+    }
+
+    #[derive(Encode, Decode, Debug, PartialEq)]
+    enum Event {
+        Hit(#[bufferfish(varint)] i32),
+        Heal {
+            #[bufferfish(varint)]
+            amount: u16,
+        },
+    }
+
+    #[test]
+    fn encodes_small_values_compactly() {
+        let bf = Move {
+            entity: 5,
+            dx: -1,
+            tick: 300,
+            speed: 9,
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(bf.as_ref(), &[0, 1, 5, 1, 0xAC, 0x02, 9]);
+    }
+
+    #[test]
+    fn roundtrips_extremes() {
+        let value = Move {
+            entity: u32::MAX,
+            dx: i16::MIN,
+            tick: u64::MAX,
+            speed: 0,
+        };
+        let mut bf = value.to_bufferfish().unwrap();
+
+        assert_eq!(bf.len(), 2 + 5 + 3 + 10 + 1);
+        assert_eq!(Move::decode(&mut bf).unwrap(), value);
+    }
+
+    #[test]
+    fn size_bounds() {
+        assert_eq!(Move::min_bytes_required(), Some(2 + 1 + 1 + 1 + 1));
+        assert_eq!(Move::max_bytes_allowed(), Some(2 + 5 + 3 + 10 + 1));
+    }
+
+    #[test]
+    fn enum_fields() {
+        for event in [Event::Hit(-70000), Event::Heal { amount: 200 }] {
+            let mut bf = event.to_bufferfish().unwrap();
+            assert_eq!(Event::decode(&mut bf).unwrap(), event);
+        }
+
+        let bf = Event::Heal { amount: 1 }.to_bufferfish().unwrap();
+        assert_eq!(bf.as_ref(), &[1, 1]);
+    }
+
+    #[test]
+    fn reports_overflow_with_field_path() {
+        // The entity varint runs past the 5 bytes a u32 may take.
+        let bytes = vec![0, 1, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+
+        let Err(BufferfishError::Decode(e)) = Move::decode(&mut Bufferfish::from(bytes)) else {
+            panic!("expected a decode error");
+        };
+
+        assert_eq!(e.path(), "Move.entity");
+        assert_eq!(e.offset(), 2);
+        assert!(matches!(e.kind(), BufferfishError::InvalidVarint));
+    }
+
+    #[test]
+    fn borrowed_reads() {
+        #[derive(DecodeRef, Debug, PartialEq)]
+        #[bufferfish(1_u16)]
+        struct MoveRef {
+            #[bufferfish(varint)]
+            entity: u32,
+            #[bufferfish(varint)]
+            dx: i16,
+            #[bufferfish(varint)]
+            tick: u64,
+            speed: u8,
+        }
+
+        let bytes = Move {
+            entity: 70000,
+            dx: -300,
+            tick: 1,
+            speed: 2,
+        }
+        .to_bufferfish()
+        .unwrap()
+        .into_vec();
+
+        assert_eq!(
+            MoveRef::decode_ref(&mut BufferfishRef::new(&bytes)).unwrap(),
+            MoveRef {
+                entity: 70000,
+                dx: -300,
+                tick: 1,
+                speed: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn annotates_varint_fields() {
+        let bf = Move {
+            entity: 5,
+            dx: -1,
+            tick: 300,
+            speed: 9,
+        }
+        .to_bufferfish()
+        .unwrap();
+
+        assert_eq!(
+            bf.annotate::<Move>().to_string(),
+            "\
+[0..2] message_id = 1
+[2..3] entity: u32 = 5
+[3..4] dx: i16 = -1
+[4..6] tick: u64 = 300
+[6..7] speed: u8 = 9
+"
+        );
+    }
+}
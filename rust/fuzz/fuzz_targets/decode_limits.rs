@@ -0,0 +1,55 @@
+#![no_main]
+
+use bufferfish::{
+    Bufferfish, BufferfishRef, Decodable, DecodableRef, Decode, DecodeLimits, DecodeRef,
+};
+use libfuzzer_sys::fuzz_target;
+
+const LIMITS: DecodeLimits = DecodeLimits {
+    max_allocation: 4096,
+    max_collection_len: 64,
+    max_string_len: 256,
+    max_depth: 8,
+};
+
+#[derive(Decode)]
+#[allow(dead_code)]
+struct Lobby {
+    name: String,
+    players: Vec<Player>,
+    grid: Vec<Vec<u16>>,
+    motd: Option<String>,
+}
+
+#[derive(Decode)]
+#[allow(dead_code)]
+struct Player {
+    id: u32,
+    tags: Vec<String>,
+    state: State,
+}
+
+#[derive(Decode)]
+#[allow(dead_code)]
+enum State {
+    Idle,
+    Moving { path: Vec<u8> },
+}
+
+#[derive(DecodeRef)]
+#[allow(dead_code)]
+struct LobbyRef<'a> {
+    name: &'a str,
+    tags: Vec<&'a str>,
+    grid: Vec<Vec<u16>>,
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut bf = Bufferfish::from(data);
+    bf.set_decode_limits(LIMITS);
+    let _ = Lobby::decode(&mut bf);
+
+    let mut bf = BufferfishRef::new(data);
+    bf.set_decode_limits(LIMITS);
+    let _ = LobbyRef::decode_ref(&mut bf);
+});
@@ -273,6 +273,98 @@ test("should read i128", () => {
     expect(bf.readInt128()).toEqual(170141183460469231731687303715884105727n)
 })
 
+test("should write varints", () => {
+    const bf = new Bufferfish()
+    bf.writeVarintUint16(0)
+    bf.writeVarintUint32(127)
+    bf.writeVarintUint64(300n)
+    bf.writeVarintInt16(-1)
+    bf.writeVarintInt32(64)
+
+    expect(bf.bytes()).toEqual(
+        new Uint8Array([0x00, 0x7f, 0xac, 0x02, 0x01, 0x80, 0x01]),
+    )
+})
+
+test("should read varints", () => {
+    const bf = new Bufferfish()
+    bf.writeVarintUint16(65535)
+    bf.writeVarintUint32(4294967295)
+    bf.writeVarintUint64(18446744073709551615n)
+    bf.writeVarintInt16(-32768)
+    bf.writeVarintInt32(2147483647)
+    bf.writeVarintInt64(-9223372036854775808n)
+
+    expect(bf.readVarintUint16()).toEqual(65535)
+    expect(bf.readVarintUint32()).toEqual(4294967295)
+    expect(bf.readVarintUint64()).toEqual(18446744073709551615n)
+    expect(bf.readVarintInt16()).toEqual(-32768)
+    expect(bf.readVarintInt32()).toEqual(2147483647)
+    expect(bf.readVarintInt64()).toEqual(-9223372036854775808n)
+    expect(bf.readVarintUint32()).toEqual(
+        Error("attempted to read past the end of the Bufferfish"),
+    )
+})
+
+test("should return error on invalid varint", () => {
+    const tooLarge = new Bufferfish(new Uint8Array([0x80, 0x80, 0x04]).buffer)
+    const tooLong = new Bufferfish(new Uint8Array(6).fill(0x80).buffer)
+
+    expect(tooLarge.readVarintUint16()).toEqual(
+        Error("varint is too long or its value is out of range"),
+    )
+    expect(tooLong.readVarintUint32()).toEqual(
+        Error("varint is too long or its value is out of range"),
+    )
+})
+
+test("should write f32", () => {
+    const bf = new Bufferfish()
+    bf.writeFloat32(0)
+    bf.writeFloat32(1.5)
+    bf.writeFloat32(-2.25)
+
+    expect(bf.bytes()).toEqual(
+        new Uint8Array([0, 0, 0, 0, 63, 192, 0, 0, 192, 16, 0, 0]),
+    )
+})
+
+test("should write f64", () => {
+    const bf = new Bufferfish()
+    bf.writeFloat64(0)
+    bf.writeFloat64(1.5)
+    bf.writeFloat64(-2.25)
+
+    expect(bf.bytes()).toEqual(
+        new Uint8Array([
+            0, 0, 0, 0, 0, 0, 0, 0, 63, 248, 0, 0, 0, 0, 0, 0, 192, 2, 0, 0, 0,
+            0, 0, 0,
+        ]),
+    )
+})
+
+test("should read f32", () => {
+    const bf = new Bufferfish()
+    bf.writeFloat32(0)
+    bf.writeFloat32(1.5)
+    bf.writeFloat32(-2.25)
+
+    expect(bf.readFloat32()).toEqual(0)
+    expect(bf.readFloat32()).toEqual(1.5)
+    expect(bf.readFloat32()).toEqual(-2.25)
+})
+
+test("should read f64", () => {
+    const bf = new Bufferfish()
+    bf.writeFloat64(0)
+    bf.writeFloat64(1.5)
+    bf.writeFloat64(Number.MAX_VALUE)
+
+    expect(bf.readFloat64()).toEqual(0)
+    expect(bf.readFloat64()).toEqual(1.5)
+    expect(bf.readFloat64()).toEqual(Number.MAX_VALUE)
+})
+
 test("should read from reset position", () => {
     const bf = new Bufferfish()
     bf.writeUint8(0)
@@ -541,6 +633,113 @@ test("should return error for arrays exceeding maximum length", () => {
     )
 })
 
+test("should write and read with a u32 or varint length prefix", () => {
+    const bf = new Bufferfish()
+    bf.setLengthPrefix("u32")
+    bf.writeString("hi")
+    bf.setLengthPrefix("varint")
+    bf.writeArray([7], (n) => bf.writeUint8(n))
+
+    expect(bf.bytes()).toEqual(new Uint8Array([0, 0, 0, 2, 104, 105, 1, 7]))
+
+    bf.setLengthPrefix("u32")
+    expect(bf.readString()).toEqual("hi")
+    bf.setLengthPrefix("varint")
+    expect(bf.readArray(() => bf.readUint8())).toEqual([7])
+})
+
+test("should write strings over 64KiB with a wider length prefix", () => {
+    const bf = new Bufferfish()
+    bf.setMaxCapacity(0)
+
+    const hugeString = "a".repeat(70000)
+
+    expect(bf.writeString(hugeString)).toEqual(
+        Error("length 70000 exceeds maximum size of 65535"),
+    )
+
+    bf.setLengthPrefix("varint")
+
+    expect(bf.writeString(hugeString)).toBeUndefined()
+    expect(bf.length()).toEqual(3 + 70000)
+    expect(bf.readString()).toEqual(hugeString)
+})
+
+test("should restore the length prefix after withLengthPrefix", () => {
+    const bf = new Bufferfish()
+    bf.withLengthPrefix("u32", () => bf.writeString("a"))
+    bf.writeString("b")
+
+    expect(bf.bytes()).toEqual(new Uint8Array([0, 0, 0, 1, 97, 0, 1, 98]))
+    expect(() =>
+        bf.withLengthPrefix("varint", () => {
+            throw new Error("failed")
+        }),
+    ).toThrow("failed")
+    expect(bf.withLengthPrefix("u32", () => bf.readString())).toEqual("a")
+    expect(bf.readString()).toEqual("b")
+})
+
+test("should write and read maps", () => {
+    const bf = new Bufferfish()
+    const map = new Map([
+        [1, "a"],
+        [2, "b"],
+    ])
+
+    bf.writeMap(
+        map,
+        (key) => bf.writeUint8(key),
+        (value) => bf.writeString(value),
+    )
+
+    expect(bf.bytes()).toEqual(new Uint8Array([0, 2, 1, 0, 1, 97, 2, 0, 1, 98]))
+    expect(bf.readMap(() => bf.readUint8(), () => bf.readString())).toEqual(map)
+})
+
+test("should write and read sets", () => {
+    const bf = new Bufferfish()
+
+    bf.writeSet(new Set([3, 1]), (n) => bf.writeUint16(n))
+
+    expect(bf.bytes()).toEqual(new Uint8Array([0, 2, 0, 3, 0, 1]))
+    expect(bf.readSet(() => bf.readUint16())).toEqual(new Set([3, 1]))
+})
+
+test("should write and read fixed-size arrays", () => {
+    const bf = new Bufferfish()
+
+    bf.writeFixedBytes(new Uint8Array([1, 2, 3]), 3)
+    bf.writeFixedArray([4, 5], 2, (n) => bf.writeUint16(n))
+
+    expect(bf.bytes()).toEqual(new Uint8Array([1, 2, 3, 0, 4, 0, 5]))
+    expect(bf.readFixedBytes(3)).toEqual(new Uint8Array([1, 2, 3]))
+    expect(bf.readFixedArray(2, () => bf.readUint16())).toEqual([4, 5])
+    expect(bf.readFixedBytes(1)).toBeInstanceOf(Error)
+})
+
+test("should write and read byte blobs", () => {
+    const bf = new Bufferfish()
+
+    bf.writeBytes(new Uint8Array([1, 2, 3]))
+    bf.writeArray([1, 2, 3], (n) => bf.writeUint8(n))
+
+    expect(bf.bytes()).toEqual(new Uint8Array([0, 3, 1, 2, 3, 0, 3, 1, 2, 3]))
+    expect(bf.readBytes()).toEqual(new Uint8Array([1, 2, 3]))
+    expect(bf.readBytes()).toEqual(new Uint8Array([1, 2, 3]))
+    expect(bf.readBytes()).toBeInstanceOf(Error)
+})
+
+test("should reject fixed-size arrays of the wrong length", () => {
+    const bf = new Bufferfish()
+
+    expect(bf.writeFixedBytes(new Uint8Array([1, 2]), 3)).toBeInstanceOf(Error)
+    expect(
+        bf.writeFixedArray([1], 2, (n) => bf.writeUint8(n)),
+    ).toBeInstanceOf(Error)
+    expect(bf.bytes()).toEqual(new Uint8Array([]))
+})
+
 test("should read an array of objects implementing read methods", () => {
     const arr = [
         { id: 1, name: "Alice", key: { inner: 1 } },
@@ -1,4 +1,11 @@
 const OVERFLOW_ERR = "attempted to read past the end of the Bufferfish"
+const VARINT_ERR = "varint is too long or its value is out of range"
+
+/**
+ * How string and collection lengths are written to and read from a
+ * Bufferfish. This must match the `LengthPrefix` used on the Rust side.
+ */
+export type LengthPrefix = "u16" | "u32" | "varint"
 
 /**
  * A wrapper around Uint8Array that provides a simple API for reading and
@@ -10,6 +17,7 @@ export class Bufferfish {
     private position: number
     private reading: boolean
     private maxCapacity: number
+    private lengthPrefix: LengthPrefix
 
     private textDecoder: TextDecoder | undefined
     private textEncoder: TextEncoder | undefined
@@ -19,6 +27,7 @@ export class Bufferfish {
         this.position = 0
         this.reading = false
         this.maxCapacity = 1024
+        this.lengthPrefix = "u16"
 
         this.textDecoder = undefined
         this.textEncoder = undefined
@@ -83,6 +92,38 @@ export class Bufferfish {
         this.maxCapacity = capacity
     }
 
+    /**
+     * Sets how string and collection lengths are written to and read from the
+     * buffer. Defaults to "u16", which limits them to 65535 bytes or items.
+     */
+    public setLengthPrefix = (prefix: LengthPrefix): void => {
+        this.lengthPrefix = prefix
+    }
+
+    /**
+     * Calls `fn` using the given length prefix for string and collection
+     * lengths, restoring the previous prefix afterwards.
+     */
+    public withLengthPrefix = <T>(prefix: LengthPrefix, fn: () => T): T => {
+        const previous = this.lengthPrefix
+        this.lengthPrefix = prefix
+
+        try {
+            return fn()
+        } finally {
+            this.lengthPrefix = previous
+        }
+    }
+
+    /**
+     * Returns the largest length the buffer's length prefix can represent.
+     *
+     * This should only be called by the library.
+     */
+    private maxLength = (): number => {
+        return this.lengthPrefix === "u16" ? 65535 : 4294967295
+    }
+
     /**
      * Returns true if the buffer is empty.
      */
@@ -371,6 +412,154 @@ export class Bufferfish {
         }
     }
 
+    /**
+     * Writes an unsigned integer to the buffer as a LEB128 varint, taking one
+     * byte for every 7 bits of the value.
+     *
+     * This should only be called by the library.
+     */
+    private writeVarint = (value: bigint): void | Error => {
+        const bytes: Array<number> = []
+
+        do {
+            const byte = Number(value & 0x7fn)
+            value >>= 7n
+            bytes.push(value === 0n ? byte : byte | 0x80)
+        } while (value !== 0n)
+
+        const err = this.write(new Uint8Array(bytes))
+        if (err instanceof Error) {
+            return err
+        }
+    }
+
+    /**
+     * Writes a signed integer to the buffer as a zigzag encoded varint, so
+     * that values close to zero take few bytes whether positive or negative.
+     *
+     * This should only be called by the library.
+     */
+    private writeZigzagVarint = (value: bigint): void | Error => {
+        return this.writeVarint(
+            BigInt.asUintN(64, (value << 1n) ^ (value >> 63n)),
+        )
+    }
+
+    /**
+     * Writes a u16 to the buffer as a varint (1-3 bytes).
+     *
+     * Returns an error if the value is out of range (0-65535).
+     */
+    public writeVarintUint16 = (value: number): void | Error => {
+        if (value > 65535 || value < 0) {
+            return new Error(`value ${value} must be between 0 and 65535`)
+        }
+
+        return this.writeVarint(BigInt(Math.trunc(value)))
+    }
+
+    /**
+     * Writes a u32 to the buffer as a varint (1-5 bytes).
+     *
+     * Returns an error if the value is out of range (0-4294967295).
+     */
+    public writeVarintUint32 = (value: number): void | Error => {
+        if (value > 4294967295 || value < 0) {
+            return new Error(`value ${value} must be between 0 and 4294967295`)
+        }
+
+        return this.writeVarint(BigInt(Math.trunc(value)))
+    }
+
+    /**
+     * Writes a u64 to the buffer as a varint (1-10 bytes).
+     *
+     * Returns an error if the value is out of range (0-18446744073709551615).
+     */
+    public writeVarintUint64 = (value: bigint): void | Error => {
+        if (value > BigInt("18446744073709551615") || value < BigInt(0)) {
+            return new Error(
+                `value ${value} must be between 0 and 18446744073709551615`,
+            )
+        }
+
+        return this.writeVarint(value)
+    }
+
+    /**
+     * Writes an i16 to the buffer as a zigzag encoded varint (1-3 bytes).
+     *
+     * Returns an error if the value is out of range (-32768-32767).
+     */
+    public writeVarintInt16 = (value: number): void | Error => {
+        if (value > 32767 || value < -32768) {
+            return new Error(`value ${value} must be between -32768 and 32767`)
+        }
+
+        return this.writeZigzagVarint(BigInt(Math.trunc(value)))
+    }
+
+    /**
+     * Writes an i32 to the buffer as a zigzag encoded varint (1-5 bytes).
+     *
+     * Returns an error if the value is out of range (-2147483648-2147483647).
+     */
+    public writeVarintInt32 = (value: number): void | Error => {
+        if (value > 2147483647 || value < -2147483648) {
+            return new Error(
+                `value ${value} must be between -2147483648 and 2147483647`,
+            )
+        }
+
+        return this.writeZigzagVarint(BigInt(Math.trunc(value)))
+    }
+
+    /**
+     * Writes an i64 to the buffer as a zigzag encoded varint (1-10 bytes).
+     *
+     * Returns an error if the value is out of range (-9223372036854775808-9223372036854775807).
+     */
+    public writeVarintInt64 = (value: bigint): void | Error => {
+        if (
+            value > BigInt("9223372036854775807") ||
+            value < BigInt("-9223372036854775808")
+        ) {
+            return new Error(
+                `value ${value} must be between -9223372036854775808 and 9223372036854775807`,
+            )
+        }
+
+        return this.writeZigzagVarint(value)
+    }
+
+    /**
+     * Writes an f32 to the buffer as four bytes.
+     */
+    public writeFloat32 = (value: number): void | Error => {
+        const slice: Uint8Array = new Uint8Array(4)
+        const view = new DataView(slice.buffer)
+        view.setFloat32(0, value)
+
+        const err = this.write(slice)
+        if (err instanceof Error) {
+            return err
+        }
+    }
+
+    /**
+     * Writes an f64 to the buffer as eight bytes.
+     */
+    public writeFloat64 = (value: number): void | Error => {
+        const slice: Uint8Array = new Uint8Array(8)
+        const view = new DataView(slice.buffer)
+        view.setFloat64(0, value)
+
+        const err = this.write(slice)
+        if (err instanceof Error) {
+            return err
+        }
+    }
+
     /**
      * Writes a boolean to the buffer as one byte.
      */
@@ -406,16 +595,38 @@ export class Bufferfish {
         }
     }
 
+    /**
+     * Writes a string or collection length using the buffer's length prefix.
+     *
+     * Returns an error if the length prefix can't represent the length.
+     */
+    public writeLength = (length: number): void | Error => {
+        if (length > this.maxLength()) {
+            return new Error(
+                `length ${length} exceeds maximum size of ${this.maxLength()}`,
+            )
+        }
+
+        switch (this.lengthPrefix) {
+            case "u16":
+                return this.writeUint16(length)
+            case "u32":
+                return this.writeUint32(length)
+            case "varint":
+                return this.writeVarintUint32(length)
+        }
+    }
+
     /**
      * Writes a unicode string literal to the buffer. It will be prefixed with
-     * its length in bytes as a u16 (two bytes), and each character will be 1 to
-     * 4-bytes, whichever is the smallest it can fit into.
+     * its length in bytes, as a u16 (two bytes) by default, and each character
+     * will be 1 to 4-bytes, whichever is the smallest it can fit into.
      */
     public writeString = (value: string): void | Error => {
         if (!this.textEncoder) this.textEncoder = new TextEncoder()
         const slice: Uint8Array = this.textEncoder.encode(value)
 
-        const err = this.writeUint16(slice.length)
+        const err = this.writeLength(slice.length)
         if (err instanceof Error) {
             return err
         }
@@ -437,21 +648,53 @@ export class Bufferfish {
         }
     }
 
+    /**
+     * Writes a run of bytes to the buffer, as for a Rust `ByteBuf`. It will be
+     * prefixed with its length, as a u16 (two bytes) by default.
+     */
+    public writeBytes = (value: Uint8Array): void | Error => {
+        const err = this.writeLength(value.length)
+        if (err instanceof Error) {
+            return err
+        }
+
+        return this.writeRawBytes(value)
+    }
+
+    /**
+     * Writes exactly `length` raw bytes to the buffer, without a length
+     * prefix, as for a Rust `[u8; N]`.
+     *
+     * Returns an error if the value is not `length` bytes long.
+     */
+    public writeFixedBytes = (
+        value: Uint8Array,
+        length: number,
+    ): void | Error => {
+        if (value.length !== length) {
+            return new Error(
+                `expected ${length} bytes for a fixed-size array, got ${value.length}`,
+            )
+        }
+
+        return this.writeRawBytes(value)
+    }
+
     /**
      * Writes an array of elements to the buffer.
-     * The array is prefixed with its length as a u16 (two bytes).
+     * The array is prefixed with its length, as a u16 (two bytes) by default.
      */
     public writeArray = <T>(
         values: Array<T>,
         writeFn: (value: T) => void | Error,
     ): void | Error => {
-        if (values.length > 65535) {
+        if (values.length > this.maxLength()) {
             return new Error(
-                `array length ${values.length} exceeds maximum size of 65535`,
+                `array length ${values.length} exceeds maximum size of ${this.maxLength()}`,
             )
         }
 
-        const err = this.writeUint16(values.length)
+        const err = this.writeLength(values.length)
         if (err instanceof Error) {
             return err
         }
@@ -464,6 +707,68 @@ export class Bufferfish {
         }
     }
 
+    /**
+     * Writes exactly `length` elements to the buffer, without a length
+     * prefix, as for a Rust `[T; N]`.
+     *
+     * Returns an error if the array does not have `length` elements.
+     */
+    public writeFixedArray = <T>(
+        values: Array<T>,
+        length: number,
+        writeFn: (value: T) => void | Error,
+    ): void | Error => {
+        if (values.length !== length) {
+            return new Error(
+                `expected ${length} elements for a fixed-size array, got ${values.length}`,
+            )
+        }
+
+        for (const value of values) {
+            const err = writeFn(value)
+            if (err instanceof Error) {
+                return err
+            }
+        }
+    }
+
+    /**
+     * Writes a map to the buffer as its size, followed by each key and its
+     * value. The size is prefixed like an array length.
+     */
+    public writeMap = <K, V>(
+        values: Map<K, V>,
+        writeKeyFn: (key: K) => void | Error,
+        writeValueFn: (value: V) => void | Error,
+    ): void | Error => {
+        const err = this.writeLength(values.size)
+        if (err instanceof Error) {
+            return err
+        }
+
+        for (const [key, value] of values) {
+            const keyErr = writeKeyFn(key)
+            if (keyErr instanceof Error) {
+                return keyErr
+            }
+
+            const valueErr = writeValueFn(value)
+            if (valueErr instanceof Error) {
+                return valueErr
+            }
+        }
+    }
+
+    /**
+     * Writes a set to the buffer, in the same format as an array.
+     */
+    public writeSet = <T>(
+        values: Set<T>,
+        writeFn: (value: T) => void | Error,
+    ): void | Error => {
+        return this.writeArray(Array.from(values), writeFn)
+    }
+
     /**
      * Attempts to read a u8 from the buffer.
      */
@@ -680,6 +985,158 @@ export class Bufferfish {
         return value
     }
 
+    /**
+     * Attempts to read a varint for an unsigned integer of the given number of
+     * bits from the buffer.
+     *
+     * This should only be called by the library.
+     */
+    private readVarint = (bits: number): bigint | Error => {
+        this.startReading()
+
+        const maxLength = Math.ceil(bits / 7)
+        let value = 0n
+
+        for (let i = 0; i < maxLength; i++) {
+            const byte = this.inner[this.position + i]
+            if (byte === undefined) {
+                return new Error(OVERFLOW_ERR)
+            }
+
+            const shift = 7 * i
+            const payload = BigInt(byte & 0x7f)
+            if (bits - shift < 7 && payload >> BigInt(bits - shift) !== 0n) {
+                return new Error(VARINT_ERR)
+            }
+
+            value |= payload << BigInt(shift)
+
+            if ((byte & 0x80) === 0) {
+                this.position += i + 1
+                return value
+            }
+        }
+
+        return new Error(VARINT_ERR)
+    }
+
+    /**
+     * Attempts to read a zigzag encoded varint for a signed integer of the
+     * given number of bits from the buffer.
+     *
+     * This should only be called by the library.
+     */
+    private readZigzagVarint = (bits: number): bigint | Error => {
+        const valueOrError = this.readVarint(bits)
+        if (valueOrError instanceof Error) {
+            return valueOrError
+        }
+
+        return (valueOrError >> 1n) ^ -(valueOrError & 1n)
+    }
+
+    /**
+     * Attempts to read a varint u16 from the buffer.
+     */
+    public readVarintUint16 = (): number | Error => {
+        const valueOrError = this.readVarint(16)
+        if (valueOrError instanceof Error) {
+            return valueOrError
+        }
+
+        return Number(valueOrError)
+    }
+
+    /**
+     * Attempts to read a varint u32 from the buffer.
+     */
+    public readVarintUint32 = (): number | Error => {
+        const valueOrError = this.readVarint(32)
+        if (valueOrError instanceof Error) {
+            return valueOrError
+        }
+
+        return Number(valueOrError)
+    }
+
+    /**
+     * Attempts to read a varint u64 from the buffer.
+     */
+    public readVarintUint64 = (): bigint | Error => {
+        return this.readVarint(64)
+    }
+
+    /**
+     * Attempts to read a zigzag encoded varint i16 from the buffer.
+     */
+    public readVarintInt16 = (): number | Error => {
+        const valueOrError = this.readZigzagVarint(16)
+        if (valueOrError instanceof Error) {
+            return valueOrError
+        }
+
+        return Number(valueOrError)
+    }
+
+    /**
+     * Attempts to read a zigzag encoded varint i32 from the buffer.
+     */
+    public readVarintInt32 = (): number | Error => {
+        const valueOrError = this.readZigzagVarint(32)
+        if (valueOrError instanceof Error) {
+            return valueOrError
+        }
+
+        return Number(valueOrError)
+    }
+
+    /**
+     * Attempts to read a zigzag encoded varint i64 from the buffer.
+     */
+    public readVarintInt64 = (): bigint | Error => {
+        return this.readZigzagVarint(64)
+    }
+
+    /**
+     * Attempts to read an f32 from the buffer.
+     */
+    public readFloat32 = (): number | Error => {
+        this.startReading()
+
+        if (this.position + 4 > this.inner.length) {
+            return new Error(OVERFLOW_ERR)
+        }
+
+        const value = new DataView(
+            this.inner.buffer,
+            this.position,
+            4,
+        ).getFloat32(0)
+        this.position += 4
+
+        return value
+    }
+
+    /**
+     * Attempts to read an f64 from the buffer.
+     */
+    public readFloat64 = (): number | Error => {
+        this.startReading()
+
+        if (this.position + 8 > this.inner.length) {
+            return new Error(OVERFLOW_ERR)
+        }
+
+        const value = new DataView(
+            this.inner.buffer,
+            this.position,
+            8,
+        ).getFloat64(0)
+        this.position += 8
+
+        return value
+    }
+
     /**
      * Attempts to read a bool from the buffer.
      */
@@ -717,11 +1174,26 @@ export class Bufferfish {
         return bools
     }
 
+    /**
+     * Attempts to read a string or collection length using the buffer's
+     * length prefix.
+     */
+    public readLength = (): number | Error => {
+        switch (this.lengthPrefix) {
+            case "u16":
+                return this.readUint16()
+            case "u32":
+                return this.readUint32()
+            case "varint":
+                return this.readVarintUint32()
+        }
+    }
+
     /**
      * Attempts to read a variable length string from the buffer.
      */
     public readString = (): string | Error => {
-        const lengthOrError = this.readUint16()
+        const lengthOrError = this.readLength()
         if (lengthOrError instanceof Error) {
             return lengthOrError
         }
@@ -741,16 +1213,56 @@ export class Bufferfish {
         return value
     }
 
+    /**
+     * Attempts to read a length-prefixed run of bytes from the buffer, as
+     * written for a Rust `ByteBuf` or `Vec<u8>`.
+     */
+    public readBytes = (): Uint8Array | Error => {
+        const lengthOrError = this.readLength()
+        if (lengthOrError instanceof Error) {
+            return lengthOrError
+        }
+
+        return this.readFixedBytes(lengthOrError)
+    }
+
+    /**
+     * Attempts to read exactly `length` raw bytes from the buffer, as written
+     * for a Rust `[u8; N]`.
+     */
+    public readFixedBytes = (length: number): Uint8Array | Error => {
+        this.startReading()
+
+        if (this.position + length > this.inner.length) {
+            return new Error(OVERFLOW_ERR)
+        }
+
+        const value = this.inner.slice(this.position, this.position + length)
+        this.position += length
+
+        return value
+    }
+
     /**
      * Attempts to read a variable-length array of elements from the buffer.
      */
     public readArray = <T>(readFn: () => T | Error): Array<T> | Error => {
-        const lengthOrError = this.readUint16()
+        const lengthOrError = this.readLength()
         if (lengthOrError instanceof Error) {
             return lengthOrError
         }
 
-        const length = lengthOrError
+        return this.readFixedArray(lengthOrError, readFn)
+    }
+
+    /**
+     * Attempts to read exactly `length` elements from the buffer, with no
+     * length prefix, as written for a Rust `[T; N]`.
+     */
+    public readFixedArray = <T>(
+        length: number,
+        readFn: () => T | Error,
+    ): Array<T> | Error => {
         const values: Array<T> = new Array(length)
 
         try {
@@ -766,4 +1278,51 @@ export class Bufferfish {
             return error instanceof Error ? error : new Error(String(error))
         }
     }
+
+    /**
+     * Attempts to read a map from the buffer. If a key is repeated, the last
+     * value read for it is kept.
+     */
+    public readMap = <K, V>(
+        readKeyFn: () => K | Error,
+        readValueFn: () => V | Error,
+    ): Map<K, V> | Error => {
+        const lengthOrError = this.readLength()
+        if (lengthOrError instanceof Error) {
+            return lengthOrError
+        }
+
+        const values = new Map<K, V>()
+
+        try {
+            for (let i = 0; i < lengthOrError; i++) {
+                const keyOrError = readKeyFn()
+                if (keyOrError instanceof Error) {
+                    return keyOrError
+                }
+
+                const valueOrError = readValueFn()
+                if (valueOrError instanceof Error) {
+                    return valueOrError
+                }
+
+                values.set(keyOrError as K, valueOrError as V)
+            }
+            return values
+        } catch (error) {
+            return error instanceof Error ? error : new Error(String(error))
+        }
+    }
+
+    /**
+     * Attempts to read a set from the buffer, in the same format as an array.
+     */
+    public readSet = <T>(readFn: () => T | Error): Set<T> | Error => {
+        const valuesOrError = this.readArray(readFn)
+        if (valuesOrError instanceof Error) {
+            return valuesOrError
+        }
+
+        return new Set(valuesOrError)
+    }
 }